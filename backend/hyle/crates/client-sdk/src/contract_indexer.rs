@@ -12,7 +12,7 @@ use axum::{
 use borsh::{BorshDeserialize, BorshSerialize};
 use sdk::{
     guest, info, Blob, BlobIndex, BlobTransaction, ContractInput, ContractName, Digestable, Hashed,
-    HyleContract, TxContext, TxId,
+    HyleContract, StateDigest, TxContext, TxId,
 };
 use utoipa::openapi::OpenApi;
 
@@ -47,6 +47,12 @@ where
         store: ContractHandlerStore<Self>,
     ) -> impl std::future::Future<Output = (Router<()>, OpenApi)> + std::marker::Send;
 
+    /// State of the indexer when the contract is registered with `state_digest`.
+    /// Contracts whose digest is a hash of their state can't be decoded and start from the default state.
+    fn initial_state(_state_digest: StateDigest) -> Result<Self> {
+        Ok(Self::default())
+    }
+
     fn handle(
         tx: &BlobTransaction,
         index: BlobIndex,
@@ -22,6 +22,7 @@ pub fn register_hyle_contract(
             verifier,
             program_id,
             state_digest,
+            timeout_window: None,
         },
         None,
         None,
@@ -52,6 +52,11 @@ impl NodeApiHttpClient {
         .await
     }
 
+    pub async fn get_fee_price(&self) -> Result<APIFeePrice> {
+        self.get("v1/consensus/fee_price", "getting fee price")
+            .await
+    }
+
     pub async fn get_node_info(&self) -> Result<NodeInfo> {
         self.get("v1/info", "getting node info").await
     }
@@ -9,7 +9,7 @@ use futures::{
     stream::{SplitSink, SplitStream},
     SinkExt, StreamExt,
 };
-use sdk::Transaction;
+use sdk::{api::APITxRejection, Transaction, TxHash};
 use tokio::{
     net::{TcpListener, TcpStream},
     sync::mpsc::{Receiver, Sender},
@@ -481,8 +481,41 @@ pub use tcp_client_server;
 pub enum TcpServerMessage {
     NewTx(Transaction),
 }
+/// Answer of the node to a transaction sent by a TCP client.
+/// The answer is encoded apart from its version, so that clients can skip the versions they don't know
+/// instead of failing to decode the stream.
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize, Eq, PartialEq)]
-pub struct TcpServerResponse;
+pub struct TcpServerResponse {
+    pub version: u16,
+    pub answer: Vec<u8>,
+}
+
+/// Whether the mempool admitted a transaction, or the reason it refused it
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize, Eq, PartialEq)]
+pub enum TcpTxAnswer {
+    Accepted(TxHash),
+    Rejected(APITxRejection),
+}
+
+impl TcpServerResponse {
+    /// Version of the [`TcpTxAnswer`] encoding
+    pub const VERSION: u16 = 1;
+
+    pub fn new(answer: &TcpTxAnswer) -> Result<Self> {
+        Ok(TcpServerResponse {
+            version: Self::VERSION,
+            answer: borsh::to_vec(answer).context("Encoding TcpTxAnswer")?,
+        })
+    }
+
+    /// The answer, or None if it is encoded in a version this client doesn't know
+    pub fn answer(&self) -> Option<TcpTxAnswer> {
+        if self.version != Self::VERSION {
+            return None;
+        }
+        borsh::from_slice(&self.answer).ok()
+    }
+}
 
 tcp_client_server! {
     pub TcpServer,
@@ -552,4 +585,24 @@ pub mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_tcp_server_response_versions() -> Result<()> {
+        use crate::tcp::{TcpServerResponse, TcpTxAnswer};
+
+        let answer = TcpTxAnswer::Accepted(sdk::TxHash::new("tx"));
+        let response: TcpServerResponse =
+            borsh::from_slice(&borsh::to_vec(&TcpServerResponse::new(&answer)?)?)?;
+        assert_eq!(response.answer(), Some(answer));
+
+        // Answers of a later version still decode, and are skipped
+        let response = TcpServerResponse {
+            version: TcpServerResponse::VERSION + 1,
+            answer: vec![42; 10],
+        };
+        let response: TcpServerResponse = borsh::from_slice(&borsh::to_vec(&response)?)?;
+        assert_eq!(response.answer(), None);
+
+        Ok(())
+    }
 }
@@ -3,13 +3,14 @@ use client_sdk::{
     transaction_builder::{ProvableBlobTx, StateUpdater, TxExecutorBuilder},
 };
 use sdk::{
-    api::{APIFees, APIFeesBalance, APIStaking},
-    ContractName, StakingAction, ValidatorPublicKey,
+    api::{APIFees, APIFeesBalance, APIStaking, APIUndelegation, APIWithdrawal},
+    erc20::ERC20Action,
+    BlobIndex, ContractName, Identity, StakingAction, ValidatorPublicKey,
 };
 
 use crate::{
     fees::{Fees, ValidatorFeeState},
-    state::Staking,
+    state::{Staking, Undelegation, Withdrawal},
 };
 
 pub mod metadata {
@@ -37,6 +38,31 @@ impl From<Staking> for APIStaking {
             delegations: val.delegations,
             total_bond: val.total_bond,
             fees: val.fees.into(),
+            withdrawals: val.withdrawals.into_iter().map(Into::into).collect(),
+            undelegations: val.undelegations.into_iter().map(Into::into).collect(),
+            unbond_requests: val.unbond_requests,
+            tombstoned: val.tombstoned,
+            slashed: val.slashed,
+        }
+    }
+}
+
+impl From<Withdrawal> for APIWithdrawal {
+    fn from(val: Withdrawal) -> Self {
+        APIWithdrawal {
+            identity: val.identity,
+            amount: val.amount,
+            unlock_height: val.unlock_height,
+        }
+    }
+}
+
+impl From<Undelegation> for APIUndelegation {
+    fn from(val: Undelegation) -> Self {
+        APIUndelegation {
+            identity: val.identity,
+            validator: val.validator,
+            unlock_height: val.unlock_height,
         }
     }
 }
@@ -57,6 +83,9 @@ impl From<Fees> for APIFees {
                     )
                 })
                 .collect(),
+            fee_per_byte: val.fee_per_byte,
+            burned: val.burned,
+            undistributed: val.undistributed,
         }
     }
 }
@@ -70,6 +99,31 @@ impl From<APIStaking> for Staking {
             delegations: val.delegations,
             total_bond: val.total_bond,
             fees: val.fees.into(),
+            withdrawals: val.withdrawals.into_iter().map(Into::into).collect(),
+            undelegations: val.undelegations.into_iter().map(Into::into).collect(),
+            unbond_requests: val.unbond_requests,
+            tombstoned: val.tombstoned,
+            slashed: val.slashed,
+        }
+    }
+}
+
+impl From<APIWithdrawal> for Withdrawal {
+    fn from(val: APIWithdrawal) -> Self {
+        Withdrawal {
+            identity: val.identity,
+            amount: val.amount,
+            unlock_height: val.unlock_height,
+        }
+    }
+}
+
+impl From<APIUndelegation> for Undelegation {
+    fn from(val: APIUndelegation) -> Self {
+        Undelegation {
+            identity: val.identity,
+            validator: val.validator,
+            unlock_height: val.unlock_height,
         }
     }
 }
@@ -91,6 +145,9 @@ impl From<APIFees> for Fees {
                     )
                 })
                 .collect(),
+            fee_per_byte: val.fee_per_byte,
+            burned: val.burned,
+            undistributed: val.undistributed,
         }
     }
 }
@@ -149,3 +206,103 @@ pub fn delegate(builder: &mut ProvableBlobTx, validator: ValidatorPublicKey) ->
     )?;
     Ok(())
 }
+
+pub fn unstake(
+    builder: &mut ProvableBlobTx,
+    contract_name: ContractName,
+    amount: u128,
+) -> anyhow::Result<()> {
+    builder.add_action(
+        contract_name,
+        StakingAction::Unstake { amount },
+        None,
+        None,
+        None,
+    )?;
+    Ok(())
+}
+
+pub fn undelegate(
+    builder: &mut ProvableBlobTx,
+    contract_name: ContractName,
+    validator: ValidatorPublicKey,
+) -> anyhow::Result<()> {
+    builder.add_action(
+        contract_name,
+        StakingAction::Undelegate { validator },
+        None,
+        None,
+        None,
+    )?;
+    Ok(())
+}
+
+pub fn unbond(
+    builder: &mut ProvableBlobTx,
+    contract_name: ContractName,
+    validator: ValidatorPublicKey,
+) -> anyhow::Result<()> {
+    builder.add_action(
+        contract_name,
+        StakingAction::Unbond { validator },
+        None,
+        None,
+        None,
+    )?;
+    Ok(())
+}
+
+/// Withdraws unlocked amounts, paid back by a hyllar transfer from the staking contract
+pub fn withdraw(
+    builder: &mut ProvableBlobTx,
+    contract_name: ContractName,
+    token: ContractName,
+    amount: u128,
+) -> anyhow::Result<()> {
+    let withdraw_index = builder.blobs.len();
+    let recipient = builder.identity.0.clone();
+    builder.add_action(
+        contract_name,
+        StakingAction::Withdraw { amount },
+        None,
+        None,
+        Some(vec![BlobIndex(withdraw_index + 1)]),
+    )?;
+    builder.add_action(
+        token,
+        ERC20Action::Transfer { recipient, amount },
+        None,
+        Some(BlobIndex(withdraw_index)),
+        None,
+    )?;
+    Ok(())
+}
+
+/// Pays the fees of the transaction with a hyllar transfer to the staking contract.
+/// With a sponsor, the transfer is taken from the allowance the sponsor gave to the caller.
+pub fn pay_tx_fees(
+    builder: &mut ProvableBlobTx,
+    contract_name: ContractName,
+    token: ContractName,
+    amount: u128,
+    sponsor: Option<Identity>,
+) -> anyhow::Result<()> {
+    let recipient = contract_name.0.clone();
+    let transfer = match &sponsor {
+        Some(sponsor) => ERC20Action::TransferFrom {
+            owner: sponsor.0.clone(),
+            recipient,
+            amount,
+        },
+        None => ERC20Action::Transfer { recipient, amount },
+    };
+    builder.add_action(
+        contract_name,
+        StakingAction::PayTxFees { amount, sponsor },
+        None,
+        None,
+        None,
+    )?;
+    builder.add_action(token, transfer, None, None, None)?;
+    Ok(())
+}
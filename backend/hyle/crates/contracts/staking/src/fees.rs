@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::{cmp::Ordering, collections::BTreeMap};
 
 use borsh::{BorshDeserialize, BorshSerialize};
 use sdk::{LaneBytesSize, ValidatorPublicKey};
@@ -15,21 +15,80 @@ pub struct ValidatorFeeState {
     pub(crate) paid_cumul_size: LaneBytesSize,
 }
 
-#[derive(
-    Debug, Default, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize, PartialEq, Eq,
-)]
+/// Lowest price of a disseminated byte
+pub const MIN_FEE_PER_BYTE: u128 = 1;
+/// Highest price of a disseminated byte, so that the fees of a slot stay far from overflowing
+pub const MAX_FEE_PER_BYTE: u128 = 1_000_000_000;
+/// Number of bytes disseminated per slot at which the price of a byte stays the same.
+/// The price goes up when slots are fuller than this, and down when they are emptier.
+pub const TARGET_BYTES_PER_SLOT: u128 = 1_000_000;
+/// Bounds the change of the price between two slots to 1/8th, as EIP-1559 does
+pub const FEE_CHANGE_DENOMINATOR: u128 = 8;
+/// Default share of the fees that is burned, the rest goes to the bonded validators
+pub const BURN_PERCENT: u128 = 50;
+
+/// Where the fees paid for data dissemination go.
+/// Fees are distributed by the consensus, so all validators need the same values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeeSplit {
+    /// Percentage of the fees that does not go to the bonded validators, at most 100
+    pub burn_percent: u128,
+    /// Credited with that share instead of burning it, when set
+    pub treasury: Option<ValidatorPublicKey>,
+}
+
+impl Default for FeeSplit {
+    fn default() -> Self {
+        FeeSplit {
+            burn_percent: BURN_PERCENT,
+            treasury: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
 pub struct Fees {
     /// Cumulative size of the data disseminated by the validators, pending fee distribution
     pub(crate) pending_fees: Vec<(ValidatorPublicKey, LaneBytesSize)>,
 
     /// Balance of each validator
     pub(crate) balances: BTreeMap<ValidatorPublicKey, ValidatorFeeState>,
+
+    /// Current price of a disseminated byte
+    pub(crate) fee_per_byte: u128,
+    /// Total amount of fees burned, or credited to the treasury
+    pub(crate) burned: u128,
+    /// Fees paid by transactions, and fees that could not be split evenly between the bonded
+    /// validators, distributed with the next fees
+    pub(crate) undistributed: u128,
+}
+
+impl Default for Fees {
+    fn default() -> Self {
+        Fees {
+            pending_fees: vec![],
+            balances: BTreeMap::new(),
+            fee_per_byte: MIN_FEE_PER_BYTE,
+            burned: 0,
+            undistributed: 0,
+        }
+    }
 }
 
 impl Fees {
     /// Deposit funds to be distributed as fees
     pub fn deposit_for_fees(&mut self, holder: ValidatorPublicKey, amount: u128) {
-        self.balances.entry(holder).or_default().balance += amount as i128;
+        self.credit(holder, amount);
+    }
+
+    /// Fees paid by a transaction go to the bonded validators, with the next distribution
+    pub(crate) fn pay_tx_fees(&mut self, amount: u128) {
+        self.undistributed = self.undistributed.saturating_add(amount);
+    }
+
+    fn credit(&mut self, holder: ValidatorPublicKey, amount: u128) {
+        let state = self.balances.entry(holder).or_default();
+        state.balance = state.balance.saturating_add(to_balance(amount));
     }
 
     /// Store the fees to be distributed
@@ -44,14 +103,26 @@ impl Fees {
         Ok(())
     }
 
-    /// Distribute the fees to the bonded validators
-    /// The current strategy is quite dummy, it can be improved!
+    /// Current price of a disseminated byte
+    pub fn fee_per_byte(&self) -> u128 {
+        self.fee_per_byte
+    }
+
+    /// Distribute the fees to the bonded validators, once per committed slot.
+    /// Part of the fees is burned, or goes to the treasury, the rest is split evenly between
+    /// the bonded validators.
+    /// The price of a byte is then updated from the number of bytes disseminated in the slot.
     ///
     /// We could imagine other strategies of distribution, like distributing
     /// the fees for the validators that voted on these DP. For this we would
     /// need to pass the PoDa to the pay_for_dadi function
-    pub(crate) fn distribute(&mut self, bonded: &[ValidatorPublicKey]) -> Result<(), String> {
-        let fee_per_byte = 1; // TODO: this value could be computed & change over time
+    pub(crate) fn distribute(
+        &mut self,
+        bonded: &[ValidatorPublicKey],
+        split: &FeeSplit,
+    ) -> Result<(), String> {
+        let mut slot_size: u128 = 0;
+        let mut slot_fees: u128 = 0;
         for (disseminator, cumul_size) in self.pending_fees.drain(..) {
             let Some(disseminator) = self.balances.get_mut(&disseminator) else {
                 // We should never come here, as the disseminator should have a balance
@@ -69,21 +140,59 @@ impl Fees {
                 ));
             }
 
-            let unpaid_size = cumul_size.0 - disseminator.paid_cumul_size.0;
-            let fee = (unpaid_size * fee_per_byte) as i128;
-            disseminator.balance -= fee;
+            let unpaid_size = (cumul_size.0 - disseminator.paid_cumul_size.0) as u128;
+            let fee = unpaid_size.saturating_mul(self.fee_per_byte);
+            disseminator.balance = disseminator.balance.saturating_sub(to_balance(fee));
             disseminator.paid_cumul_size = cumul_size;
 
-            // TODO: we might loose some token here as the division is rounded
-            let fee_per_validator = fee / bonded.len() as i128;
+            slot_size = slot_size.saturating_add(unpaid_size);
+            slot_fees = slot_fees.saturating_add(fee);
+        }
+
+        // Computed without overflowing, whatever the fees of the slot
+        let burn_percent = split.burn_percent.min(100);
+        let burned = slot_fees / 100 * burn_percent + slot_fees % 100 * burn_percent / 100;
+        self.burned = self.burned.saturating_add(burned);
+        if let Some(treasury) = &split.treasury {
+            self.credit(treasury.clone(), burned);
+        }
+        self.undistributed = self.undistributed.saturating_add(slot_fees - burned);
+        if !bonded.is_empty() {
+            // What cannot be split evenly is kept for the next distribution
+            let fee_per_validator = self.undistributed / bonded.len() as u128;
+            self.undistributed %= bonded.len() as u128;
             for validator in bonded.iter() {
-                let state = self.balances.entry(validator.clone()).or_default();
-                state.balance += fee_per_validator;
+                self.credit(validator.clone(), fee_per_validator);
             }
         }
 
+        self.update_fee_per_byte(slot_size);
         Ok(())
     }
+
+    /// Moves the price of a byte towards the one at which slots disseminate
+    /// `TARGET_BYTES_PER_SLOT` bytes, by at most 1/8th per slot, between `MIN_FEE_PER_BYTE`
+    /// and `MAX_FEE_PER_BYTE`.
+    fn update_fee_per_byte(&mut self, slot_size: u128) {
+        // Like blocks in EIP-1559, slots count for at most twice the target
+        let slot_size = slot_size.min(2 * TARGET_BYTES_PER_SLOT);
+        let price = self.fee_per_byte;
+        let delta = price.saturating_mul(slot_size.abs_diff(TARGET_BYTES_PER_SLOT))
+            / TARGET_BYTES_PER_SLOT
+            / FEE_CHANGE_DENOMINATOR;
+        // The price always moves when slots are off target, even when it is too low for 1/8th to matter
+        let price = match slot_size.cmp(&TARGET_BYTES_PER_SLOT) {
+            Ordering::Greater => price.saturating_add(delta.max(1)),
+            Ordering::Less => price.saturating_sub(delta.max(1)),
+            Ordering::Equal => price,
+        };
+        self.fee_per_byte = price.clamp(MIN_FEE_PER_BYTE, MAX_FEE_PER_BYTE);
+    }
+}
+
+/// Balances are signed, amounts beyond `i128::MAX` count as `i128::MAX`
+fn to_balance(amount: u128) -> i128 {
+    i128::try_from(amount).unwrap_or(i128::MAX)
 }
 
 #[cfg(test)]
@@ -117,11 +226,153 @@ mod tests {
 
         fees.deposit_for_fees(validator1.clone(), 200);
         fees.pay_for_dadi(validator1.clone(), cumul_size).unwrap();
-        fees.distribute(&[validator1.clone(), validator2.clone()])
+        fees.distribute(
+            &[validator1.clone(), validator2.clone()],
+            &FeeSplit::default(),
+        )
+        .unwrap();
+
+        // Half of the 100 paid is burned, the other half is split between the validators
+        assert_eq!(fees.balances.get(&validator1).unwrap().balance, 125);
+        assert_eq!(fees.balances.get(&validator2).unwrap().balance, 25);
+        assert_eq!(fees.burned, 50);
+        assert_eq!(fees.undistributed, 0);
+    }
+
+    #[test]
+    fn test_distribute_remainder() {
+        let mut fees = Fees::default();
+        let validators = ["p1", "p2", "p3"].map(ValidatorPublicKey::new_for_tests);
+
+        fees.deposit_for_fees(validators[0].clone(), 200);
+        fees.pay_for_dadi(validators[0].clone(), LaneBytesSize(20))
+            .unwrap();
+        fees.distribute(&validators, &FeeSplit::default()).unwrap();
+        // 10 are distributed, 3 to each validator, 1 is kept
+        assert_eq!(fees.balances.get(&validators[1]).unwrap().balance, 3);
+        assert_eq!(fees.undistributed, 1);
+
+        fees.pay_for_dadi(validators[0].clone(), LaneBytesSize(30))
             .unwrap();
+        fees.distribute(&validators, &FeeSplit::default()).unwrap();
+        // 5 more, plus the 1 kept
+        assert_eq!(fees.balances.get(&validators[1]).unwrap().balance, 5);
+        assert_eq!(fees.undistributed, 0);
+
+        // No token is lost
+        let balances: i128 = fees.balances.values().map(|b| b.balance).sum();
+        assert_eq!(
+            balances + fees.burned as i128 + fees.undistributed as i128,
+            200
+        );
+    }
+
+    #[test]
+    fn test_pay_tx_fees() {
+        let mut fees = Fees::default();
+        let validators = ["p1", "p2"].map(ValidatorPublicKey::new_for_tests);
+
+        // Transaction fees all go to the validators, whatever the price of a byte
+        fees.fee_per_byte = MAX_FEE_PER_BYTE;
+        fees.pay_tx_fees(101);
+        fees.distribute(&validators, &FeeSplit::default()).unwrap();
+        assert_eq!(fees.balances.get(&validators[0]).unwrap().balance, 50);
+        assert_eq!(fees.balances.get(&validators[1]).unwrap().balance, 50);
+        assert_eq!(fees.burned, 0);
+        assert_eq!(fees.undistributed, 1);
+    }
+
+    #[test]
+    fn test_distribute_to_treasury() {
+        let mut fees = Fees::default();
+        let validators = ["p1", "p2"].map(ValidatorPublicKey::new_for_tests);
+        let treasury = ValidatorPublicKey::new_for_tests("treasury");
+        let split = FeeSplit {
+            burn_percent: 30,
+            treasury: Some(treasury.clone()),
+        };
+
+        fees.deposit_for_fees(validators[0].clone(), 200);
+        fees.pay_for_dadi(validators[0].clone(), LaneBytesSize(100))
+            .unwrap();
+        fees.distribute(&validators, &split).unwrap();
+
+        // 30 of the 100 paid go to the treasury instead of being burned, 70 to the validators
+        assert_eq!(fees.balances.get(&treasury).unwrap().balance, 30);
+        assert_eq!(fees.balances.get(&validators[0]).unwrap().balance, 135);
+        assert_eq!(fees.balances.get(&validators[1]).unwrap().balance, 35);
+        assert_eq!(fees.burned, 30);
+    }
+
+    #[test]
+    fn test_fee_overflow() {
+        let mut fees = Fees::default();
+        let validator = ValidatorPublicKey::new_for_tests("p1");
+        fees.deposit_for_fees(validator.clone(), 100);
 
-        assert_eq!(fees.balances.get(&validator1).unwrap().balance, 150);
-        assert_eq!(fees.balances.get(&validator2).unwrap().balance, 50);
+        // The price stops at its maximum
+        let mut cumul_size = 0;
+        for _ in 0..500 {
+            cumul_size += 2 * TARGET_BYTES_PER_SLOT as u64;
+            fees.pay_for_dadi(validator.clone(), LaneBytesSize(cumul_size))
+                .unwrap();
+            fees.distribute(&[validator.clone()], &FeeSplit::default())
+                .unwrap();
+        }
+        assert_eq!(fees.fee_per_byte(), MAX_FEE_PER_BYTE);
+
+        // Huge sizes and amounts saturate instead of overflowing
+        fees.pay_for_dadi(validator.clone(), LaneBytesSize(u64::MAX))
+            .unwrap();
+        fees.pay_tx_fees(u128::MAX);
+        fees.deposit_for_fees(validator.clone(), u128::MAX);
+        fees.distribute(&[validator.clone()], &FeeSplit::default())
+            .unwrap();
+        assert_eq!(fees.fee_per_byte(), MAX_FEE_PER_BYTE);
+    }
+
+    #[test]
+    fn test_fee_per_byte() {
+        let mut fees = Fees::default();
+        let validator = ValidatorPublicKey::new_for_tests("p1");
+        fees.deposit_for_fees(validator.clone(), u64::MAX as u128);
+        assert_eq!(fees.fee_per_byte(), MIN_FEE_PER_BYTE);
+
+        // Full slots make the price go up
+        let mut cumul_size = 0;
+        for _ in 0..20 {
+            cumul_size += 2 * TARGET_BYTES_PER_SLOT as u64;
+            fees.pay_for_dadi(validator.clone(), LaneBytesSize(cumul_size))
+                .unwrap();
+            fees.distribute(&[validator.clone()], &FeeSplit::default())
+                .unwrap();
+        }
+        let high_price = fees.fee_per_byte();
+        assert!(high_price > 10);
+
+        // By at most 1/8th per slot
+        cumul_size += 10 * TARGET_BYTES_PER_SLOT as u64;
+        fees.pay_for_dadi(validator.clone(), LaneBytesSize(cumul_size))
+            .unwrap();
+        fees.distribute(&[validator.clone()], &FeeSplit::default())
+            .unwrap();
+        assert_eq!(fees.fee_per_byte(), high_price + high_price / 8);
+
+        // Slots at the target keep the price
+        let price = fees.fee_per_byte();
+        cumul_size += TARGET_BYTES_PER_SLOT as u64;
+        fees.pay_for_dadi(validator.clone(), LaneBytesSize(cumul_size))
+            .unwrap();
+        fees.distribute(&[validator.clone()], &FeeSplit::default())
+            .unwrap();
+        assert_eq!(fees.fee_per_byte(), price);
+
+        // Empty slots bring it back down to the minimum
+        for _ in 0..100 {
+            fees.distribute(&[validator.clone()], &FeeSplit::default())
+                .unwrap();
+        }
+        assert_eq!(fees.fee_per_byte(), MIN_FEE_PER_BYTE);
     }
 
     #[test]
@@ -132,7 +383,10 @@ mod tests {
         let cumul_size = LaneBytesSize(100);
 
         fees.pay_for_dadi(validator1.clone(), cumul_size).unwrap();
-        let result = fees.distribute(&[validator1.clone(), validator2.clone()]);
+        let result = fees.distribute(
+            &[validator1.clone(), validator2.clone()],
+            &FeeSplit::default(),
+        );
         assert!(result.is_err());
     }
 
@@ -146,7 +400,7 @@ mod tests {
         fees.deposit_for_fees(validator.clone(), 200);
         fees.pay_for_dadi(validator.clone(), cumul_size1).unwrap();
         fees.pay_for_dadi(validator.clone(), cumul_size2).unwrap();
-        let result = fees.distribute(&[validator.clone()]);
+        let result = fees.distribute(&[validator.clone()], &FeeSplit::default());
         assert!(result.is_err());
     }
 }
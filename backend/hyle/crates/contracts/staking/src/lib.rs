@@ -1,6 +1,8 @@
 use sdk::{
-    erc20::ERC20Action, utils::parse_contract_input, Blob, BlobIndex, ContractInput, HyleContract,
-    RunResult, StakingAction,
+    erc20::ERC20Action,
+    utils::{parse_contract_input, parse_structured_blob},
+    Blob, BlobIndex, BlockHeight, ContractInput, ContractName, HyleContract, Identity, RunResult,
+    StakingAction,
 };
 use state::Staking;
 
@@ -12,7 +14,7 @@ pub mod state;
 
 impl HyleContract for Staking {
     fn execute(&mut self, contract_input: &ContractInput) -> RunResult {
-        let (action, execution_ctx) = parse_contract_input::<StakingAction>(contract_input)?;
+        let (action, mut execution_ctx) = parse_contract_input::<StakingAction>(contract_input)?;
 
         let output = match action {
             StakingAction::Stake { amount } => {
@@ -22,11 +24,50 @@ impl HyleContract for Staking {
             StakingAction::Delegate { validator } => {
                 self.delegate_to(execution_ctx.caller.clone(), validator)
             }
-            StakingAction::Distribute { claim: _ } => todo!(),
+            StakingAction::Distribute { claim: _ } => {
+                Err("Distribute is not supported".to_string())
+            }
             StakingAction::DepositForFees { holder, amount } => {
                 check_transfer_blob(&contract_input.blobs, contract_input.index + 1, amount)?;
                 self.deposit_for_fees(holder, amount)
             }
+            StakingAction::Unstake { amount } => self.unstake(
+                execution_ctx.caller.clone(),
+                amount,
+                block_height(contract_input)?,
+            ),
+            StakingAction::Undelegate { validator } => self.undelegate(
+                execution_ctx.caller.clone(),
+                validator,
+                block_height(contract_input)?,
+            ),
+            StakingAction::Unbond { validator } => {
+                self.request_unbond(execution_ctx.caller.clone(), validator)
+            }
+            StakingAction::Withdraw { amount } => {
+                // The withdrawn amount is paid back by a transfer from the staking contract
+                execution_ctx.is_in_callee_blobs(
+                    &ContractName("hyllar".to_string()),
+                    ERC20Action::Transfer {
+                        recipient: execution_ctx.caller.0.clone(),
+                        amount,
+                    },
+                )?;
+                self.withdraw(
+                    execution_ctx.caller.clone(),
+                    amount,
+                    block_height(contract_input)?,
+                )
+            }
+            StakingAction::PayTxFees { amount, sponsor } => {
+                check_fee_transfer_blob(
+                    &contract_input.blobs,
+                    contract_input.index + 1,
+                    amount,
+                    sponsor.as_ref(),
+                )?;
+                self.pay_tx_fees(amount)
+            }
         };
 
         match output {
@@ -36,8 +77,67 @@ impl HyleContract for Staking {
     }
 }
 
+/// Height of the block that sequenced the transaction, from which unbonding delays are counted
+fn block_height(contract_input: &ContractInput) -> Result<BlockHeight, String> {
+    contract_input
+        .tx_ctx
+        .as_ref()
+        .map(|tx_ctx| tx_ctx.block_height)
+        .ok_or("Missing tx context".to_string())
+}
+
+/// Amount of fees paid by the `PayTxFees` blobs of a transaction, once checked that each of
+/// them is followed by the hyllar transfer that pays it.
+pub fn paid_tx_fees(blobs: &[Blob]) -> Result<u128, String> {
+    let mut paid: u128 = 0;
+    for (index, blob) in blobs.iter().enumerate() {
+        if blob.contract_name.0 != "staking" {
+            continue;
+        }
+        let Some(action) = parse_structured_blob::<StakingAction>(blobs, &BlobIndex(index)) else {
+            continue;
+        };
+        if let StakingAction::PayTxFees { amount, sponsor } = action.data.parameters {
+            check_fee_transfer_blob(blobs, BlobIndex(index + 1), amount, sponsor.as_ref())?;
+            paid = paid.saturating_add(amount);
+        }
+    }
+    Ok(paid)
+}
+
+/// Fees are paid with a transfer from the caller, or from the sponsor when there is one
+fn check_fee_transfer_blob(
+    blobs: &[Blob],
+    index: BlobIndex,
+    amount: u128,
+    sponsor: Option<&Identity>,
+) -> Result<(), String> {
+    let Some(sponsor) = sponsor else {
+        return check_transfer_blob(blobs, index, amount);
+    };
+    let transfer_action = parse_structured_blob::<ERC20Action>(blobs, &index)
+        .ok_or("No fee transfer blob found".to_string())?;
+    if transfer_action.contract_name.0 != "hyllar" {
+        return Err(format!(
+            "Only hyllar token are accepted to pay fees. Got {}.",
+            transfer_action.contract_name
+        ));
+    }
+    match transfer_action.data.parameters {
+        ERC20Action::TransferFrom {
+            owner,
+            recipient,
+            amount: transfer_amount,
+        } if owner == sponsor.0 && recipient == "staking" && transfer_amount == amount => Ok(()),
+        els => Err(format!(
+            "Wrong ERC20Action, should be a transfer of {:?} from {} to 'staking' but was {:?}",
+            amount, sponsor, els
+        )),
+    }
+}
+
 fn check_transfer_blob(blobs: &[Blob], index: BlobIndex, amount: u128) -> Result<(), String> {
-    let transfer_action = sdk::utils::parse_structured_blob::<ERC20Action>(blobs, &index)
+    let transfer_action = parse_structured_blob::<ERC20Action>(blobs, &index)
         .ok_or("No transfer blob found".to_string())?;
     match transfer_action.data.parameters {
         ERC20Action::Transfer {
@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 use anyhow::Result;
 use borsh::{BorshDeserialize, BorshSerialize};
@@ -8,7 +8,7 @@ use sdk::{
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
-use crate::fees::Fees;
+use crate::fees::{FeeSplit, Fees};
 
 #[derive(Debug, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
 pub struct Staking {
@@ -24,11 +24,50 @@ pub struct Staking {
 
     /// Struct to handle fees
     pub(crate) fees: Fees,
+
+    /// Unstaked amounts waiting for the end of their unbonding delay, oldest first
+    pub(crate) withdrawals: Vec<Withdrawal>,
+    /// Delegations stopped less than the unbonding delay ago: the stakers can still be slashed
+    /// for the validator they left, even once delegated to another one.
+    pub(crate) undelegations: Vec<Undelegation>,
+    /// Validators that asked to leave the consensus. They are unbonded by the consensus
+    /// at the end of a slot.
+    pub(crate) unbond_requests: BTreeSet<ValidatorPublicKey>,
+
+    /// Validators slashed for signing conflicting messages, that can no longer be bonded
+    pub(crate) tombstoned: BTreeSet<ValidatorPublicKey>,
+    /// Amounts cut from the stakers when their validator was slashed. Slashing is decided by
+    /// the consensus and is not known to the contract, so stakes and withdrawals keep the
+    /// amounts the contract tracks and the cut amounts are only removed from voting power.
+    pub(crate) slashed: BTreeMap<Identity, u128>,
+}
+
+/// An unstaked amount, that can be withdrawn from the given height
+#[derive(Debug, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+pub struct Withdrawal {
+    pub identity: Identity,
+    pub amount: u128,
+    pub unlock_height: BlockHeight,
+}
+
+/// A stopped delegation, that can be slashed until the given height
+#[derive(Debug, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+pub struct Undelegation {
+    pub identity: Identity,
+    pub validator: ValidatorPublicKey,
+    pub unlock_height: BlockHeight,
 }
 
 /// Minimal stake necessary to be part of consensus
 pub const MIN_STAKE: u128 = 32;
 
+/// Number of blocks an unstaked amount stays locked before it can be withdrawn, and a stopped
+/// delegation stays slashable
+pub const UNBONDING_DELAY: u64 = 10_000;
+
+/// Percentage of the stake delegated to a validator that is cut when it is slashed
+pub const SLASH_PERCENT: u128 = 5;
+
 impl Staking {
     pub fn new() -> Self {
         Staking {
@@ -38,6 +77,11 @@ impl Staking {
             bonded: Vec::new(),
             total_bond: 0,
             fees: Fees::default(),
+            withdrawals: Vec::new(),
+            undelegations: Vec::new(),
+            unbond_requests: BTreeSet::new(),
+            tombstoned: BTreeSet::new(),
+            slashed: BTreeMap::new(),
         }
     }
 
@@ -61,21 +105,116 @@ impl Staking {
         if self.is_bonded(&validator) {
             return Err("Validator already bonded".to_string());
         }
+        if self.is_tombstoned(&validator) {
+            return Err("Validator was slashed and cannot be bonded".to_string());
+        }
 
         info!("🔐 Bonded validator {}", validator);
         if let Some(stake) = self.get_stake(&validator) {
             if stake < MIN_STAKE {
                 return Err("Validator does not have enough stake".to_string());
             }
+            self.unbond_requests.remove(&validator);
             self.bonded.push(validator);
             self.bonded.sort(); // TODO insert in order?
-            self.total_bond += stake;
+            self.update_total_bond();
             Ok(())
         } else {
             Err("Validator does not have enough stake".to_string())
         }
     }
 
+    /// Remove a validator from the consensus
+    /// This function is meant to be called by the consensus
+    pub fn unbond(&mut self, validator: &ValidatorPublicKey) -> Result<(), String> {
+        if !self.is_bonded(validator) {
+            return Err("Validator is not bonded".to_string());
+        }
+        if self.bonded.len() == 1 {
+            return Err("Cannot unbond the last bonded validator".to_string());
+        }
+
+        info!("🔓 Unbonded validator {}", validator);
+        self.bonded.retain(|v| v != validator);
+        self.unbond_requests.remove(validator);
+        self.update_total_bond();
+        Ok(())
+    }
+
+    pub fn is_tombstoned(&self, validator: &ValidatorPublicKey) -> bool {
+        self.tombstoned.contains(validator)
+    }
+
+    /// Punish a validator that signed conflicting messages: cut `SLASH_PERCENT` of the stakes
+    /// delegated to it, including the amounts being unstaked and the delegations stopped during
+    /// the unbonding delay, and remove it from the consensus for good. The cut amounts no longer
+    /// count as voting power. Returns the amount cut.
+    /// `height` is the height of the block where the validator is slashed.
+    /// This function is meant to be called by the consensus
+    pub fn slash(
+        &mut self,
+        validator: &ValidatorPublicKey,
+        height: BlockHeight,
+    ) -> Result<u128, String> {
+        if !self.tombstoned.insert(validator.clone()) {
+            return Err("Validator was already slashed".to_string());
+        }
+
+        let mut delegators = self.delegations.get(validator).cloned().unwrap_or_default();
+        delegators.extend(
+            self.undelegations
+                .iter()
+                .filter(|u| &u.validator == validator && u.unlock_height > height)
+                .map(|u| u.identity.clone()),
+        );
+        let mut slashed = 0;
+        for delegator in delegators.iter() {
+            let pending: u128 = self
+                .withdrawals
+                .iter()
+                .filter(|w| &w.identity == delegator)
+                .map(|w| w.amount)
+                .sum();
+            let cut = (self.staked(delegator) + pending) * SLASH_PERCENT / 100;
+            if cut > 0 {
+                *self.slashed.entry(delegator.clone()).or_default() += cut;
+                slashed += cut;
+            }
+        }
+
+        info!("⚔️ Slashed validator {} of {}", validator, slashed);
+        self.unbond_requests.remove(validator);
+        // The last bonded validator stays, so that the chain can go on
+        if self.is_bonded(validator) && self.bonded.len() > 1 {
+            self.bonded.retain(|v| v != validator);
+        }
+        self.update_total_bond();
+        Ok(slashed)
+    }
+
+    /// Next bonded validator to remove from the consensus: one that asked to leave,
+    /// or whose stake fell below the minimum. The last bonded validator is never removed.
+    pub fn next_validator_to_unbond(&self) -> Option<&ValidatorPublicKey> {
+        if self.bonded.len() <= 1 {
+            return None;
+        }
+        self.bonded.iter().find(|v| self.should_unbond(v))
+    }
+
+    /// Whether a bonded validator asked to leave the consensus, or no longer has enough stake
+    pub fn should_unbond(&self, validator: &ValidatorPublicKey) -> bool {
+        self.unbond_requests.contains(validator)
+            || self.get_stake(validator).unwrap_or(0) < MIN_STAKE
+    }
+
+    /// Recompute the bonded amount from the stakes delegated to the bonded validators.
+    /// Stakes change with every block, but the consensus needs the same total for a whole slot:
+    /// it is recomputed when validators are bonded, unbonded or slashed, and by the consensus
+    /// when a slot is committed.
+    pub fn update_total_bond(&mut self) {
+        self.total_bond = self.compute_voting_power(&self.bonded);
+    }
+
     /// Compute f value
     pub fn compute_f(&self) -> u128 {
         self.total_bond().div_ceil(3)
@@ -92,7 +231,7 @@ impl Staking {
         self.delegations.get(validator).map(|delegations| {
             delegations
                 .iter()
-                .map(|delegator| self.stakes.get(delegator).unwrap_or(&0))
+                .map(|delegator| self.voting_stake(delegator))
                 .sum()
         })
     }
@@ -106,6 +245,82 @@ impl Staking {
         Ok("Staked".to_string())
     }
 
+    /// Remove an amount from the stake, to be withdrawn once the unbonding delay is over.
+    /// `height` is the height of the block that sequenced the transaction.
+    pub fn unstake(
+        &mut self,
+        staker: Identity,
+        amount: u128,
+        height: BlockHeight,
+    ) -> Result<String, String> {
+        let stake = self.staked(&staker);
+        if stake < amount {
+            return Err(format!("Cannot unstake {amount}, only {stake} is staked"));
+        }
+
+        info!("💸 Removing {} from stake for {}", amount, staker);
+        if stake == amount {
+            self.stakes.remove(&staker);
+        } else {
+            self.stakes.insert(staker.clone(), stake - amount);
+        }
+        self.withdrawals.push(Withdrawal {
+            identity: staker,
+            amount,
+            unlock_height: height + UNBONDING_DELAY,
+        });
+        Ok("Unstaked".to_string())
+    }
+
+    /// Amount staked by the staker that still counts as voting power, once slashing is deducted
+    fn voting_stake(&self, staker: &Identity) -> u128 {
+        self.staked(staker)
+            .saturating_sub(self.slashed.get(staker).copied().unwrap_or(0))
+    }
+
+    /// Amount staked by the staker
+    pub fn staked(&self, staker: &Identity) -> u128 {
+        self.stakes.get(staker).copied().unwrap_or(0)
+    }
+
+    /// Amount that the staker can withdraw at the given height
+    pub fn withdrawable(&self, staker: &Identity, height: BlockHeight) -> u128 {
+        self.withdrawals
+            .iter()
+            .filter(|w| &w.identity == staker && w.unlock_height <= height)
+            .map(|w| w.amount)
+            .sum()
+    }
+
+    /// Withdraw unstaked amounts whose unbonding delay is over, oldest first.
+    /// `height` is the height of the block that sequenced the transaction.
+    pub fn withdraw(
+        &mut self,
+        staker: Identity,
+        amount: u128,
+        height: BlockHeight,
+    ) -> Result<String, String> {
+        let withdrawable = self.withdrawable(&staker, height);
+        if withdrawable < amount {
+            return Err(format!(
+                "Cannot withdraw {amount}, only {withdrawable} is unlocked"
+            ));
+        }
+
+        info!("🏧 Withdrawing {} for {}", amount, staker);
+        let mut remaining = amount;
+        self.withdrawals.retain_mut(|w| {
+            if remaining == 0 || w.identity != staker || w.unlock_height > height {
+                return true;
+            }
+            let taken = remaining.min(w.amount);
+            w.amount -= taken;
+            remaining -= taken;
+            w.amount > 0
+        });
+        Ok("Withdrawn".to_string())
+    }
+
     /// Delegate to a validator, or fail if already delegated to another validator
     pub fn delegate_to(
         &mut self,
@@ -124,6 +339,56 @@ impl Staking {
         Ok("Delegated".to_string())
     }
 
+    /// Stop delegating to a validator. The stake stays slashable for this validator until the
+    /// unbonding delay is over.
+    /// `height` is the height of the block that sequenced the transaction.
+    pub fn undelegate(
+        &mut self,
+        staker: Identity,
+        validator: ValidatorPublicKey,
+        height: BlockHeight,
+    ) -> Result<String, String> {
+        let Some(delegators) = self.delegations.get_mut(&validator) else {
+            return Err("Not delegated to this validator".to_string());
+        };
+        let Some(position) = delegators.iter().position(|d| d == &staker) else {
+            return Err("Not delegated to this validator".to_string());
+        };
+
+        info!("👋 {} stops delegating to {}", staker, validator);
+        delegators.remove(position);
+        if delegators.is_empty() {
+            self.delegations.remove(&validator);
+        }
+        // Stopped delegations that can no longer be slashed are dropped
+        self.undelegations.retain(|u| u.unlock_height > height);
+        self.undelegations.push(Undelegation {
+            identity: staker,
+            validator,
+            unlock_height: height + UNBONDING_DELAY,
+        });
+        Ok("Undelegated".to_string())
+    }
+
+    /// Ask for a validator the staker delegates to to leave the consensus
+    pub fn request_unbond(
+        &mut self,
+        staker: Identity,
+        validator: ValidatorPublicKey,
+    ) -> Result<String, String> {
+        if !self
+            .delegations
+            .get(&validator)
+            .is_some_and(|delegators| delegators.contains(&staker))
+        {
+            return Err("Only a delegator can ask a validator to unbond".to_string());
+        }
+
+        info!("🚪 {} asks {} to unbond", staker, validator);
+        self.unbond_requests.insert(validator);
+        Ok("Unbond requested".to_string())
+    }
+
     //    ----------
     //      Fees
     //    ----------
@@ -139,6 +404,14 @@ impl Staking {
         Ok("Deposited".to_string())
     }
 
+    /// Fees paid by a transaction, to be distributed to the bonded validators.
+    /// The amount the transaction declares is taken as is, whatever the current price of a byte.
+    /// This function is meant to be called from BlobTransaction
+    pub fn pay_tx_fees(&mut self, amount: u128) -> Result<String, String> {
+        self.fees.pay_tx_fees(amount);
+        Ok("Fees paid".to_string())
+    }
+
     /// Store the fees to be distributed
     /// DaDi = Data dissemination
     /// This function is meant to be called by the consensus
@@ -154,8 +427,13 @@ impl Staking {
 
     /// Distribute the fees to the bonded validators
     /// This function is meant to be called by the consensus
-    pub fn distribute(&mut self) -> Result<(), String> {
-        self.fees.distribute(&self.bonded)
+    pub fn distribute(&mut self, split: &FeeSplit) -> Result<(), String> {
+        self.fees.distribute(&self.bonded, split)
+    }
+
+    /// Current price of a disseminated byte
+    pub fn fee_per_byte(&self) -> u128 {
+        self.fees.fee_per_byte()
     }
 }
 
@@ -187,6 +465,190 @@ impl Digestable for Staking {
                 hasher.update(i.0.to_le_bytes());
             }
         }
+        for w in self.withdrawals.iter() {
+            hasher.update(&w.identity.0);
+            hasher.update(w.amount.to_le_bytes());
+            hasher.update(w.unlock_height.0.to_le_bytes());
+        }
+        for u in self.undelegations.iter() {
+            hasher.update(&u.identity.0);
+            hasher.update(&u.validator.0);
+            hasher.update(u.unlock_height.0.to_le_bytes());
+        }
+        for v in self.unbond_requests.iter() {
+            hasher.update(&v.0);
+        }
         StateDigest(hasher.finalize().to_vec())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn staking_with_validators(validators: &[&str]) -> Staking {
+        let mut staking = Staking::new();
+        for name in validators {
+            let validator = ValidatorPublicKey::new_for_tests(name);
+            staking.stake(Identity::new(*name), 100).unwrap();
+            staking
+                .delegate_to(Identity::new(*name), validator.clone())
+                .unwrap();
+            staking.bond(validator).unwrap();
+        }
+        staking
+    }
+
+    #[test]
+    fn test_unstake_and_withdraw() {
+        let mut staking = staking_with_validators(&["p1", "p2"]);
+        let staker = Identity::new("p1");
+
+        assert!(staking
+            .unstake(staker.clone(), 101, BlockHeight(10))
+            .is_err());
+        staking
+            .unstake(staker.clone(), 60, BlockHeight(10))
+            .unwrap();
+        staking
+            .unstake(staker.clone(), 20, BlockHeight(20))
+            .unwrap();
+        assert_eq!(staking.stakes.get(&staker), Some(&20));
+        // The bonded amount only changes at the end of the slot
+        assert_eq!(staking.total_bond(), 200);
+        staking.update_total_bond();
+        assert_eq!(staking.total_bond(), 120);
+
+        // Nothing can be withdrawn before the end of the unbonding delay
+        assert_eq!(
+            staking.withdrawable(&staker, BlockHeight(10 + UNBONDING_DELAY - 1)),
+            0
+        );
+        assert!(staking
+            .withdraw(staker.clone(), 60, BlockHeight(10 + UNBONDING_DELAY - 1))
+            .is_err());
+
+        let height = BlockHeight(10 + UNBONDING_DELAY);
+        assert_eq!(staking.withdrawable(&staker, height), 60);
+        staking.withdraw(staker.clone(), 50, height).unwrap();
+        assert_eq!(staking.withdrawable(&staker, height), 10);
+        assert!(staking.withdraw(staker.clone(), 20, height).is_err());
+
+        let height = BlockHeight(20 + UNBONDING_DELAY);
+        staking.withdraw(staker.clone(), 30, height).unwrap();
+        assert!(staking.withdrawals.is_empty());
+    }
+
+    #[test]
+    fn test_undelegate() {
+        let mut staking = staking_with_validators(&["p1", "p2"]);
+        let validator = ValidatorPublicKey::new_for_tests("p1");
+
+        assert!(staking
+            .undelegate(Identity::new("p2"), validator.clone(), BlockHeight(10))
+            .is_err());
+        staking
+            .undelegate(Identity::new("p1"), validator.clone(), BlockHeight(10))
+            .unwrap();
+        assert_eq!(staking.get_stake(&validator), None);
+        assert_eq!(staking.total_bond(), 200);
+        staking.update_total_bond();
+        assert_eq!(staking.total_bond(), 100);
+        assert!(staking.should_unbond(&validator));
+
+        // The stake can be delegated to another validator right away
+        staking
+            .delegate_to(Identity::new("p1"), ValidatorPublicKey::new_for_tests("p2"))
+            .unwrap();
+        assert_eq!(staking.undelegations.len(), 1);
+    }
+
+    #[test]
+    fn test_slash_undelegated() {
+        let mut staking = staking_with_validators(&["p1", "p2", "p3"]);
+        let p1 = ValidatorPublicKey::new_for_tests("p1");
+        let p2 = ValidatorPublicKey::new_for_tests("p2");
+        staking
+            .undelegate(Identity::new("p1"), p1.clone(), BlockHeight(10))
+            .unwrap();
+        staking
+            .delegate_to(Identity::new("p1"), p2.clone())
+            .unwrap();
+
+        // Stakers that left the validator during the unbonding delay are still cut
+        assert_eq!(staking.slash(&p1, BlockHeight(20)), Ok(5));
+        assert_eq!(staking.get_stake(&p2), Some(195));
+
+        // Once the delay is over, they are no longer
+        staking
+            .undelegate(Identity::new("p2"), p2.clone(), BlockHeight(20))
+            .unwrap();
+        assert_eq!(staking.slash(&p2, BlockHeight(20 + UNBONDING_DELAY)), Ok(5));
+        assert_eq!(staking.slashed.get(&Identity::new("p2")), None);
+    }
+
+    #[test]
+    fn test_unbond() {
+        let mut staking = staking_with_validators(&["p1", "p2"]);
+        let p1 = ValidatorPublicKey::new_for_tests("p1");
+        let p2 = ValidatorPublicKey::new_for_tests("p2");
+        assert_eq!(staking.next_validator_to_unbond(), None);
+
+        // Only a delegator can ask a validator to leave
+        assert!(staking
+            .request_unbond(Identity::new("p1"), p2.clone())
+            .is_err());
+        staking
+            .request_unbond(Identity::new("p2"), p2.clone())
+            .unwrap();
+        assert_eq!(staking.next_validator_to_unbond(), Some(&p2));
+
+        staking.unbond(&p2).unwrap();
+        assert_eq!(staking.bonded(), &vec![p1.clone()]);
+        assert_eq!(staking.total_bond(), 100);
+        assert!(staking.unbond_requests.is_empty());
+
+        // The last bonded validator stays, whatever its stake
+        staking
+            .unstake(Identity::new("p1"), 100, BlockHeight(0))
+            .unwrap();
+        assert_eq!(staking.next_validator_to_unbond(), None);
+        assert!(staking.unbond(&p1).is_err());
+    }
+
+    #[test]
+    fn test_slash() {
+        let mut staking = staking_with_validators(&["p1", "p2"]);
+        let p1 = ValidatorPublicKey::new_for_tests("p1");
+        let p2 = ValidatorPublicKey::new_for_tests("p2");
+        staking
+            .unstake(Identity::new("p1"), 40, BlockHeight(10))
+            .unwrap();
+
+        // Both the stake and the pending withdrawals of the delegators are cut from their
+        // voting power, while the amounts tracked by the contract are left untouched
+        assert_eq!(staking.slash(&p1, BlockHeight(10)), Ok(5));
+        assert_eq!(staking.get_stake(&p1), Some(55));
+        assert_eq!(staking.staked(&Identity::new("p1")), 60);
+        assert_eq!(
+            staking.withdrawable(&Identity::new("p1"), BlockHeight(10 + UNBONDING_DELAY)),
+            40
+        );
+        assert_eq!(staking.bonded(), &vec![p2.clone()]);
+        assert_eq!(staking.total_bond(), 100);
+        // Whatever the contract allows can still be unstaked
+        staking
+            .unstake(Identity::new("p1"), 60, BlockHeight(20))
+            .unwrap();
+        assert_eq!(staking.get_stake(&p1), Some(0));
+
+        // A slashed validator cannot come back nor be slashed twice
+        assert!(staking.is_tombstoned(&p1));
+        assert!(staking.bond(p1.clone()).is_err());
+        assert!(staking.slash(&p1, BlockHeight(10)).is_err());
+
+        // The last bonded validator stays, even when slashed
+        assert_eq!(staking.slash(&p2, BlockHeight(10)), Ok(5));
+        assert_eq!(staking.bonded(), &vec![p2]);
+    }
+}
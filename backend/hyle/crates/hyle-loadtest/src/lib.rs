@@ -123,6 +123,7 @@ pub async fn setup(hyllar: Hyllar, url: String, verifier: String) -> Result<()>
             verifier: verifier.into(),
             program_id: hyle_contracts::HYLLAR_ID.to_vec().into(),
             state_digest: hyllar.as_digest(),
+            timeout_window: None,
         }
         .as_blob("hyle".into(), None, None)],
     );
@@ -411,6 +412,7 @@ pub async fn long_running_test(node_url: String, _indexer_url: String) -> Result
                 verifier: hyle_contract_sdk::Verifier("test".to_string()),
                 program_id: hyle_contracts::HYLLAR_ID.to_vec().into(),
                 state_digest: Hyllar::default().as_digest(),
+                timeout_window: None,
             }
             .as_blob("hyle".into(), None, None)],
         );
@@ -424,6 +426,7 @@ pub async fn long_running_test(node_url: String, _indexer_url: String) -> Result
                 verifier: hyle_contract_sdk::Verifier("test".to_string()),
                 program_id: hyle_contracts::HYDENTITY_ID.to_vec().into(),
                 state_digest: Hydentity::default().as_digest(),
+                timeout_window: None,
             }
             .as_blob("hyle".into(), None, None)],
         );
@@ -1,8 +1,10 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
+use borsh::{BorshDeserialize, BorshSerialize};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 use strum::IntoDiscriminant;
+use strum_macros::IntoStaticStr;
 use utoipa::ToSchema;
 
 use crate::{
@@ -24,6 +26,8 @@ pub struct APIRegisterContract {
     pub program_id: ProgramId,
     pub state_digest: StateDigest,
     pub contract_name: ContractName,
+    #[serde(default)]
+    pub timeout_window: Option<BlockHeight>,
 }
 
 /// Copy from Staking contract
@@ -41,6 +45,31 @@ pub struct APIStaking {
 
     /// Struct to handle fees
     pub fees: APIFees,
+
+    /// Unstaked amounts waiting for the end of their unbonding delay, oldest first
+    pub withdrawals: Vec<APIWithdrawal>,
+    /// Stopped delegations, still slashable until the end of their unbonding delay
+    pub undelegations: Vec<APIUndelegation>,
+    /// Validators that asked to leave the consensus
+    pub unbond_requests: BTreeSet<ValidatorPublicKey>,
+    /// Validators slashed for signing conflicting messages
+    pub tombstoned: BTreeSet<ValidatorPublicKey>,
+    /// Amounts cut from the stakers of slashed validators, deducted from voting power
+    pub slashed: BTreeMap<Identity, u128>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, PartialEq, Eq)]
+pub struct APIWithdrawal {
+    pub identity: Identity,
+    pub amount: u128,
+    pub unlock_height: BlockHeight,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, PartialEq, Eq)]
+pub struct APIUndelegation {
+    pub identity: Identity,
+    pub validator: ValidatorPublicKey,
+    pub unlock_height: BlockHeight,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema, Clone, PartialEq, Eq)]
@@ -53,6 +82,24 @@ pub struct APIFeesBalance {
 pub struct APIFees {
     /// Balance of each validator
     pub balances: BTreeMap<ValidatorPublicKey, APIFeesBalance>,
+    /// Current price of a disseminated byte
+    pub fee_per_byte: u128,
+    /// Total amount of fees burned, or credited to the treasury
+    pub burned: u128,
+    /// Fees waiting to be split between the bonded validators
+    pub undistributed: u128,
+}
+
+/// Price of the data disseminated by the validators, to estimate the cost of a transaction
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, PartialEq, Eq)]
+pub struct APIFeePrice {
+    /// Current price of a byte
+    pub fee_per_byte: u128,
+    /// Number of bytes disseminated per slot at which the price stays the same.
+    /// It goes up on fuller slots and down on emptier ones, by at most 1/8th per slot.
+    pub target_bytes_per_slot: u128,
+    /// Highest price of a byte
+    pub max_fee_per_byte: u128,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -181,3 +228,48 @@ pub struct APIBlob {
     pub data: Vec<u8>, // Actual blob data
     pub verified: bool,        // Verification status
 }
+
+/// Why the mempool refused a transaction sent to the REST or TCP entry points
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+    BorshSerialize,
+    BorshDeserialize,
+    ToSchema,
+    PartialEq,
+    Eq,
+    IntoStaticStr,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum TxRejectionCode {
+    /// The identity is not of the form `<id>.<contract_name>`, with a blob of that contract
+    InvalidIdentity,
+    /// A 'hyle' blob registers a contract with an invalid name, verifier or program id
+    InvalidContractRegistration,
+    /// A blob targets a contract that is not registered
+    UnknownContract,
+    TooManyBlobs,
+    BlobTooLarge,
+    TxTooLarge,
+    /// The same transaction was sent recently
+    DuplicateTx,
+    /// A `PayTxFees` blob is not followed by the hyllar transfer that pays it
+    InvalidFeePayment,
+    /// The transaction pays less than the minimum fees for its size
+    InsufficientFee,
+    /// Too many proofs are waiting for verification, overall or from the same contract or address
+    ProofQueueFull,
+}
+
+#[derive(
+    Debug, Clone, Serialize, Deserialize, BorshSerialize, BorshDeserialize, ToSchema, PartialEq, Eq,
+)]
+pub struct APITxRejection {
+    pub tx_hash: TxHash,
+    pub code: TxRejectionCode,
+    pub message: String,
+}
@@ -24,11 +24,18 @@ pub struct Block {
     pub blob_proof_outputs: Vec<HandledBlobProofOutput>,
     pub verified_blobs: Vec<(TxHash, BlobIndex, Option<usize>)>,
     pub new_bounded_validators: Vec<ValidatorPublicKey>,
-    pub staking_actions: Vec<(Identity, StakingAction)>,
+    pub unbonded_validators: Vec<ValidatorPublicKey>,
+    pub slashed_validators: Vec<ValidatorPublicKey>,
+    /// Settled staking actions, with the identity of their transaction and
+    /// the height of the block that sequenced it
+    pub staking_actions: Vec<(Identity, StakingAction, BlockHeight)>,
     pub registered_contracts: Vec<(TxHash, RegisterContractEffect)>,
     pub deleted_contracts: Vec<(TxHash, ContractName)>,
+    pub updated_contract_programs: Vec<(TxHash, UpdateContractProgramEffect)>,
     pub updated_states: BTreeMap<ContractName, StateDigest>,
     pub transactions_events: BTreeMap<TxHash, Vec<TransactionStateEvent>>,
+    /// Consensus parameters set by a settled transaction of the block
+    pub consensus_params: Option<ConsensusParams>,
 }
 
 impl Block {
@@ -403,6 +403,7 @@ pub struct ProgramId(pub Vec<u8>);
 pub enum OnchainEffect {
     RegisterContract(RegisterContractEffect),
     DeleteContract(ContractName),
+    UpdateContractProgram(UpdateContractProgramEffect),
 }
 
 /// This struct has to be the zkvm committed output. It will be used by
@@ -604,6 +605,9 @@ pub struct RegisterContractAction {
     pub program_id: ProgramId,
     pub state_digest: StateDigest,
     pub contract_name: ContractName,
+    /// Number of blocks blob transactions on this contract have to settle before timing out.
+    /// The protocol default applies if none is given, and the window is kept within the protocol bounds.
+    pub timeout_window: Option<BlockHeight>,
 }
 
 #[cfg(feature = "full")]
@@ -616,6 +620,9 @@ impl Hashed<TxHash> for RegisterContractAction {
         hasher.update(self.program_id.0.clone());
         hasher.update(self.state_digest.0.clone());
         hasher.update(self.contract_name.0.clone());
+        if let Some(timeout_window) = self.timeout_window {
+            hasher.update(timeout_window.0.to_le_bytes());
+        }
         let hash_bytes = hasher.finalize();
         TxHash(hex::encode(hash_bytes))
     }
@@ -665,6 +672,63 @@ impl ContractAction for DeleteContractAction {
     }
 }
 
+#[derive(
+    Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize,
+)]
+/// Used as a blob action to change the program of a contract in the 'hyle' TLD.
+/// The contract keeps its state.
+pub struct UpdateContractProgramAction {
+    pub contract_name: ContractName,
+    pub verifier: Verifier,
+    pub program_id: ProgramId,
+}
+
+impl ContractAction for UpdateContractProgramAction {
+    fn as_blob(
+        &self,
+        contract_name: ContractName,
+        caller: Option<BlobIndex>,
+        callees: Option<Vec<BlobIndex>>,
+    ) -> Blob {
+        Blob {
+            contract_name,
+            data: BlobData::from(StructuredBlobData {
+                caller,
+                callees,
+                parameters: self.clone(),
+            }),
+        }
+    }
+}
+
+#[derive(
+    Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize,
+)]
+/// Used as a blob action to set the identity that can delete, or update the program of, the
+/// contracts that have no owner in the 'hyle' TLD.
+/// Only valid in the genesis block, or when sent by the current TLD admin.
+pub struct SetTldAdminAction {
+    pub tld_admin: Option<Identity>,
+}
+
+impl ContractAction for SetTldAdminAction {
+    fn as_blob(
+        &self,
+        contract_name: ContractName,
+        caller: Option<BlobIndex>,
+        callees: Option<Vec<BlobIndex>>,
+    ) -> Blob {
+        Blob {
+            contract_name,
+            data: BlobData::from(StructuredBlobData {
+                caller,
+                callees,
+                parameters: self.clone(),
+            }),
+        }
+    }
+}
+
 /// Used by the Hylé node to recognize contract registration.
 /// Simply output this struct in your HyleOutput registered_contracts.
 /// See uuid-tld for examples.
@@ -686,6 +750,9 @@ pub struct RegisterContractEffect {
     pub program_id: ProgramId,
     pub state_digest: StateDigest,
     pub contract_name: ContractName,
+    /// Number of blocks blob transactions on this contract have to settle before timing out.
+    /// The protocol default applies if none is given, and the window is kept within the protocol bounds.
+    pub timeout_window: Option<BlockHeight>,
 }
 
 #[cfg(feature = "full")]
@@ -698,6 +765,45 @@ impl Hashed<TxHash> for RegisterContractEffect {
         hasher.update(self.program_id.0.clone());
         hasher.update(self.state_digest.0.clone());
         hasher.update(self.contract_name.0.clone());
+        if let Some(timeout_window) = self.timeout_window {
+            hasher.update(timeout_window.0.to_le_bytes());
+        }
+        let hash_bytes = hasher.finalize();
+        TxHash(hex::encode(hash_bytes))
+    }
+}
+
+/// Used by the Hylé node to recognize contract upgrades.
+/// Output this struct in your HyleOutput onchain_effects to change the program of your own
+/// contract, or of a contract registered under your TLD. The contract keeps its state.
+#[derive(
+    Default,
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    Hash,
+    BorshSerialize,
+    BorshDeserialize,
+)]
+#[cfg_attr(feature = "full", derive(utoipa::ToSchema))]
+pub struct UpdateContractProgramEffect {
+    pub contract_name: ContractName,
+    pub verifier: Verifier,
+    pub program_id: ProgramId,
+}
+
+#[cfg(feature = "full")]
+impl Hashed<TxHash> for UpdateContractProgramEffect {
+    fn hashed(&self) -> TxHash {
+        use sha3::{Digest, Sha3_256};
+
+        let mut hasher = Sha3_256::new();
+        hasher.update(self.contract_name.0.clone());
+        hasher.update(self.verifier.0.clone());
+        hasher.update(self.program_id.0.clone());
         let hash_bytes = hasher.finalize();
         TxHash(hex::encode(hash_bytes))
     }
@@ -2,6 +2,7 @@ use std::fmt::Display;
 
 use borsh::{BorshDeserialize, BorshSerialize};
 use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
 use sha3::{Digest, Sha3_256};
 use strum_macros::IntoStaticStr;
 use utoipa::ToSchema;
@@ -110,6 +111,79 @@ pub enum Ticket {
     TimeoutQC(QuorumCertificate),
 }
 
+/// How the leader of each slot and view is chosen among the bonded validators
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    BorshSerialize,
+    BorshDeserialize,
+    strum_macros::Display,
+    strum_macros::EnumString,
+)]
+pub enum LeaderElection {
+    /// Bonded validators lead in turn
+    RoundRobin,
+    /// Leaders are drawn with a probability proportional to their bonded stake, from a seed
+    /// signed by the previous leader
+    #[default]
+    StakeWeighted,
+}
+
+/// Parameters that all validators apply the same way, set in the genesis block by a
+/// [`SetConsensusParamsAction`] blob.
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+pub struct ConsensusParams {
+    #[serde_as(as = "serde_with::DisplayFromStr")]
+    pub leader_election: LeaderElection,
+    /// Percentage of the data dissemination fees that does not go to the bonded validators,
+    /// at most 100
+    pub fee_burn_percent: u8,
+    /// Validator credited with that share instead of burning it
+    pub fee_treasury: Option<ValidatorPublicKey>,
+}
+
+impl Default for ConsensusParams {
+    fn default() -> Self {
+        ConsensusParams {
+            leader_election: LeaderElection::default(),
+            fee_burn_percent: 50,
+            fee_treasury: None,
+        }
+    }
+}
+
+/// Used as a blob action of the 'hyle' TLD to set the consensus parameters.
+/// Only valid in the genesis block.
+#[derive(
+    Debug, Serialize, Deserialize, Default, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize,
+)]
+pub struct SetConsensusParamsAction {
+    pub params: ConsensusParams,
+}
+
+impl ContractAction for SetConsensusParamsAction {
+    fn as_blob(
+        &self,
+        contract_name: ContractName,
+        caller: Option<BlobIndex>,
+        callees: Option<Vec<BlobIndex>>,
+    ) -> Blob {
+        Blob {
+            contract_name,
+            data: BlobData::from(StructuredBlobData {
+                caller,
+                callees,
+                parameters: self.clone(),
+            }),
+        }
+    }
+}
+
 #[cfg(feature = "sqlx")]
 impl sqlx::Type<sqlx::Postgres> for ConsensusProposalHash {
     fn type_info() -> sqlx::postgres::PgTypeInfo {
@@ -170,6 +244,9 @@ pub struct ConsensusProposal {
     pub staking_actions: Vec<ConsensusStakingAction>,
     pub timestamp: u64,
     pub parent_hash: ConsensusProposalHash,
+    /// Signature of the leader over the slot and the current leader seed, from which the seed of
+    /// the next slot is derived. BLS signatures are unique, so the leader cannot choose it.
+    pub seed_signature: Signature,
 }
 
 /// This is the hash of the proposal, signed by validators
@@ -193,9 +270,23 @@ impl Hashed<ConsensusProposalHash> for ConsensusProposal {
                 hasher.update(&lane_id.0 .0);
                 hasher.update(cumul_size.0.to_le_bytes())
             }
+            ConsensusStakingAction::Unbond { validator } => {
+                hasher.update(b"unbond");
+                hasher.update(&validator.0)
+            }
+            ConsensusStakingAction::Slash { evidence } => {
+                hasher.update(b"slash");
+                hasher.update(&evidence.first.signature.signature.0);
+                hasher.update(&evidence.second.signature.signature.0)
+            }
+            ConsensusStakingAction::Jail { validator } => {
+                hasher.update(b"jail");
+                hasher.update(&validator.0)
+            }
         });
         hasher.update(self.timestamp.to_le_bytes());
         hasher.update(self.parent_hash.0.as_bytes());
+        hasher.update(&self.seed_signature.0);
         ConsensusProposalHash(hex::encode(hasher.finalize()))
     }
 }
@@ -232,6 +323,48 @@ pub enum ConsensusStakingAction {
         lane_id: LaneId,
         cumul_size: LaneBytesSize,
     },
+
+    /// Removing a validator from the consensus, once the slot is committed
+    Unbond { validator: ValidatorPublicKey },
+
+    /// Punishing a validator that signed conflicting messages
+    Slash {
+        // Boxed to reduce size of the enum
+        evidence: Box<EquivocationEvidence>,
+    },
+
+    /// Removing a validator that stopped voting from the consensus
+    Jail { validator: ValidatorPublicKey },
+}
+
+/// Two conflicting messages signed by the same validator for the same slot and view: two Prepare
+/// or Confirm messages of a leader for different proposals, or two PrepareVote or ConfirmAck
+/// messages of a voter for different proposals.
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    Debug,
+    Clone,
+    Deserialize,
+    Serialize,
+    PartialEq,
+    Eq,
+    Ord,
+    PartialOrd,
+)]
+pub struct EquivocationEvidence {
+    pub first: SignedByValidator<ConsensusNetMessage>,
+    pub second: SignedByValidator<ConsensusNetMessage>,
+    /// Proposals the two messages are for, which hold their slot and view.
+    /// Prepare messages carry their proposal, so this is only set for the other messages.
+    pub proposals: Option<(ConsensusProposal, ConsensusProposal)>,
+}
+
+impl EquivocationEvidence {
+    /// Validator that signed both messages
+    pub fn offender(&self) -> &ValidatorPublicKey {
+        &self.first.signature.validator
+    }
 }
 
 impl From<NewValidatorCandidate> for ConsensusStakingAction {
@@ -384,6 +517,7 @@ mod tests {
             staking_actions: vec![],
             timestamp: 1,
             parent_hash: ConsensusProposalHash("".to_string()),
+            seed_signature: Signature::default(),
         };
         let hash = proposal.hashed();
         assert_eq!(hash.0.len(), 64);
@@ -414,6 +548,7 @@ mod tests {
             .into()],
             timestamp: 1,
             parent_hash: ConsensusProposalHash("parent".to_string()),
+            seed_signature: Signature::default(),
         };
         let mut b = ConsensusProposal {
             slot: 1,
@@ -443,6 +578,7 @@ mod tests {
             .into()],
             timestamp: 1,
             parent_hash: ConsensusProposalHash("parent".to_string()),
+            seed_signature: Signature::default(),
         };
         assert_eq!(a.hashed(), b.hashed());
         a.timestamp = 2;
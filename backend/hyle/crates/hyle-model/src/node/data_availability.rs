@@ -15,6 +15,11 @@ pub struct Contract {
     pub program_id: ProgramId,
     pub state: StateDigest,
     pub verifier: Verifier,
+    /// Timeout window declared at registration
+    pub timeout_window: Option<BlockHeight>,
+    /// Identity that registered the contract through the 'hyle' TLD.
+    /// Only this identity can delete or upgrade the contract through the 'hyle' TLD.
+    pub owner: Option<Identity>,
 }
 
 #[derive(
@@ -38,6 +43,9 @@ pub struct UnsettledBlobTransaction {
     pub tx_context: Arc<TxContext>,
     pub blobs_hash: BlobsHash,
     pub blobs: Vec<UnsettledBlobMetadata>,
+    /// Number of blocks the transaction has to settle once it is the next one to settle
+    /// on all its contracts
+    pub timeout_window: BlockHeight,
 }
 
 #[derive(
@@ -122,6 +130,7 @@ impl Hashed<HyleOutputHash> for HyleOutput {
         self.onchain_effects.iter().for_each(|c| match c {
             OnchainEffect::RegisterContract(c) => hasher.update(contract::Hashed::hashed(c).0),
             OnchainEffect::DeleteContract(cn) => hasher.update(cn.0.as_bytes()),
+            OnchainEffect::UpdateContractProgram(c) => hasher.update(contract::Hashed::hashed(c).0),
         });
         hasher.update(&self.program_outputs);
         HyleOutputHash(hasher.finalize().to_vec())
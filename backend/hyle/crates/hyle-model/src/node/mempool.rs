@@ -20,7 +20,8 @@ pub enum MempoolStatusEvent {
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum MempoolBlockEvent {
-    BuiltSignedBlock(SignedBlock),
+    // Boxed to reduce size of the enum
+    BuiltSignedBlock(Box<SignedBlock>),
     StartedBuildingBlocks(BlockHeight),
 }
 
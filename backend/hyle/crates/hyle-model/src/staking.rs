@@ -29,6 +29,35 @@ pub enum StakingAction {
         holder: ValidatorPublicKey,
         amount: u128,
     },
+
+    /// Removes an amount from the stake of the caller. It can be withdrawn
+    /// once the unbonding delay is over.
+    Unstake {
+        amount: u128,
+    },
+    /// Stops delegating the stake of the caller to the validator
+    Undelegate {
+        validator: ValidatorPublicKey,
+    },
+    /// Asks for a validator the caller delegates to to leave the consensus
+    Unbond {
+        validator: ValidatorPublicKey,
+    },
+    /// Pays back unstaked amounts whose unbonding delay is over, with a
+    /// hyllar transfer from the staking contract to the caller
+    Withdraw {
+        amount: u128,
+    },
+
+    /// Pays the fees of the transaction, distributed to the bonded validators, with a hyllar
+    /// transfer to the staking contract in the next blob. When there is a sponsor, it pays
+    /// instead of the caller, with a `TransferFrom` it approved.
+    /// The amount is binding: settlement only checks the transfer, never a price, so the
+    /// transaction settles whatever the price of a byte when it is sequenced.
+    PayTxFees {
+        amount: u128,
+        sponsor: Option<Identity>,
+    },
 }
 
 impl ContractAction for StakingAction {
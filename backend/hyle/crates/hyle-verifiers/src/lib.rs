@@ -1,7 +1,7 @@
 #![warn(unused_crate_dependencies)]
 
 use std::fmt::Write;
-use std::io::Read;
+use std::path::PathBuf;
 
 use anyhow::{bail, Context, Error};
 use hyle_model::{HyleOutput, ProgramId};
@@ -14,6 +14,7 @@ mod noir_utils;
 
 pub mod risc0 {
     pub use risc0_zkvm::serde::from_slice;
+    pub use risc0_zkvm::Journal;
 }
 
 pub mod versions {
@@ -41,9 +42,21 @@ pub fn risc0_proof_verifier(
     Ok(receipt.journal)
 }
 
-/// At present, we are using binary to facilitate the integration of the Noir verifier.
-/// This is not meant to be a permanent solution.
+/// Decodes the journal of a Risc0 proof without verifying it, to look at what it claims.
+pub fn risc0_unverified_journal(encoded_receipt: &[u8]) -> Result<risc0_zkvm::Journal, Error> {
+    let receipt = borsh::from_slice::<risc0_zkvm::Receipt>(encoded_receipt)
+        .context("Error while decoding Risc0 proof's receipt")?;
+    Ok(receipt.journal)
+}
+
+/// Verifies a Noir proof and returns the HyleOutputs committed to by its public inputs.
+///
+/// The outputs are read from the proof in process, but the proof itself is still checked by
+/// the `bb` binary, as no library linkable from Rust verifies Barretenberg proofs yet.
 pub fn noir_proof_verifier(proof: &[u8], image_id: &[u8]) -> Result<Vec<HyleOutput>, Error> {
+    let public_outputs = noir_utils::extract_public_inputs(proof, image_id)?;
+    let hyle_outputs = noir_utils::parse_noir_outputs(public_outputs)?;
+
     let mut rng = rand::rng();
     let salt: [u8; 16] = rng.random();
     let mut salt_hex = String::with_capacity(salt.len() * 2);
@@ -51,22 +64,24 @@ pub fn noir_proof_verifier(proof: &[u8], image_id: &[u8]) -> Result<Vec<HyleOutp
         write!(salt_hex, "{:02x}", b).unwrap();
     }
 
-    let proof_path = &format!("/tmp/noir-proof-{salt_hex}");
-    let vk_path = &format!("/tmp/noir-vk-{salt_hex}");
-    let output_path = &format!("/tmp/noir-output-{salt_hex}");
+    let proof_path = std::env::temp_dir().join(format!("noir-proof-{salt_hex}"));
+    let vk_path = std::env::temp_dir().join(format!("noir-vk-{salt_hex}"));
+    let _files = TempFiles(vec![proof_path.clone(), vk_path.clone()]);
 
     // Write proof and publicKey to files
-    std::fs::write(proof_path, proof)?;
-    std::fs::write(vk_path, image_id)?;
+    std::fs::write(&proof_path, proof)?;
+    std::fs::write(&vk_path, image_id)?;
 
     // Verifying proof
+    // TODO: verify UltraHonk proofs in process, nodes without `bb` in their PATH reject all Noir proofs
     let verification_output = std::process::Command::new("bb")
         .arg("verify")
         .arg("-p")
-        .arg(proof_path)
+        .arg(&proof_path)
         .arg("-k")
-        .arg(vk_path)
-        .output()?;
+        .arg(&vk_path)
+        .output()
+        .context("Could not run bb to verify the Noir proof, is it installed and in the PATH?")?;
 
     if !verification_output.status.success() {
         bail!(
@@ -75,39 +90,20 @@ pub fn noir_proof_verifier(proof: &[u8], image_id: &[u8]) -> Result<Vec<HyleOutp
         );
     }
 
-    // Extracting outputs
-    let public_outputs_output = std::process::Command::new("bb")
-        .arg("proof_as_fields")
-        .arg("-p")
-        .arg(proof_path)
-        .arg("-k")
-        .arg(vk_path)
-        .arg("-o")
-        .arg(output_path)
-        .output()?;
+    tracing::info!("✅ Noir proof verified.");
 
-    if !public_outputs_output.status.success() {
-        bail!(
-            "Could not extract output from Noir proof: {}",
-            String::from_utf8_lossy(&verification_output.stderr)
-        );
-    }
+    Ok(hyle_outputs)
+}
+
+/// Files removed when dropped, so that none are left behind on error paths.
+struct TempFiles(Vec<PathBuf>);
 
-    // Reading output
-    let mut file = std::fs::File::open(output_path).context("Failed to open output file")?;
-    let mut output_json = String::new();
-    file.read_to_string(&mut output_json)
-        .context("Failed to read output file content")?;
-
-    let mut public_outputs: Vec<String> = serde_json::from_str(&output_json)?;
-    // TODO: support multi-output proofs.
-    let hyle_output = crate::noir_utils::parse_noir_output(&mut public_outputs)?;
-
-    // Delete proof_path, vk_path, output_path
-    let _ = std::fs::remove_file(proof_path);
-    let _ = std::fs::remove_file(vk_path);
-    let _ = std::fs::remove_file(output_path);
-    Ok(vec![hyle_output])
+impl Drop for TempFiles {
+    fn drop(&mut self) {
+        for path in &self.0 {
+            let _ = std::fs::remove_file(path);
+        }
+    }
 }
 
 /// The following environment variables are used to configure the prover:
@@ -132,13 +128,16 @@ pub fn sp1_proof_verifier(
         .verify(&proof, &vk)
         .context("SP1 proof verification failed")?;
 
-    // TODO: support multi-output proofs.
-    let hyle_output = borsh::from_slice::<HyleOutput>(proof.public_values.as_slice())
-        .context("Failed to extract HyleOuput from SP1 proof")?;
+    // First try to decode it as a single HyleOutput
+    let hyle_outputs = match borsh::from_slice::<HyleOutput>(proof.public_values.as_slice()) {
+        Ok(hyle_output) => vec![hyle_output],
+        Err(_) => borsh::from_slice::<Vec<HyleOutput>>(proof.public_values.as_slice())
+            .context("Failed to extract HyleOuput from SP1 proof")?,
+    };
 
     tracing::info!("✅ SP1 proof verified.",);
 
-    Ok(vec![hyle_output])
+    Ok(hyle_outputs)
 }
 
 pub fn validate_risc0_program_id(program_id: &ProgramId) -> Result<(), Error> {
@@ -208,7 +207,25 @@ mod tests {
             success = 1
         ```
     */
-    #[ignore = "manual test"]
+    fn webauthn_output() -> HyleOutput {
+        HyleOutput {
+            version: 1,
+            initial_state: StateDigest(vec![0, 0, 0, 0]),
+            next_state: StateDigest(vec![0, 0, 0, 0]),
+            identity: Identity(
+                "3f368bf90c71946fc7b0cde9161ace42985d235f.ecdsa_secp256r1".to_owned(),
+            ),
+            index: BlobIndex(0),
+            blobs: vec![1, 1, 1, 1, 1],
+            success: true,
+            tx_hash: TxHash::default(), // TODO
+            tx_ctx: None,
+            onchain_effects: vec![],
+            program_outputs: vec![],
+        }
+    }
+
+    #[ignore = "manual test, needs the bb binary"]
     #[test_log::test]
     fn test_noir_proof_verifier() {
         let noir_proof = load_file_as_bytes("./tests/proofs/webauthn.noir.proof");
@@ -217,29 +234,36 @@ mod tests {
         let result = noir_proof_verifier(&noir_proof, &image_id);
         match result {
             Ok(outputs) => {
-                assert_eq!(
-                    outputs,
-                    vec![HyleOutput {
-                        version: 1,
-                        initial_state: StateDigest(vec![0, 0, 0, 0]),
-                        next_state: StateDigest(vec![0, 0, 0, 0]),
-                        identity: Identity(
-                            "3f368bf90c71946fc7b0cde9161ace42985d235f.ecdsa_secp256r1".to_owned()
-                        ),
-                        index: BlobIndex(0),
-                        blobs: vec![1, 1, 1, 1, 1],
-                        success: true,
-                        tx_hash: TxHash::default(), // TODO
-                        tx_ctx: None,
-                        onchain_effects: vec![],
-                        program_outputs: vec![]
-                    }]
-                );
+                assert_eq!(outputs, vec![webauthn_output()]);
             }
             Err(e) => panic!("Noir verification failed: {:?}", e),
         }
     }
 
+    #[test]
+    fn test_noir_public_outputs() {
+        let noir_proof = load_file_as_bytes("./tests/proofs/webauthn.noir.proof");
+        let image_id = load_file_as_bytes("./tests/proofs/webauthn.noir.vk");
+
+        let public_outputs = crate::noir_utils::extract_public_inputs(&noir_proof, &image_id)
+            .expect("extracting public inputs");
+        assert_eq!(public_outputs.len(), 82);
+
+        let outputs =
+            crate::noir_utils::parse_noir_outputs(public_outputs).expect("parsing outputs");
+        assert_eq!(outputs, vec![webauthn_output()]);
+    }
+
+    #[test]
+    fn test_noir_truncated_proof() {
+        let noir_proof = load_file_as_bytes("./tests/proofs/webauthn.noir.proof");
+        let image_id = load_file_as_bytes("./tests/proofs/webauthn.noir.vk");
+
+        // Rejected before bb is run
+        assert!(noir_proof_verifier(noir_proof.get(..1000).unwrap(), &image_id).is_err());
+        assert!(noir_proof_verifier(&noir_proof, image_id.get(..4).unwrap()).is_err());
+    }
+
     #[test]
     fn test_check_risc0_program_id() {
         let valid_program_id = ProgramId(vec![0; 32]); // Assuming a valid 32-byte ID
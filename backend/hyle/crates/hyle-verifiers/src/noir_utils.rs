@@ -1,21 +1,64 @@
 use std::collections::VecDeque;
 
-use anyhow::{Context, Error};
-use hyle_model::{
-    BlobIndex, BlockHeight, ConsensusProposalHash, HyleOutput, StateDigest, TxHash,
-    HYLE_TESTNET_CHAIN_ID,
-};
-
-pub fn parse_noir_output(vector: &mut Vec<String>) -> Result<HyleOutput, Error> {
-    let version = u32::from_str_radix(vector.remove(0).strip_prefix("0x").context("parsing")?, 16)?;
+use anyhow::{bail, Context, Error};
+use hyle_model::{BlobIndex, HyleOutput, StateDigest, TxHash};
+
+/// Size in bytes of the field elements of Noir proofs and verification keys.
+const FIELD_SIZE: usize = 32;
+
+/// Extracts the public inputs of a proof written by `bb prove`, as hex encoded fields.
+///
+/// The public inputs are prepended to the proof. Their number is read from the verification
+/// key, whose header holds the circuit type, the circuit size and the number of public inputs,
+/// as big-endian u32.
+pub fn extract_public_inputs(proof: &[u8], vk: &[u8]) -> Result<Vec<String>, Error> {
+    let num_public_inputs = vk
+        .get(8..12)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u32::from_be_bytes)
+        .context("Noir verification key is too short")? as usize;
+
+    let Some(public_inputs) = proof.get(..num_public_inputs * FIELD_SIZE) else {
+        bail!(
+            "Noir proof is too short to hold {} public inputs",
+            num_public_inputs
+        );
+    };
+
+    Ok(public_inputs
+        .chunks(FIELD_SIZE)
+        .map(|field| {
+            let hex: String = field.iter().map(|byte| format!("{:02x}", byte)).collect();
+            format!("0x{hex}")
+        })
+        .collect())
+}
+
+/// Parses the public inputs of a Noir proof into the HyleOutputs they commit to.
+/// A proof can hold several outputs, laid out one after the other.
+pub fn parse_noir_outputs(vector: Vec<String>) -> Result<Vec<HyleOutput>, Error> {
+    let mut vector = VecDeque::from(vector);
+    let mut outputs = vec![];
+    while !vector.is_empty() {
+        let output = parse_noir_output(&mut vector)
+            .with_context(|| format!("parsing Noir output {}", outputs.len()))?;
+        outputs.push(output);
+    }
+    if outputs.is_empty() {
+        bail!("Noir proof has no public inputs");
+    }
+    Ok(outputs)
+}
+
+fn parse_noir_output(vector: &mut VecDeque<String>) -> Result<HyleOutput, Error> {
+    let version = parse_u32(vector)?;
     let initial_state = parse_array(vector)?;
     let next_state = parse_array(vector)?;
     let identity = parse_string(vector)?;
     let tx_hash = parse_string(vector)?;
-    let index = u32::from_str_radix(vector.remove(0).strip_prefix("0x").context("parsing")?, 16)?;
+    let index = parse_u32(vector)?;
     let blobs = parse_blobs(vector)?;
-    let success =
-        u32::from_str_radix(vector.remove(0).strip_prefix("0x").context("parsing")?, 16)? == 1;
+    let success = parse_u32(vector)? == 1;
 
     Ok(HyleOutput {
         version,
@@ -23,13 +66,8 @@ pub fn parse_noir_output(vector: &mut Vec<String>) -> Result<HyleOutput, Error>
         next_state: StateDigest(next_state),
         identity: identity.into(),
         tx_hash: TxHash(tx_hash),
-        tx_ctx: Some(hyle_model::TxContext {
-            // TODO
-            block_hash: ConsensusProposalHash::default(),
-            block_height: BlockHeight(0),
-            timestamp: 1,
-            chain_id: HYLE_TESTNET_CHAIN_ID,
-        }),
+        // Noir programs do not commit to the transaction context
+        tx_ctx: None,
         index: BlobIndex(index as usize),
         blobs,
         success,
@@ -38,13 +76,26 @@ pub fn parse_noir_output(vector: &mut Vec<String>) -> Result<HyleOutput, Error>
     })
 }
 
-fn parse_string(vector: &mut Vec<String>) -> Result<String, Error> {
-    let length =
-        usize::from_str_radix(vector.remove(0).strip_prefix("0x").context("parsing")?, 16)?;
+fn parse_field(vector: &mut VecDeque<String>) -> Result<String, Error> {
+    let field = vector
+        .pop_front()
+        .ok_or_else(|| anyhow::anyhow!("Missing public input"))?;
+    Ok(field.strip_prefix("0x").context("parsing")?.to_string())
+}
+
+fn parse_u32(vector: &mut VecDeque<String>) -> Result<u32, Error> {
+    Ok(u32::from_str_radix(&parse_field(vector)?, 16)?)
+}
+
+fn parse_usize(vector: &mut VecDeque<String>) -> Result<usize, Error> {
+    Ok(usize::from_str_radix(&parse_field(vector)?, 16)?)
+}
+
+fn parse_string(vector: &mut VecDeque<String>) -> Result<String, Error> {
+    let length = parse_usize(vector)?;
     let mut resp = String::with_capacity(length);
     for _ in 0..length {
-        let code =
-            u32::from_str_radix(vector.remove(0).strip_prefix("0x").context("parsing")?, 16)?;
+        let code = parse_u32(vector)?;
         let ch = std::char::from_u32(code)
             .ok_or_else(|| anyhow::anyhow!("Invalid char code: {}", code))?;
         resp.push(ch);
@@ -52,54 +103,122 @@ fn parse_string(vector: &mut Vec<String>) -> Result<String, Error> {
     Ok(resp)
 }
 
-fn parse_array(vector: &mut Vec<String>) -> Result<Vec<u8>, Error> {
-    let length =
-        usize::from_str_radix(vector.remove(0).strip_prefix("0x").context("parsing")?, 16)?;
+fn parse_array(vector: &mut VecDeque<String>) -> Result<Vec<u8>, Error> {
+    let length = parse_usize(vector)?;
     let mut resp = Vec::with_capacity(length);
     for _ in 0..length {
-        let num = u8::from_str_radix(vector.remove(0).strip_prefix("0x").context("parsing")?, 16)?;
+        let num = u8::from_str_radix(&parse_field(vector)?, 16)?;
         resp.push(num);
     }
     Ok(resp)
 }
 
-fn parse_blobs(vector: &mut Vec<String>) -> Result<Vec<u8>, Error> {
-    let _blob_len =
-        usize::from_str_radix(vector.remove(0).strip_prefix("0x").context("parsing")?, 16)?;
+fn parse_blobs(vector: &mut VecDeque<String>) -> Result<Vec<u8>, Error> {
+    let _blob_len = parse_usize(vector)?;
     // Arbitrary value that says that blobs field is size for only 10 elements
-    let blob_data: Vec<String> = vector.drain(0..10.min(vector.len())).collect();
-    let mut blob_data = VecDeque::from(blob_data);
-
-    let blob_number = usize::from_str_radix(
-        blob_data
-            .pop_front()
-            .ok_or_else(|| anyhow::anyhow!("Missing blob number"))?
-            .strip_prefix("0x")
-            .context("parsing")?,
-        16,
-    )?;
+    let mut blob_data: VecDeque<String> = vector.drain(0..10.min(vector.len())).collect();
+
+    let blob_number = parse_usize(&mut blob_data).context("Missing blob number")?;
     let mut blobs = Vec::new();
 
     for _ in 0..blob_number {
-        let blob_size = usize::from_str_radix(
-            blob_data
-                .pop_front()
-                .ok_or_else(|| anyhow::anyhow!("Missing blob size"))?
-                .strip_prefix("0x")
-                .context("parsing")?,
-            16,
-        )?;
+        let blob_size = parse_usize(&mut blob_data).context("Missing blob size")?;
 
         for _ in 0..blob_size {
-            let v = &blob_data
-                .pop_front()
-                .ok_or_else(|| anyhow::anyhow!("Missing blob data"))?;
-            blobs.push(u8::from_str_radix(
-                v.strip_prefix("0x").context("parsing")?,
-                16,
-            )?);
+            let v = parse_field(&mut blob_data).context("Missing blob data")?;
+            blobs.push(u8::from_str_radix(&v, 16)?);
         }
     }
 
     Ok(blobs)
 }
+
+#[cfg(test)]
+mod tests {
+    use hyle_model::{BlobIndex, HyleOutput, Identity, StateDigest, TxHash};
+
+    use super::{extract_public_inputs, parse_noir_outputs};
+
+    fn fields(values: &[u32]) -> Vec<String> {
+        values.iter().map(|v| format!("0x{:064x}", v)).collect()
+    }
+
+    fn output_fields(identity: &str, success: bool) -> Vec<u32> {
+        let mut values = vec![1, 4, 0, 0, 0, 0, 2, 1, 2];
+        values.push(identity.len() as u32);
+        values.extend(identity.chars().map(|c| c as u32));
+        // tx_hash, index
+        values.extend([0, 1]);
+        // blobs_len, then 10 blob fields
+        values.extend([4, 1, 3, 4, 5, 6, 0, 0, 0, 0, 0]);
+        values.push(success as u32);
+        values
+    }
+
+    fn expected_output(identity: &str, success: bool) -> HyleOutput {
+        HyleOutput {
+            version: 1,
+            initial_state: StateDigest(vec![0, 0, 0, 0]),
+            next_state: StateDigest(vec![1, 2]),
+            identity: Identity(identity.to_owned()),
+            index: BlobIndex(1),
+            blobs: vec![4, 5, 6],
+            success,
+            tx_hash: TxHash::default(),
+            tx_ctx: None,
+            onchain_effects: vec![],
+            program_outputs: vec![],
+        }
+    }
+
+    #[test]
+    fn test_parse_single_output() {
+        let outputs = parse_noir_outputs(fields(&output_fields("bob.wallet", true)))
+            .expect("parsing outputs");
+        assert_eq!(outputs, vec![expected_output("bob.wallet", true)]);
+    }
+
+    #[test]
+    fn test_parse_multiple_outputs() {
+        let values = [
+            output_fields("bob.wallet", true),
+            output_fields("alice.wallet", false),
+        ]
+        .concat();
+        let outputs = parse_noir_outputs(fields(&values)).expect("parsing outputs");
+        assert_eq!(
+            outputs,
+            vec![
+                expected_output("bob.wallet", true),
+                expected_output("alice.wallet", false)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_truncated_outputs() {
+        let mut values = output_fields("bob.wallet", true);
+        assert!(parse_noir_outputs(vec![]).is_err());
+
+        // A second output cut in the middle of its identity
+        values.extend(&output_fields("alice.wallet", true)[..12]);
+        assert!(parse_noir_outputs(fields(&values)).is_err());
+
+        assert!(parse_noir_outputs(vec!["12".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_extract_public_inputs() {
+        let vk = [[0, 0, 0, 2], [0, 0, 16, 0], [0, 0, 0, 2]].concat();
+        let mut proof = vec![0; 64];
+        proof[31] = 1;
+        proof[63] = 0xab;
+        proof.extend([7; 100]);
+
+        let public_inputs = extract_public_inputs(&proof, &vk).expect("extracting");
+        assert_eq!(public_inputs, fields(&[1, 0xab]));
+
+        assert!(extract_public_inputs(&proof[..63], &vk).is_err());
+        assert!(extract_public_inputs(&proof, &vk[..10]).is_err());
+    }
+}
@@ -2,16 +2,12 @@ use anyhow::{bail, Context, Result};
 use axum::Router;
 use axum_otel_metrics::HttpMetricsLayerBuilder;
 use clap::Parser;
-use hydentity::Hydentity;
 use hyle::{
     bus::{metrics::BusMetrics, SharedMessageBus},
     consensus::Consensus,
     data_availability::DataAvailability,
     genesis::Genesis,
-    indexer::{
-        contract_state_indexer::{ContractStateIndexer, ContractStateIndexerCtx},
-        Indexer,
-    },
+    indexer::{contract_state_indexer::build_contract_state_indexers, Indexer},
     mempool::Mempool,
     model::{api::NodeInfo, CommonRunContext, NodeRunContext, SharedRunContext},
     node_state::module::NodeStateModule,
@@ -27,7 +23,6 @@ use hyle::{
         modules::ModulesHandler,
     },
 };
-use hyllar::Hyllar;
 use std::sync::{Arc, Mutex};
 use testcontainers_modules::{
     postgres::Postgres,
@@ -154,24 +149,7 @@ async fn main() -> Result<()> {
 
     if run_indexer {
         handler.build_module::<Indexer>(ctx.common.clone()).await?;
-        handler
-            .build_module::<ContractStateIndexer<Hyllar>>(ContractStateIndexerCtx {
-                contract_name: "hyllar".into(),
-                common: ctx.common.clone(),
-            })
-            .await?;
-        handler
-            .build_module::<ContractStateIndexer<Hyllar>>(ContractStateIndexerCtx {
-                contract_name: "hyllar2".into(),
-                common: ctx.common.clone(),
-            })
-            .await?;
-        handler
-            .build_module::<ContractStateIndexer<Hydentity>>(ContractStateIndexerCtx {
-                contract_name: "hydentity".into(),
-                common: ctx.common.clone(),
-            })
-            .await?;
+        build_contract_state_indexers(&mut handler, &ctx.common).await?;
     }
 
     handler
@@ -52,6 +52,13 @@ where
     Res: Clone + Send + Sync + 'static,
 {
     fn request(&mut self, cmd: Cmd) -> impl std::future::Future<Output = Result<Res>> + Send;
+
+    /// Sends the query right away, and returns a future resolving to its answer.
+    /// Queries are received in the order they were sent, while their answers can be awaited concurrently.
+    fn send_request(
+        &mut self,
+        cmd: Cmd,
+    ) -> impl std::future::Future<Output = Result<Res>> + Send + 'static;
 }
 
 impl<Cmd, Res, T: BusClientSender<Query<Cmd, Res>> + Send> CmdRespClient<Cmd, Res> for T
@@ -60,6 +67,13 @@ where
     Res: Clone + Send + Sync + 'static,
 {
     async fn request(&mut self, cmd: Cmd) -> Result<Res> {
+        self.send_request(cmd).await
+    }
+
+    fn send_request(
+        &mut self,
+        cmd: Cmd,
+    ) -> impl std::future::Future<Output = Result<Res>> + Send + 'static {
         let (tx, rx) = tokio::sync::oneshot::channel();
         let query_cmd = Query(Arc::new(Mutex::new(Some(InnerQuery {
             callback: tx,
@@ -68,13 +82,15 @@ where
 
         _ = self.send(query_cmd);
 
-        match tokio::time::timeout(Duration::from_secs(CLIENT_TIMEOUT_SECONDS), rx).await {
-            Ok(Ok(res)) => res,
-            Ok(Err(e)) => bail!("Error while calling topic: {}", e),
-            Err(timeouterror) => bail!(
-                "Timeout triggered while calling topic with query: {}",
-                timeouterror.to_string()
-            ),
+        async move {
+            match tokio::time::timeout(Duration::from_secs(CLIENT_TIMEOUT_SECONDS), rx).await {
+                Ok(Ok(res)) => res,
+                Ok(Err(e)) => bail!("Error while calling topic: {}", e),
+                Err(timeouterror) => bail!(
+                    "Timeout triggered while calling topic with query: {}",
+                    timeouterror.to_string()
+                ),
+            }
         }
     }
 }
@@ -207,4 +223,25 @@ mod test {
 
         assert_eq!(res.await.unwrap(), 3);
     }
+
+    #[tokio::test]
+    async fn test_send_request_concurrently() {
+        let shared_bus = SharedMessageBus::default();
+        let mut sender = TestBusClient::new_from_bus(shared_bus.new_handle()).await;
+        let mut receiver = TestBusClient::new_from_bus(shared_bus).await;
+
+        tokio::spawn(async move {
+            handle_messages! {
+                on_bus receiver,
+                command_response<i32, u8> value => {
+                    Ok(*value as u8)
+                }
+            }
+        });
+        let first = sender.send_request(1);
+        let second = sender.send_request(2);
+
+        assert_eq!(second.await.unwrap(), 2);
+        assert_eq!(first.await.unwrap(), 1);
+    }
 }
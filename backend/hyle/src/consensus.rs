@@ -26,7 +26,12 @@ use role_follower::{FollowerRole, FollowerState};
 use role_leader::{LeaderRole, LeaderState};
 use role_timeout::{TimeoutRole, TimeoutRoleState, TimeoutState};
 use serde::{Deserialize, Serialize};
-use staking::state::{Staking, MIN_STAKE};
+use sha3::{Digest, Sha3_256};
+use slashing::SlashingState;
+use staking::{
+    fees::FeeSplit,
+    state::{Staking, MIN_STAKE},
+};
 use std::ops::Deref;
 use std::ops::DerefMut;
 use std::time::Duration;
@@ -42,6 +47,7 @@ pub mod module;
 pub mod role_follower;
 pub mod role_leader;
 pub mod role_timeout;
+pub mod slashing;
 
 // -----------------------------
 // ------ Consensus bus --------
@@ -131,6 +137,19 @@ pub struct ConsensusStore {
     bft_round_state: BFTRoundState,
     /// Validators that asked to be part of consensus
     validator_candidates: Vec<NewValidatorCandidate>,
+    /// Misbehaviours of the validators waiting to be punished
+    slashing: SlashingState,
+    /// Hash of the seed signature of the last committed proposal, from which leaders are elected
+    leader_seed: Vec<u8>,
+    /// Parameters set in the genesis block
+    params: ConsensusParams,
+}
+
+/// Message signed by the leader of a slot, from which the leader seed of the next slot is derived
+#[derive(BorshSerialize)]
+struct LeaderSeedMessage {
+    slot: Slot,
+    seed: Vec<u8>,
 }
 
 pub struct Consensus {
@@ -156,26 +175,76 @@ impl DerefMut for Consensus {
 }
 
 impl Consensus {
+    /// Leader of the next view of the current slot, should the current view time out
     fn next_leader(&self) -> Result<ValidatorPublicKey> {
-        // Find out who the next leader will be.
-        let leader_index = self
-            .bft_round_state
-            .staking
-            .bonded()
-            .iter()
-            .position(|v| v == &self.bft_round_state.consensus_proposal.round_leader)
-            .context(format!(
-                "Leader {} not found in validators",
-                &self.bft_round_state.consensus_proposal.round_leader,
-            ))?;
+        let proposal = &self.bft_round_state.consensus_proposal;
+        self.elect_leader(proposal.slot, proposal.view + 1)
+    }
 
-        Ok(self
-            .bft_round_state
-            .staking
-            .bonded()
-            .get((leader_index + 1) % self.bft_round_state.staking.bonded().len())
-            .context("No next leader found")?
-            .clone())
+    /// Find out who leads a view of a slot.
+    fn elect_leader(&self, slot: Slot, view: View) -> Result<ValidatorPublicKey> {
+        let bonded = self.bft_round_state.staking.bonded();
+        let leader = match self.store.params.leader_election {
+            // Bonded validators are sorted, so the next leader is the first one after the current
+            // leader, who may just have been unbonded.
+            LeaderElection::RoundRobin => bonded
+                .iter()
+                .find(|v| *v > &self.bft_round_state.consensus_proposal.round_leader)
+                .or(bonded.first()),
+            // The seed is only known once the previous slot is committed, so leaders cannot be
+            // targeted far ahead, and it changes with the view so that a timeout elects a new leader.
+            // It comes from a unique signature of the previous leader, that no one can grind.
+            LeaderElection::StakeWeighted => {
+                let mut hasher = Sha3_256::new();
+                hasher.update(&self.store.leader_seed);
+                hasher.update(slot.to_le_bytes());
+                hasher.update(view.to_le_bytes());
+                let draw = hasher
+                    .finalize()
+                    .first_chunk::<16>()
+                    .copied()
+                    .unwrap_or_default();
+                let total_bond = self.bft_round_state.staking.compute_voting_power(bonded);
+                let mut draw = u128::from_le_bytes(draw) % total_bond.max(1);
+                bonded.iter().find(|v| {
+                    let stake = self.bft_round_state.staking.get_stake(v).unwrap_or(0);
+                    if draw < stake {
+                        return true;
+                    }
+                    draw -= stake;
+                    false
+                })
+            }
+        };
+        Ok(leader.context("No next leader found")?.clone())
+    }
+
+    /// Signature of the current slot and leader seed, for the proposal of this node as a leader
+    fn sign_leader_seed(&self) -> Result<Signature> {
+        let signed = self.crypto.sign(LeaderSeedMessage {
+            slot: self.bft_round_state.consensus_proposal.slot,
+            seed: self.store.leader_seed.clone(),
+        })?;
+        Ok(signed.signature.signature)
+    }
+
+    /// Verifies that the seed signature of a proposal is its leader's signature of the proposal
+    /// slot and the current leader seed.
+    fn verify_seed_signature(&self, proposal: &ConsensusProposal) -> Result<()> {
+        let signed = SignedByValidator {
+            msg: LeaderSeedMessage {
+                slot: proposal.slot,
+                seed: self.store.leader_seed.clone(),
+            },
+            signature: ValidatorSignature {
+                signature: proposal.seed_signature.clone(),
+                validator: proposal.round_leader.clone(),
+            },
+        };
+        if !BlstCrypto::verify(&signed)? {
+            bail!("Invalid leader seed signature");
+        }
+        Ok(())
     }
 
     /// Reset bft_round_state for the next round of consensus.
@@ -192,6 +261,8 @@ impl Consensus {
 
         let staking_actions =
             std::mem::take(&mut self.bft_round_state.consensus_proposal.staking_actions);
+        let seed_signature =
+            std::mem::take(&mut self.bft_round_state.consensus_proposal.seed_signature);
 
         // Reset round state, carrying over staking and current proposal.
         self.bft_round_state = BFTRoundState {
@@ -209,13 +280,21 @@ impl Consensus {
             ..BFTRoundState::default()
         };
 
+        let round_leader = self.bft_round_state.consensus_proposal.round_leader.clone();
+
         // If we finish the round via a committed proposal, update some state
         match ticket {
             Some(Ticket::CommitQC(qc)) => {
+                let committed_slot = self.bft_round_state.consensus_proposal.slot;
                 self.bft_round_state.consensus_proposal.parent_hash = round_proposal_hash;
                 self.bft_round_state.consensus_proposal.slot += 1;
                 self.bft_round_state.consensus_proposal.view = 0;
-                self.bft_round_state.follower.buffered_quorum_certificate = Some(qc);
+                self.bft_round_state.follower.buffered_quorum_certificate = Some(qc.clone());
+                self.store.leader_seed = Sha3_256::digest(&seed_signature.0).to_vec();
+                let fee_split = FeeSplit {
+                    burn_percent: self.store.params.fee_burn_percent.into(),
+                    treasury: self.store.params.fee_treasury.clone(),
+                };
                 let staking = &mut self.store.bft_round_state.staking;
                 for action in staking_actions {
                     match action {
@@ -232,11 +311,31 @@ impl Consensus {
                         } => staking
                             .pay_for_dadi(lane_id, cumul_size)
                             .map_err(|e| anyhow::anyhow!(e))?,
+                        // Validators leave once the slot is committed, before the next leader is chosen.
+                        ConsensusStakingAction::Unbond { validator } => {
+                            debug!("👋 Validator unbonded: {}", validator);
+                            staking.unbond(&validator).map_err(|e| anyhow::anyhow!(e))?;
+                        }
+                        ConsensusStakingAction::Slash { evidence } => {
+                            staking
+                                .slash(evidence.offender(), BlockHeight(committed_slot))
+                                .map_err(|e| anyhow::anyhow!(e))?;
+                        }
+                        ConsensusStakingAction::Jail { validator } => {
+                            warn!("🔒 Validator jailed: {}", validator);
+                            staking.unbond(&validator).map_err(|e| anyhow::anyhow!(e))?;
+                        }
                     }
                 }
-                staking.distribute().map_err(|e| anyhow::anyhow!(e))?;
+                staking
+                    .distribute(&fee_split)
+                    .map_err(|e| anyhow::anyhow!(e))?;
+                // Stakes that changed during the slot count from the next one
+                staking.update_total_bond();
+                self.on_slot_committed(committed_slot, &round_leader);
             }
             Some(Ticket::TimeoutQC(_)) => {
+                self.on_view_timed_out(&round_leader);
                 self.bft_round_state.consensus_proposal.parent_hash = round_parent_hash;
                 self.bft_round_state.consensus_proposal.view += 1;
             }
@@ -251,7 +350,10 @@ impl Consensus {
             self.bft_round_state.consensus_proposal.view
         );
 
-        self.bft_round_state.consensus_proposal.round_leader = self.next_leader()?;
+        self.bft_round_state.consensus_proposal.round_leader = self.elect_leader(
+            self.bft_round_state.consensus_proposal.slot,
+            self.bft_round_state.consensus_proposal.view,
+        )?;
 
         if self.bft_round_state.consensus_proposal.round_leader == *self.crypto.validator_pubkey() {
             self.bft_round_state.state_tag = StateTag::Leader;
@@ -282,6 +384,7 @@ impl Consensus {
     }
 
     fn verify_staking_actions(&mut self, proposal: &ConsensusProposal) -> Result<()> {
+        let mut leaving = 0;
         for action in &proposal.staking_actions {
             match action {
                 ConsensusStakingAction::Bond { candidate } => {
@@ -291,8 +394,46 @@ impl Consensus {
                     lane_id,
                     cumul_size,
                 } => Self::verify_dadi_fees(&proposal.cut, lane_id, cumul_size)?,
+                ConsensusStakingAction::Unbond { validator } => {
+                    leaving += 1;
+                    self.verify_validator_to_unbond(validator)?;
+                }
+                ConsensusStakingAction::Slash { evidence } => {
+                    leaving += 1;
+                    self.verify_equivocation_evidence(evidence)?;
+                }
+                ConsensusStakingAction::Jail { validator } => {
+                    leaving += 1;
+                    self.verify_validator_to_jail(validator)?;
+                }
             }
         }
+        // Validators leave one at a time, so that the quorum never changes by more than one
+        // validator between two slots.
+        if leaving > 1 {
+            bail!(
+                "Only one validator can be unbonded per slot, got {}",
+                leaving
+            );
+        }
+        Ok(())
+    }
+
+    /// Verify that a validator can leave the consensus: it asked to, or no longer has enough stake.
+    fn verify_validator_to_unbond(&self, validator: &ValidatorPublicKey) -> Result<()> {
+        let staking = &self.bft_round_state.staking;
+        if !staking.is_bonded(validator) {
+            bail!("Unbonded validator {} is not bonded", validator);
+        }
+        if staking.bonded().len() <= 1 {
+            bail!("Cannot unbond the last bonded validator {}", validator);
+        }
+        if !staking.should_unbond(validator) {
+            bail!(
+                "Validator {} did not ask to unbond and has enough stake",
+                validator
+            );
+        }
         Ok(())
     }
 
@@ -504,6 +645,8 @@ impl Consensus {
             ..
         } = msg.clone();
 
+        self.record_signed_message(&msg);
+
         match net_message {
             ConsensusNetMessage::Prepare(consensus_proposal, ticket) => {
                 self.on_prepare(sender, consensus_proposal, ticket)
@@ -729,30 +872,72 @@ impl Consensus {
                 let block_total_tx = block.total_txs();
                 for action in block.staking_actions {
                     match action {
-                        (identity, StakingAction::Stake { amount }) => {
+                        (identity, StakingAction::Stake { amount }, _) => {
                             self.store
                                 .bft_round_state
                                 .staking
                                 .stake(identity, amount)
                                 .map_err(|e| anyhow!(e))?;
                         }
-                        (identity, StakingAction::Delegate { validator }) => {
+                        (identity, StakingAction::Delegate { validator }, _) => {
                             self.store
                                 .bft_round_state
                                 .staking
                                 .delegate_to(identity, validator)
                                 .map_err(|e| anyhow!(e))?;
                         }
-                        (_identity, StakingAction::Distribute { claim: _ }) => todo!(),
-                        (_identity, StakingAction::DepositForFees { holder, amount }) => {
+                        // Rejected by the contract, this is never settled
+                        (identity, StakingAction::Distribute { claim: _ }, _) => {
+                            warn!("Ignoring unsupported Distribute action from {}", identity);
+                        }
+                        (_identity, StakingAction::DepositForFees { holder, amount }, _) => {
                             self.store
                                 .bft_round_state
                                 .staking
                                 .deposit_for_fees(holder, amount)
                                 .map_err(|e| anyhow!(e))?;
                         }
+                        (identity, StakingAction::Unstake { amount }, height) => {
+                            self.store
+                                .bft_round_state
+                                .staking
+                                .unstake(identity, amount, height)
+                                .map_err(|e| anyhow!(e))?;
+                        }
+                        (identity, StakingAction::Undelegate { validator }, height) => {
+                            self.store
+                                .bft_round_state
+                                .staking
+                                .undelegate(identity, validator, height)
+                                .map_err(|e| anyhow!(e))?;
+                        }
+                        (identity, StakingAction::Unbond { validator }, _) => {
+                            self.store
+                                .bft_round_state
+                                .staking
+                                .request_unbond(identity, validator)
+                                .map_err(|e| anyhow!(e))?;
+                        }
+                        (identity, StakingAction::Withdraw { amount }, height) => {
+                            self.store
+                                .bft_round_state
+                                .staking
+                                .withdraw(identity, amount, height)
+                                .map_err(|e| anyhow!(e))?;
+                        }
+                        (_identity, StakingAction::PayTxFees { amount, sponsor: _ }, _) => {
+                            self.store
+                                .bft_round_state
+                                .staking
+                                .pay_tx_fees(amount)
+                                .map_err(|e| anyhow!(e))?;
+                        }
                     }
                 }
+                if let Some(params) = block.consensus_params {
+                    info!("⚙️ Consensus parameters set to {:?}", params);
+                    self.store.params = params;
+                }
                 for validator in block.new_bounded_validators.iter() {
                     self.store
                         .bft_round_state
@@ -760,8 +945,30 @@ impl Consensus {
                         .bond(validator.clone())
                         .map_err(|e| anyhow!(e))?;
                 }
+                // Validators that follow the consensus slashed and unbonded them when the slot
+                // was committed
+                for validator in block.slashed_validators.iter() {
+                    if !self.store.bft_round_state.staking.is_tombstoned(validator) {
+                        self.store
+                            .bft_round_state
+                            .staking
+                            .slash(validator, block.block_height)
+                            .map_err(|e| anyhow!(e))?;
+                    }
+                }
+                for validator in block.unbonded_validators.iter() {
+                    if self.store.bft_round_state.staking.is_bonded(validator) {
+                        self.store
+                            .bft_round_state
+                            .staking
+                            .unbond(validator)
+                            .map_err(|e| anyhow!(e))?;
+                    }
+                }
 
                 if let StateTag::Joining = self.bft_round_state.state_tag {
+                    // A joining node does not commit slots yet
+                    self.store.bft_round_state.staking.update_total_bond();
                     if self.store.bft_round_state.joining.staking_updated_to < block.block_height.0
                     {
                         info!(
@@ -858,6 +1065,7 @@ impl Consensus {
                     GenesisEvent::GenesisBlock(signed_block) => {
                         self.bft_round_state.consensus_proposal.parent_hash = signed_block.hashed();
                         self.bft_round_state.consensus_proposal.round_leader = signed_block.consensus_proposal.round_leader.clone();
+                        self.store.leader_seed = signed_block.certificate.hashed().0;
 
                         if self.bft_round_state.consensus_proposal.round_leader == *self.crypto.validator_pubkey() {
                             self.bft_round_state.state_tag = StateTag::Leader;
@@ -1090,6 +1298,8 @@ pub mod test {
                 .bft_round_state
                 .consensus_proposal
                 .parent_hash = ConsensusProposalHash("genesis".to_string());
+            // Seeds the leader election, as processing the genesis block would
+            self.consensus.store.leader_seed = b"genesis".to_vec();
 
             if index == 0 {
                 self.consensus.bft_round_state.state_tag = StateTag::Leader;
@@ -1174,6 +1384,37 @@ pub mod test {
             matches!(self.consensus.bft_round_state.state_tag, StateTag::Joining)
         }
 
+        pub(crate) fn is_leader(&self) -> bool {
+            matches!(self.consensus.bft_round_state.state_tag, StateTag::Leader)
+        }
+
+        /// Orders the nodes by their role in the current view: its leader first, then the leader
+        /// of the next view, then the others. Tests can then name the nodes by role, whoever
+        /// the leader election picks.
+        pub fn by_role<const N: usize>(mut nodes: [ConsensusTestCtx; N]) -> [ConsensusTestCtx; N] {
+            let Some(node) = nodes.first() else {
+                return nodes;
+            };
+            let leader = node
+                .consensus
+                .bft_round_state
+                .consensus_proposal
+                .round_leader
+                .clone();
+            let next_leader = node.consensus.next_leader().unwrap();
+            nodes.sort_by_key(|node| {
+                let pubkey = node.validator_pubkey();
+                if pubkey == leader {
+                    0
+                } else if pubkey == next_leader {
+                    1
+                } else {
+                    2
+                }
+            });
+            nodes
+        }
+
         pub fn setup_for_round(
             nodes: &mut [&mut ConsensusTestCtx],
             leader: usize,
@@ -1245,12 +1486,17 @@ pub mod test {
             self.consensus
                 .handle_node_state_event(NodeStateEvent::NewBlock(Box::new(Block {
                     staking_actions: vec![
-                        (staker.name.clone().into(), StakingAction::Stake { amount }),
+                        (
+                            staker.name.clone().into(),
+                            StakingAction::Stake { amount },
+                            BlockHeight(0),
+                        ),
                         (
                             staker.name.clone().into(),
                             StakingAction::Delegate {
                                 validator: staker.pubkey(),
                             },
+                            BlockHeight(0),
                         ),
                     ],
                     ..Default::default()
@@ -1274,12 +1520,17 @@ pub mod test {
             self.consensus
                 .handle_node_state_event(NodeStateEvent::NewBlock(Box::new(Block {
                     staking_actions: vec![
-                        (self.name.clone().into(), StakingAction::Stake { amount }),
+                        (
+                            self.name.clone().into(),
+                            StakingAction::Stake { amount },
+                            BlockHeight(0),
+                        ),
                         (
                             self.name.clone().into(),
                             StakingAction::Delegate {
                                 validator: self.consensus.crypto.validator_pubkey().clone(),
                             },
+                            BlockHeight(0),
                         ),
                     ],
                     ..Default::default()
@@ -1384,14 +1635,15 @@ pub mod test {
     }
     #[test_log::test(tokio::test)]
     async fn test_happy_path() {
-        let (mut node1, mut node2): (ConsensusTestCtx, ConsensusTestCtx) = build_nodes!(2).await;
+        let (node1, node2): (ConsensusTestCtx, ConsensusTestCtx) = build_nodes!(2).await;
 
-        node1.start_round().await;
-        // Slot 0 - leader = node1
+        // Slot 1 - leader = node1, as set by the genesis
+        let [mut leader, mut follower] = ConsensusTestCtx::by_role([node1, node2]);
+        leader.start_round().await;
 
         let (cp1, ticket1) = simple_commit_round! {
-            leader: node1,
-            followers: [node2]
+            leader: leader,
+            followers: [follower]
         };
 
         assert_eq!(cp1.slot, 1);
@@ -1402,12 +1654,13 @@ pub mod test {
         );
         assert_eq!(ticket1, Ticket::Genesis);
 
-        // Slot 1 - leader = node2
-        node2.start_round().await;
+        // Slot 2 - leader elected from the seed of slot 1
+        let [mut leader, mut follower] = ConsensusTestCtx::by_role([leader, follower]);
+        leader.start_round().await;
 
         let (cp2, ticket2) = simple_commit_round! {
-            leader: node2,
-            followers: [node1]
+            leader: leader,
+            followers: [follower]
         };
 
         assert_eq!(cp2.slot, 2);
@@ -1415,12 +1668,13 @@ pub mod test {
         assert_eq!(cp2.parent_hash, cp1.hashed());
         assert!(matches!(ticket2, Ticket::CommitQC(_)));
 
-        // Slot 2 - leader = node1
-        node1.start_round().await;
+        // Slot 3 - leader elected from the seed of slot 2
+        let [mut leader, mut follower] = ConsensusTestCtx::by_role([leader, follower]);
+        leader.start_round().await;
 
         let (cp3, ticket3) = simple_commit_round! {
-            leader: node1,
-            followers: [node2]
+            leader: leader,
+            followers: [follower]
         };
 
         assert_eq!(cp3.slot, 3);
@@ -1515,6 +1769,7 @@ pub mod test {
                     )],
                     staking_actions: vec![],
                     parent_hash: ConsensusProposalHash("hash".into()),
+                    seed_signature: Signature::default(),
                 },
                 Ticket::Genesis,
             ))
@@ -1556,6 +1811,7 @@ pub mod test {
                     )],
                     staking_actions: vec![],
                     parent_hash: ConsensusProposalHash("hash".into()),
+                    seed_signature: Signature::default(),
                 },
                 Ticket::Genesis,
             ))
@@ -1606,6 +1862,7 @@ pub mod test {
                     )],
                     staking_actions: vec![],
                     parent_hash: ConsensusProposalHash("hash".into()),
+                    seed_signature: Signature::default(),
                 },
                 Ticket::Genesis,
             ))
@@ -1650,6 +1907,9 @@ pub mod test {
 
         assert_eq!(cp.timestamp, 1000);
 
+        // Slot 2 - node2 names the elected leader from here
+        let [mut node2, mut node1, mut node3, mut node4] =
+            ConsensusTestCtx::by_role([node1, node2, node3, node4]);
         node2.start_round_at(900).await;
 
         broadcast! {
@@ -1699,6 +1959,9 @@ pub mod test {
 
         assert_eq!(cp.timestamp, 1000);
 
+        // Slot 2 - node2 names the elected leader from here
+        let [mut node2, mut node1, mut node3, mut node4] =
+            ConsensusTestCtx::by_role([node1, node2, node3, node4]);
         node2.start_round_at(3000).await;
 
         assert_eq!(
@@ -1766,13 +2029,17 @@ pub mod test {
 
     #[test_log::test(tokio::test)]
     async fn test_timeout_join_mutiny_4() {
-        let (mut node1, mut node2, mut node3, mut node4): (
+        let (node1, node2, node3, node4): (
             ConsensusTestCtx,
             ConsensusTestCtx,
             ConsensusTestCtx,
             ConsensusTestCtx,
         ) = build_nodes!(4).await;
 
+        // node1 leads view 0 and node2 view 1
+        let [mut node1, mut node2, mut node3, mut node4] =
+            ConsensusTestCtx::by_role([node1, node2, node3, node4]);
+
         node1.start_round().await;
         // Slot 1 - leader = node1
 
@@ -1825,13 +2092,17 @@ pub mod test {
 
     #[test_log::test(tokio::test)]
     async fn test_timeout_join_mutiny_leader_4() {
-        let (mut node1, mut node2, mut node3, mut node4): (
+        let (node1, node2, node3, node4): (
             ConsensusTestCtx,
             ConsensusTestCtx,
             ConsensusTestCtx,
             ConsensusTestCtx,
         ) = build_nodes!(4).await;
 
+        // node1 leads view 0 and node2 view 1
+        let [mut node1, mut node2, mut node3, mut node4] =
+            ConsensusTestCtx::by_role([node1, node2, node3, node4]);
+
         node1.start_round().await;
         // Slot 1 - leader = node1
 
@@ -1925,13 +2196,17 @@ pub mod test {
 
     #[test_log::test(tokio::test)]
     async fn test_timeout_next_leader_build_and_use_its_timeout_certificate() {
-        let (mut node1, mut node2, mut node3, mut node4): (
+        let (node1, node2, node3, node4): (
             ConsensusTestCtx,
             ConsensusTestCtx,
             ConsensusTestCtx,
             ConsensusTestCtx,
         ) = build_nodes!(4).await;
 
+        // node1 leads view 0 and node2 view 1
+        let [mut node1, mut node2, mut node3, mut node4] =
+            ConsensusTestCtx::by_role([node1, node2, node3, node4]);
+
         node1.start_round().await;
         // Slot 1 - leader = node1
 
@@ -1994,7 +2269,7 @@ pub mod test {
 
     #[test_log::test(tokio::test)]
     async fn timeout_only_emit_certificate_once() {
-        let (mut node1, mut node2, mut node3, mut node4, mut node5): (
+        let (node1, node2, node3, node4, node5): (
             ConsensusTestCtx,
             ConsensusTestCtx,
             ConsensusTestCtx,
@@ -2002,6 +2277,10 @@ pub mod test {
             ConsensusTestCtx,
         ) = build_nodes!(5).await;
 
+        // node1 leads view 0 and node2 view 1
+        let [mut node1, mut node2, mut node3, mut node4, mut node5] =
+            ConsensusTestCtx::by_role([node1, node2, node3, node4, node5]);
+
         node1.start_round().await;
         // Slot 1 - leader = node1
 
@@ -2068,7 +2347,7 @@ pub mod test {
 
     #[test_log::test(tokio::test)]
     async fn timeout_next_leader_receive_timeout_certificate_without_timeouting() {
-        let (mut node1, mut node2, mut node3, mut node4, mut node5, mut node6, mut node7): (
+        let (node1, node2, node3, node4, node5, node6, node7): (
             ConsensusTestCtx,
             ConsensusTestCtx,
             ConsensusTestCtx,
@@ -2078,6 +2357,10 @@ pub mod test {
             ConsensusTestCtx,
         ) = build_nodes!(7).await;
 
+        // node1 leads view 0 and node2 view 1
+        let [mut node1, mut node2, mut node3, mut node4, mut node5, mut node6, mut node7] =
+            ConsensusTestCtx::by_role([node1, node2, node3, node4, node5, node6, node7]);
+
         node1.start_round().await;
 
         let lost_prepare = node1.assert_broadcast("Lost Prepare slot 1/view 0").msg;
@@ -2158,15 +2441,16 @@ pub mod test {
 
     #[test_log::test(tokio::test)]
     async fn test_candidacy() {
-        let (mut node1, mut node2): (ConsensusTestCtx, ConsensusTestCtx) = build_nodes!(2).await;
+        let (node1, node2): (ConsensusTestCtx, ConsensusTestCtx) = build_nodes!(2).await;
 
-        // Slot 1
+        // Slot 1 - leader = node1, as set by the genesis
+        let [mut leader, mut follower] = ConsensusTestCtx::by_role([node1, node2]);
         {
-            node1.start_round().await;
+            leader.start_round().await;
 
             let (cp, ticket) = simple_commit_round! {
-                leader: node1,
-                followers: [node2]
+                leader: leader,
+                followers: [follower]
             };
 
             assert_eq!(cp.slot, 1);
@@ -2177,59 +2461,61 @@ pub mod test {
         let mut node3 = ConsensusTestCtx::new_node("node-3").await;
         node3.consensus.bft_round_state.state_tag = StateTag::Joining;
         node3.consensus.bft_round_state.joining.staking_updated_to = 1;
-        node3.add_bonded_staker(&node1, 100, "Add staker").await;
-        node3.add_bonded_staker(&node2, 100, "Add staker").await;
+        node3.add_bonded_staker(&leader, 100, "Add staker").await;
+        node3.add_bonded_staker(&follower, 100, "Add staker").await;
 
-        // Slot 2: Node3 synchronizes its consensus to the others. - leader = node2
+        // Slot 2: Node3 synchronizes its consensus to the others.
+        let [mut leader, mut follower] = ConsensusTestCtx::by_role([leader, follower]);
         {
             info!("➡️  Leader proposal");
-            node2.start_round().await;
+            leader.start_round().await;
 
             broadcast! {
                 description: "Leader Proposal",
-                from: node2, to: [node1, node3]
+                from: leader, to: [follower, node3]
             };
             send! {
                 description: "Prepare Vote",
-                from: [node1], to: node2,
+                from: [follower], to: leader,
                 message_matches: ConsensusNetMessage::PrepareVote(_)
             };
             broadcast! {
                 description: "Leader Confirm",
-                from: node2, to: [node1, node3]
+                from: leader, to: [follower, node3]
             };
             send! {
                 description: "Confirm Ack",
-                from: [node1], to: node2,
+                from: [follower], to: leader,
                 message_matches: ConsensusNetMessage::ConfirmAck(_)
             };
             broadcast! {
                 description: "Leader Commit",
-                from: node2, to: [node1, node3]
+                from: leader, to: [follower, node3]
             };
         }
 
-        // Slot 3: New slave candidates - leader = node1
+        // Slot 3: New slave candidates
+        let [mut leader, mut follower] = ConsensusTestCtx::by_role([leader, follower]);
         {
             info!("➡️  Leader proposal");
-            node1.start_round().await;
-            let leader_proposal = node1.assert_broadcast("Leader proposal");
-            node2.handle_msg(&leader_proposal, "Leader proposal");
+            leader.start_round().await;
+            let leader_proposal = leader.assert_broadcast("Leader proposal");
+            follower.handle_msg(&leader_proposal, "Leader proposal");
             node3.handle_msg(&leader_proposal, "Leader proposal");
             info!("➡️  Slave vote");
-            let slave_vote = node2.assert_send(&node1.validator_pubkey(), "Slave vote");
-            node1.handle_msg(&slave_vote, "Slave vote");
+            let slave_vote = follower.assert_send(&leader.validator_pubkey(), "Slave vote");
+            leader.handle_msg(&slave_vote, "Slave vote");
             info!("➡️  Leader confirm");
-            let leader_confirm = node1.assert_broadcast("Leader confirm");
-            node2.handle_msg(&leader_confirm, "Leader confirm");
+            let leader_confirm = leader.assert_broadcast("Leader confirm");
+            follower.handle_msg(&leader_confirm, "Leader confirm");
             node3.handle_msg(&leader_confirm, "Leader confirm");
             info!("➡️  Slave confirm ack");
             let slave_confirm_ack =
-                node2.assert_send(&node1.validator_pubkey(), "Slave confirm ack");
-            node1.handle_msg(&slave_confirm_ack, "Slave confirm ack");
+                follower.assert_send(&leader.validator_pubkey(), "Slave confirm ack");
+            leader.handle_msg(&slave_confirm_ack, "Slave confirm ack");
             info!("➡️  Leader commit");
-            let leader_commit = node1.assert_broadcast("Leader commit");
-            node2.handle_msg(&leader_commit, "Leader commit");
+            let leader_commit = leader.assert_broadcast("Leader commit");
+            follower.handle_msg(&leader_commit, "Leader commit");
 
             info!("➡️  Slave 2 candidacy");
             node3.with_stake(100, "Add stake").await;
@@ -2237,63 +2523,603 @@ pub mod test {
             node3.handle_msg(&leader_commit, "Leader commit");
             let slave2_candidacy = node3.assert_broadcast("Slave 2 candidacy");
             assert_contains!(
-                node1.handle_msg_err(&slave2_candidacy).to_string(),
+                leader.handle_msg_err(&slave2_candidacy).to_string(),
                 "validator is not staking"
             );
-            node1.add_staker(&node3, 100, "Add staker").await;
-            node1.handle_msg(&slave2_candidacy, "Slave 2 candidacy");
-            node2.add_staker(&node3, 100, "Add staker").await;
-            node2.handle_msg(&slave2_candidacy, "Slave 2 candidacy");
+            leader.add_staker(&node3, 100, "Add staker").await;
+            leader.handle_msg(&slave2_candidacy, "Slave 2 candidacy");
+            follower.add_staker(&node3, 100, "Add staker").await;
+            follower.handle_msg(&slave2_candidacy, "Slave 2 candidacy");
         }
 
-        // Slot 4: Still a slot without slave 2 - leader = node 2
+        // Slot 4: Still a slot without slave 2
+        let [mut leader, mut follower] = ConsensusTestCtx::by_role([leader, follower]);
         {
-            info!("➡️  Leader proposal - Slot 3");
-            node2.start_round().await;
+            info!("➡️  Leader proposal - Slot 4");
+            leader.start_round().await;
 
             broadcast! {
                 description: "Leader Proposal",
-                from: node2, to: [node1, node3],
+                from: leader, to: [follower, node3],
                 message_matches: ConsensusNetMessage::Prepare(_, _) => {
-                    assert_eq!(node2.consensus.bft_round_state.staking.bonded().len(), 2);
+                    assert_eq!(leader.consensus.bft_round_state.staking.bonded().len(), 2);
                 }
             };
             send! {
                 description: "Prepare Vote",
-                from: [node1], to: node2,
+                from: [follower], to: leader,
                 message_matches: ConsensusNetMessage::PrepareVote(_)
             };
             broadcast! {
                 description: "Leader Confirm",
-                from: node2, to: [node1, node3]
+                from: leader, to: [follower, node3]
             };
             send! {
                 description: "Confirm Ack",
-                from: [node1], to: node2,
+                from: [follower], to: leader,
                 message_matches: ConsensusNetMessage::ConfirmAck(_)
             };
             broadcast! {
                 description: "Leader Commit",
-                from: node2, to: [node1, node3]
+                from: leader, to: [follower, node3]
             };
         }
 
-        // Slot 5: Slave 2 joined consensus, leader = node-3
+        // Slot 5: Slave 2 joined consensus, and may be elected
+        let [mut leader, mut follower1, mut follower2] =
+            ConsensusTestCtx::by_role([leader, follower, node3]);
         {
             info!("➡️  Leader proposal");
-            node3.start_round().await;
+            leader.start_round().await;
 
             let (cp, _) = simple_commit_round! {
-                leader: node3,
-                followers: [node1, node2]
+                leader: leader,
+                followers: [follower1, follower2]
             };
             assert_eq!(cp.slot, 5);
-            assert_eq!(node2.consensus.bft_round_state.staking.bonded().len(), 3);
+            assert_eq!(
+                follower1.consensus.bft_round_state.staking.bonded().len(),
+                3
+            );
+        }
+
+        for node in [&leader, &follower1, &follower2] {
+            assert_eq!(node.consensus.bft_round_state.consensus_proposal.slot, 6);
+        }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_unbond() {
+        let (mut node1, mut node2, mut node3, mut node4): (
+            ConsensusTestCtx,
+            ConsensusTestCtx,
+            ConsensusTestCtx,
+            ConsensusTestCtx,
+        ) = build_nodes!(4).await;
+        let node4_pubkey = node4.validator_pubkey();
+
+        // A validator that did not ask to leave and has enough stake cannot be unbonded
+        let proposal = ConsensusProposal {
+            staking_actions: vec![ConsensusStakingAction::Unbond {
+                validator: node4_pubkey.clone(),
+            }],
+            ..ConsensusProposal::default()
+        };
+        assert_contains!(
+            node2
+                .consensus
+                .verify_staking_actions(&proposal)
+                .unwrap_err()
+                .to_string(),
+            "did not ask to unbond"
+        );
+
+        // The delegator of node 4 asks it to leave the consensus
+        let delegator: Identity = hex::encode(&node4_pubkey.0).into();
+        for node in [&mut node1, &mut node2, &mut node3, &mut node4] {
+            node.handle_node_state_event(NodeStateEvent::NewBlock(Box::new(Block {
+                staking_actions: vec![(
+                    delegator.clone(),
+                    StakingAction::Unbond {
+                        validator: node4_pubkey.clone(),
+                    },
+                    BlockHeight(0),
+                )],
+                ..Default::default()
+            })))
+            .await
+            .expect("Unbond request");
+        }
+        node2
+            .consensus
+            .verify_staking_actions(&proposal)
+            .expect("Unbond is valid");
+
+        // Validators leave one at a time
+        let proposal = ConsensusProposal {
+            staking_actions: vec![
+                ConsensusStakingAction::Unbond {
+                    validator: node4_pubkey.clone(),
+                },
+                ConsensusStakingAction::Unbond {
+                    validator: node4_pubkey.clone(),
+                },
+            ],
+            ..ConsensusProposal::default()
+        };
+        assert_contains!(
+            node2
+                .consensus
+                .verify_staking_actions(&proposal)
+                .unwrap_err()
+                .to_string(),
+            "Only one validator can be unbonded per slot"
+        );
+
+        // Slot 1 - leader = node1, node 4 is unbonded once the slot is committed
+        node1.start_round().await;
+        let (cp, _) = simple_commit_round! {
+            leader: node1,
+            followers: [node2, node3, node4]
+        };
+        assert!(cp
+            .staking_actions
+            .contains(&ConsensusStakingAction::Unbond {
+                validator: node4_pubkey.clone()
+            }));
+
+        // All nodes agree on a next leader that is still bonded
+        let next_leader = &node1
+            .consensus
+            .bft_round_state
+            .consensus_proposal
+            .round_leader;
+        assert_ne!(next_leader, &node4_pubkey);
+        for node in [&node1, &node2, &node3, &node4] {
+            let staking = node.staking();
+            assert_eq!(staking.bonded().len(), 3);
+            assert!(!staking.is_bonded(&node4_pubkey));
+            assert_eq!(
+                &node
+                    .consensus
+                    .bft_round_state
+                    .consensus_proposal
+                    .round_leader,
+                next_leader
+            );
         }
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_equivocation_evidence() {
+        let (mut node1, mut node2, mut node3, mut node4): (
+            ConsensusTestCtx,
+            ConsensusTestCtx,
+            ConsensusTestCtx,
+            ConsensusTestCtx,
+        ) = build_nodes!(4).await;
 
-        assert_eq!(node1.consensus.bft_round_state.consensus_proposal.slot, 6);
-        assert_eq!(node2.consensus.bft_round_state.consensus_proposal.slot, 6);
-        assert_eq!(node3.consensus.bft_round_state.consensus_proposal.slot, 6);
+        node1.start_round_at(1000).await;
+        simple_commit_round! {
+            leader: node1,
+            followers: [node2, node3, node4]
+        };
+
+        // Slot 2 - node2 names the elected leader from here, which also signs a second proposal
+        // for the same slot
+        let [mut node2, _node1, mut node3, mut node4] =
+            ConsensusTestCtx::by_role([node1, node2, node3, node4]);
+        node2.start_round_at(2000).await;
+        let prepare = node2.assert_broadcast("Leader proposal");
+        let ConsensusNetMessage::Prepare(cp, ticket) = prepare.msg.clone() else {
+            panic!("Expected a Prepare message");
+        };
+        let conflicting_cp = ConsensusProposal {
+            timestamp: 900,
+            ..cp.clone()
+        };
+        let conflicting = node2
+            .consensus
+            .sign_net_message(ConsensusNetMessage::Prepare(conflicting_cp.clone(), ticket))
+            .unwrap();
+
+        // Node 3 votes for the first proposal only
+        node3.handle_msg(&prepare, "Leader proposal");
+        assert_contains!(
+            format!("{:#}", node3.handle_msg_err(&conflicting)),
+            "another proposal"
+        );
+
+        // Node 3 saw both proposals and punishes node 2 when it leads
+        let Some(punishment) = node3.consensus.next_punishment() else {
+            panic!("Expected a punishment for node 2");
+        };
+        let ConsensusStakingAction::Slash { evidence } = &punishment else {
+            panic!("Expected a Slash action");
+        };
+        assert_eq!(evidence.offender(), &node2.validator_pubkey());
+
+        // Node 4 did not see the proposals, but can check the evidence
+        node4
+            .consensus
+            .verify_staking_actions(&ConsensusProposal {
+                staking_actions: vec![punishment.clone()],
+                ..ConsensusProposal::default()
+            })
+            .expect("Valid evidence");
+
+        let same_proposal = ConsensusStakingAction::Slash {
+            evidence: Box::new(EquivocationEvidence {
+                first: prepare.clone(),
+                second: prepare.clone(),
+                proposals: None,
+            }),
+        };
+        assert_contains!(
+            node4
+                .consensus
+                .verify_staking_actions(&ConsensusProposal {
+                    staking_actions: vec![same_proposal],
+                    ..ConsensusProposal::default()
+                })
+                .unwrap_err()
+                .to_string(),
+            "same proposal"
+        );
+
+        // Voters that sign votes for both proposals equivocate too
+        let votes = [&cp, &conflicting_cp].map(|proposal| {
+            node3
+                .consensus
+                .sign_net_message(ConsensusNetMessage::PrepareVote(proposal.hashed()))
+                .unwrap()
+        });
+        let [first, second] = votes;
+        let mut double_vote = EquivocationEvidence {
+            first,
+            second,
+            proposals: None,
+        };
+        assert_contains!(
+            node4
+                .consensus
+                .verify_equivocation_evidence(&double_vote)
+                .unwrap_err()
+                .to_string(),
+            "same kind"
+        );
+        double_vote.proposals = Some((cp.clone(), conflicting_cp.clone()));
+        node4
+            .consensus
+            .verify_equivocation_evidence(&double_vote)
+            .expect("Valid vote evidence");
+        double_vote.proposals = Some((cp.clone(), cp.clone()));
+        assert_contains!(
+            node4
+                .consensus
+                .verify_equivocation_evidence(&double_vote)
+                .unwrap_err()
+                .to_string(),
+            "do not match"
+        );
+
+        // Once slashed, node 2 is out of the consensus and cannot be slashed again
+        node4
+            .consensus
+            .bft_round_state
+            .staking
+            .slash(&node2.validator_pubkey(), BlockHeight(2))
+            .expect("Slash node 2");
+        assert!(!node4.staking().is_bonded(&node2.validator_pubkey()));
+        assert_contains!(
+            node4
+                .consensus
+                .verify_staking_actions(&ConsensusProposal {
+                    staking_actions: vec![punishment],
+                    ..ConsensusProposal::default()
+                })
+                .unwrap_err()
+                .to_string(),
+            "already slashed"
+        );
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_jailing() {
+        let (node1, node2, node3, node4): (
+            ConsensusTestCtx,
+            ConsensusTestCtx,
+            ConsensusTestCtx,
+            ConsensusTestCtx,
+        ) = build_nodes!(4).await;
+
+        // node1 leads view 0 and node2 view 1
+        let [mut node1, mut node2, mut node3, mut node4] =
+            ConsensusTestCtx::by_role([node1, node2, node3, node4]);
+
+        let mut conf = Conf::default();
+        conf.consensus.slot_duration = 1000;
+        conf.consensus.jailing.enabled = true;
+        conf.consensus.jailing.missed_views = 1;
+        let conf = Arc::new(conf);
+        for node in [&mut node1, &mut node2, &mut node3, &mut node4] {
+            node.consensus.config = conf.clone();
+        }
+
+        // Slot 1 view 0 - leader = node1, whose proposal is lost
+        node1.start_round().await;
+        node1.assert_broadcast("Lost prepare");
+        assert!(node2.consensus.next_punishment().is_none());
+
+        ConsensusTestCtx::timeout(&mut [&mut node3, &mut node4]).await;
+        broadcast! {
+            description: "Follower - Timeout",
+            from: node3, to: [node1, node2],
+            message_matches: ConsensusNetMessage::Timeout(..)
+        };
+        broadcast! {
+            description: "Follower - Timeout",
+            from: node4, to: [node1],
+            message_matches: ConsensusNetMessage::Timeout(..)
+        };
+        broadcast! {
+            description: "Follower - Timeout",
+            from: node1, to: [node2],
+            message_matches: ConsensusNetMessage::Timeout(..)
+        };
+        node2.assert_broadcast("Timeout Message 2");
+
+        // The view ended with a timeout certificate: node 2, next leader, jails node 1
+        let Some(ConsensusStakingAction::Jail { validator }) = node2.consensus.next_punishment()
+        else {
+            panic!("Expected node 1 to be jailed");
+        };
+        assert_eq!(validator, node1.validator_pubkey());
+
+        // Validators that did not lead a timed out view are not jailed
+        assert_contains!(
+            node2
+                .consensus
+                .verify_staking_actions(&ConsensusProposal {
+                    staking_actions: vec![ConsensusStakingAction::Jail {
+                        validator: node3.validator_pubkey()
+                    }],
+                    ..ConsensusProposal::default()
+                })
+                .unwrap_err()
+                .to_string(),
+            "only missed 0 views"
+        );
+
+        // Slot 1 view 1 - the followers count the timed out view from the ticket, and accept
+        // the jailing
+        node2.start_round().await;
+        let (cp, _) = simple_commit_round! {
+            leader: node2,
+            followers: [node1, node3, node4]
+        };
+        assert!(cp.staking_actions.contains(&ConsensusStakingAction::Jail {
+            validator: node1.validator_pubkey()
+        }));
+        assert!(!node3.staking().is_bonded(&node1.validator_pubkey()));
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_stake_weighted_leader_election() {
+        let (mut node1, mut node2, mut node3, mut node4): (
+            ConsensusTestCtx,
+            ConsensusTestCtx,
+            ConsensusTestCtx,
+            ConsensusTestCtx,
+        ) = build_nodes!(4).await;
+
+        // Slot 1 - leader = node1, as set by the genesis
+        node1.start_round_at(1000).await;
+        let (cp, _) = simple_commit_round! {
+            leader: node1,
+            followers: [node2, node3, node4]
+        };
+
+        // All validators elected the same leaders from the seed signed by the leader of slot 1
+        let [mut leader, mut node2, mut node3, mut node4] =
+            ConsensusTestCtx::by_role([node1, node2, node3, node4]);
+        let next_leader = leader.consensus.next_leader().unwrap();
+        for node in [&leader, &node2, &node3, &node4] {
+            assert_eq!(
+                node.consensus.store.leader_seed,
+                leader.consensus.store.leader_seed
+            );
+            assert_eq!(
+                node.consensus
+                    .bft_round_state
+                    .consensus_proposal
+                    .round_leader,
+                leader.validator_pubkey()
+            );
+            assert_eq!(node.consensus.next_leader().unwrap(), next_leader);
+        }
+        assert_eq!(
+            leader.consensus.store.leader_seed,
+            Sha3_256::digest(&cp.seed_signature.0).to_vec()
+        );
+
+        // Slot 2 - the elected leader is followed by the others
+        leader.start_round_at(2000).await;
+        let prepare = leader.assert_broadcast("Leader proposal");
+        for node in [&mut node2, &mut node4] {
+            node.handle_msg(&prepare, "Leader proposal");
+        }
+
+        // A proposal whose seed is not signed by its leader is rejected
+        let ConsensusNetMessage::Prepare(cp, ticket) = prepare.msg.clone() else {
+            panic!("Expected a Prepare message");
+        };
+        let forged = leader
+            .consensus
+            .sign_net_message(ConsensusNetMessage::Prepare(
+                ConsensusProposal {
+                    seed_signature: node2
+                        .consensus
+                        .crypto
+                        .sign(LeaderSeedMessage {
+                            slot: 2,
+                            seed: node2.consensus.store.leader_seed.clone(),
+                        })
+                        .unwrap()
+                        .signature
+                        .signature,
+                    ..cp
+                },
+                ticket,
+            ))
+            .unwrap();
+        assert_contains!(
+            format!("{:#}", node3.handle_msg_err(&forged)),
+            "Invalid leader seed signature"
+        );
+
+        // Another seed elects other leaders
+        let node = &mut node4;
+        let elected = |node: &ConsensusTestCtx| {
+            (0..32)
+                .map(|view| node.consensus.elect_leader(3, view).unwrap())
+                .collect::<Vec<_>>()
+        };
+        let leaders = elected(node);
+        node.consensus.store.leader_seed = vec![1, 2, 3];
+        assert_ne!(elected(node), leaders);
+
+        // Leaders are drawn with a probability proportional to their stake
+        let heavy = node.validator_pubkey();
+        node.consensus
+            .bft_round_state
+            .staking
+            .stake(hex::encode(heavy.0.clone()).into(), 9_700)
+            .unwrap();
+        let leaders = elected(node);
+        assert!(leaders.iter().filter(|v| **v == heavy).count() > 24);
+        assert!(leaders
+            .iter()
+            .all(|v| node.consensus.bft_round_state.staking.is_bonded(v)));
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_tx_fees_settle_to_the_bonded_validators() {
+        use crate::{
+            genesis::States,
+            mempool::{admission::check_blob_tx, KnownContracts},
+            node_state::{test::new_proof_tx, NodeState},
+            utils::conf::MempoolConf,
+        };
+        use client_sdk::transaction_builder::{ProvableBlobTx, TxExecutorBuilder};
+        use hyle_contract_sdk::Digestable;
+
+        let (mut node1, mut node2, mut node3, mut node4): (
+            ConsensusTestCtx,
+            ConsensusTestCtx,
+            ConsensusTestCtx,
+            ConsensusTestCtx,
+        ) = build_nodes!(4).await;
+
+        // The faucet pays the fees of its transaction with hyllar
+        let mut executor = TxExecutorBuilder::new(States {
+            hyllar: hyllar::Hyllar::default(),
+            hydentity: hydentity::Hydentity::default(),
+            staking: Staking::new(),
+        })
+        .build();
+        let mut tx = ProvableBlobTx::new(hyllar::FAUCET_ID.into());
+        hydentity::client::register_identity(&mut tx, "hydentity".into(), "password".into())
+            .unwrap();
+        executor.process(tx).unwrap();
+        let mut node_state = NodeState::default();
+        for (contract_name, state_digest) in [
+            ("hydentity", executor.hydentity.as_digest()),
+            ("hyllar", executor.hyllar.as_digest()),
+            ("staking", executor.staking.as_digest()),
+        ] {
+            node_state.handle_register_contract_effect(&RegisterContractEffect {
+                verifier: "test".into(),
+                program_id: ProgramId(vec![]),
+                state_digest,
+                contract_name: contract_name.into(),
+                timeout_window: None,
+            });
+        }
+        let mut tx = ProvableBlobTx::new(hyllar::FAUCET_ID.into());
+        hydentity::client::verify_identity(
+            &mut tx,
+            "hydentity".into(),
+            &executor.hydentity,
+            "password".into(),
+        )
+        .unwrap();
+        staking::client::pay_tx_fees(&mut tx, "staking".into(), "hyllar".into(), 1_000, None)
+            .unwrap();
+        let proof_tx = executor.process(tx).unwrap();
+        let blob_tx = BlobTransaction::new(proof_tx.identity.clone(), proof_tx.blobs.clone());
+
+        // The mempool admits it with a minimum fee
+        let known_contracts = KnownContracts(
+            ["hydentity", "hyllar", "staking"]
+                .into_iter()
+                .map(|name| (name.into(), ("test".into(), ProgramId(vec![]))))
+                .collect(),
+        );
+        let conf = MempoolConf {
+            min_fee_per_byte: 1,
+            ..MempoolConf::default()
+        };
+        check_blob_tx(&conf, &known_contracts, &blob_tx.hashed(), &blob_tx).unwrap();
+
+        // It settles with the proofs of both contracts
+        let mut txs: Vec<Transaction> = vec![blob_tx.clone().into()];
+        for (contract_name, hyle_output) in proof_tx.outputs.iter() {
+            txs.push(new_proof_tx(contract_name, hyle_output, &blob_tx.hashed()).into());
+        }
+        let block = node_state.handle_signed_block(&SignedBlock {
+            data_proposals: vec![(LaneId::default(), vec![DataProposal::new(None, txs)])],
+            certificate: AggregateSignature::default(),
+            consensus_proposal: ConsensusProposal {
+                slot: 1,
+                ..ConsensusProposal::default()
+            },
+        });
+        assert_eq!(block.successful_txs, vec![blob_tx.hashed()]);
+        assert_eq!(
+            block.staking_actions,
+            vec![(
+                hyllar::FAUCET_ID.into(),
+                StakingAction::PayTxFees {
+                    amount: 1_000,
+                    sponsor: None
+                },
+                BlockHeight(1)
+            )]
+        );
+
+        // The validators split the fees when the next slot is committed
+        let staking = node1.staking();
+        for node in [&mut node1, &mut node2, &mut node3, &mut node4] {
+            node.handle_node_state_event(NodeStateEvent::NewBlock(Box::new(block.clone())))
+                .await
+                .expect("Settled fees");
+        }
+        node1.start_round().await;
+        simple_commit_round! {
+            leader: node1,
+            followers: [node2, node3, node4]
+        };
+
+        let mut without_fees = staking.clone();
+        without_fees.distribute(&FeeSplit::default()).unwrap();
+        let mut expected = staking;
+        expected.pay_tx_fees(1_000).unwrap();
+        expected.distribute(&FeeSplit::default()).unwrap();
+        assert_ne!(expected, without_fees);
+        for node in [&node1, &node2, &node3, &node4] {
+            assert_eq!(node.staking(), expected);
+        }
     }
 
     bus_client! {
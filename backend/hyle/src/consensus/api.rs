@@ -1,8 +1,11 @@
 use anyhow::anyhow;
 use axum::{debug_handler, extract::State, http::StatusCode, response::IntoResponse, Json, Router};
 use client_sdk::contract_indexer::AppError;
-use hyle_model::api::APIStaking;
-use staking::state::Staking;
+use hyle_model::api::{APIFeePrice, APIStaking};
+use staking::{
+    fees::{MAX_FEE_PER_BYTE, TARGET_BYTES_PER_SLOT},
+    state::Staking,
+};
 use tracing::error;
 use utoipa::OpenApi;
 use utoipa_axum::{router::OpenApiRouter, routes};
@@ -40,6 +43,7 @@ pub async fn api(ctx: &CommonRunContext) -> Router<()> {
     let (router, api) = OpenApiRouter::with_openapi(ConsensusAPI::openapi())
         .routes(routes!(get_consensus_state))
         .routes(routes!(get_consensus_staking_state))
+        .routes(routes!(get_fee_price))
         .split_for_parts();
 
     if let Ok(mut o) = ctx.openapi.lock() {
@@ -102,6 +106,35 @@ pub async fn get_consensus_staking_state(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/fee_price",
+    tag = "Consensus",
+    responses(
+        (status = OK, body = APIFeePrice)
+    )
+)]
+#[debug_handler]
+pub async fn get_fee_price(
+    State(mut state): State<RouterState>,
+) -> Result<impl IntoResponse, AppError> {
+    match state.bus.request(QueryConsensusStakingState {}).await {
+        Ok(staking) => Ok(Json(APIFeePrice {
+            fee_per_byte: staking.fee_per_byte(),
+            target_bytes_per_slot: TARGET_BYTES_PER_SLOT,
+            max_fee_per_byte: MAX_FEE_PER_BYTE,
+        })),
+        Err(err) => {
+            error!("{:?}", err);
+
+            Err(AppError(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                anyhow!("Error while getting fee price: {err}"),
+            ))
+        }
+    }
+}
+
 impl Clone for RouterState {
     fn clone(&self) -> Self {
         use crate::utils::static_type_map::Pick;
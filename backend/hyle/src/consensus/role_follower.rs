@@ -93,7 +93,13 @@ impl FollowerRole for Consensus {
             }
         }
 
-        // TODO: check we haven't voted for a proposal this slot/view already.
+        // A leader that signed another proposal for this slot/view is equivocating
+        if self.is_conflicting_proposal(&sender, &consensus_proposal) {
+            self.metrics.prepare_error("conflicting_proposal");
+            bail!(
+                "Leader already signed another proposal for this slot/view. I won't vote for it."
+            );
+        }
 
         // After processing the ticket, we should be in the right slot/view.
 
@@ -114,6 +120,8 @@ impl FollowerRole for Consensus {
             );
         }
 
+        self.verify_seed_signature(&consensus_proposal)?;
+
         self.verify_poda(&consensus_proposal)?;
 
         self.verify_staking_actions(&consensus_proposal)?;
@@ -124,10 +124,22 @@ impl LeaderRole for Consensus {
             });
         }
 
+        // One validator at most leaves the consensus per slot, misbehaving ones first
+        if let Some(punishment) = self.next_punishment() {
+            debug!("⚔️ Punishing a misbehaving validator");
+            staking_actions.push(punishment);
+        } else if let Some(validator) = self.bft_round_state.staking.next_validator_to_unbond() {
+            debug!("👋 Unbonding validator {}", validator);
+            staking_actions.push(ConsensusStakingAction::Unbond {
+                validator: validator.clone(),
+            });
+        }
+
         // Start Consensus with following cut
         self.bft_round_state.consensus_proposal.cut = cut;
         self.bft_round_state.consensus_proposal.staking_actions = staking_actions;
         self.bft_round_state.consensus_proposal.timestamp = current_timestamp;
+        self.bft_round_state.consensus_proposal.seed_signature = self.sign_leader_seed()?;
 
         self.metrics.start_new_round("consensus_proposal");
 
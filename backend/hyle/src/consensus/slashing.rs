@@ -0,0 +1,340 @@
+use std::collections::BTreeMap;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use tracing::{debug, info, warn};
+
+use super::Consensus;
+use crate::{
+    model::{
+        AggregateSignature, ConsensusNetMessage, ConsensusProposal, ConsensusProposalHash,
+        ConsensusStakingAction, EquivocationEvidence, Hashed, QuorumCertificate, Signed,
+        SignedByValidator, Slot, ValidatorPublicKey, View,
+    },
+    utils::crypto::BlstCrypto,
+};
+use anyhow::{bail, Result};
+
+/// Messages that a validator signs at most once per slot and view
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum SignedKind {
+    Prepare,
+    PrepareVote,
+    Confirm,
+    ConfirmAck,
+}
+
+/// Signer, kind, slot and view of a signed consensus message
+type SignedKey = (ValidatorPublicKey, SignedKind, Slot, View);
+
+/// Misbehaviours of the validators waiting to be punished.
+/// Kept outside of the round state, as they outlive the slot where they are seen.
+#[derive(BorshSerialize, BorshDeserialize, Default)]
+pub(super) struct SlashingState {
+    /// First message of each kind signed by each validator for each slot and view, with the
+    /// proposal it is for
+    signed: BTreeMap<SignedKey, (SignedByValidator<ConsensusNetMessage>, ConsensusProposal)>,
+    /// Proposals seen in Prepare messages, to find the slot and view of the messages that only
+    /// refer to them
+    proposals: BTreeMap<ConsensusProposalHash, ConsensusProposal>,
+    /// Evidence of equivocation not yet included in a committed proposal
+    pending_evidence: Vec<EquivocationEvidence>,
+    /// Number of consecutive views led by each bonded validator that ended with a timeout
+    /// certificate
+    missed_views: BTreeMap<ValidatorPublicKey, u64>,
+}
+
+impl Consensus {
+    /// Keeps the messages signed by the bonded validators for the coming slots, to catch a
+    /// validator that signs two of them for different proposals in the same slot and view.
+    pub(super) fn record_signed_message(&mut self, msg: &SignedByValidator<ConsensusNetMessage>) {
+        let signer = &msg.signature.validator;
+        if !self.bft_round_state.staking.is_bonded(signer) {
+            return;
+        }
+        let (kind, proposal) = match &msg.msg {
+            ConsensusNetMessage::Prepare(proposal, _) => {
+                if proposal.slot >= self.bft_round_state.consensus_proposal.slot {
+                    self.store
+                        .slashing
+                        .proposals
+                        .entry(proposal.hashed())
+                        .or_insert_with(|| proposal.clone());
+                }
+                (SignedKind::Prepare, Some(proposal.clone()))
+            }
+            ConsensusNetMessage::PrepareVote(hash) => {
+                (SignedKind::PrepareVote, self.known_proposal(hash))
+            }
+            ConsensusNetMessage::Confirm(qc) => {
+                (SignedKind::Confirm, self.certified_proposal(signer, qc))
+            }
+            ConsensusNetMessage::ConfirmAck(hash) => {
+                (SignedKind::ConfirmAck, self.known_proposal(hash))
+            }
+            _ => return,
+        };
+        // Without its proposal, the slot and view of the message are unknown
+        let Some(proposal) = proposal else {
+            return;
+        };
+        if proposal.slot < self.bft_round_state.consensus_proposal.slot {
+            return;
+        }
+
+        let key = (signer.clone(), kind, proposal.slot, proposal.view);
+        let Some((first, first_proposal)) = self.store.slashing.signed.get(&key) else {
+            self.store
+                .slashing
+                .signed
+                .insert(key, (msg.clone(), proposal));
+            return;
+        };
+        if first_proposal.hashed() == proposal.hashed() {
+            return;
+        }
+
+        let evidence = EquivocationEvidence {
+            first: first.clone(),
+            second: msg.clone(),
+            proposals: match kind {
+                SignedKind::Prepare => None,
+                _ => Some((first_proposal.clone(), proposal.clone())),
+            },
+        };
+        let offender = evidence.offender();
+        if self.bft_round_state.staking.is_tombstoned(offender)
+            || self
+                .store
+                .slashing
+                .pending_evidence
+                .iter()
+                .any(|e| e.offender() == offender)
+        {
+            return;
+        }
+        warn!(
+            "⚔️ Validator {} signed two {:?} for different proposals in slot {} view {}",
+            offender, kind, proposal.slot, proposal.view
+        );
+        self.store.slashing.pending_evidence.push(evidence);
+    }
+
+    /// Whether the leader already signed another proposal for the same slot and view.
+    /// Voting for both would be an equivocation.
+    pub(super) fn is_conflicting_proposal(
+        &self,
+        leader: &ValidatorPublicKey,
+        proposal: &ConsensusProposal,
+    ) -> bool {
+        self.store
+            .slashing
+            .signed
+            .get(&(
+                leader.clone(),
+                SignedKind::Prepare,
+                proposal.slot,
+                proposal.view,
+            ))
+            .is_some_and(|(_, first)| first.hashed() != proposal.hashed())
+    }
+
+    /// Proposal with the given hash, among the current one and the ones seen in Prepare messages
+    fn known_proposal(&self, hash: &ConsensusProposalHash) -> Option<ConsensusProposal> {
+        if let Some(proposal) = self.store.slashing.proposals.get(hash) {
+            return Some(proposal.clone());
+        }
+        let current = &self.bft_round_state.consensus_proposal;
+        (&current.hashed() == hash).then(|| current.clone())
+    }
+
+    /// Proposal of the leader that the quorum certificate of its Confirm message is for
+    fn certified_proposal(
+        &self,
+        leader: &ValidatorPublicKey,
+        qc: &QuorumCertificate,
+    ) -> Option<ConsensusProposal> {
+        std::iter::once(&self.bft_round_state.consensus_proposal)
+            .chain(self.store.slashing.proposals.values())
+            .filter(|proposal| &proposal.round_leader == leader)
+            .find(|proposal| Self::certifies(qc, proposal))
+            .cloned()
+    }
+
+    /// Whether the quorum certificate aggregates PrepareVote signatures for the proposal.
+    /// The voting power is not checked, as the stakes may have changed since.
+    fn certifies(qc: &QuorumCertificate, proposal: &ConsensusProposal) -> bool {
+        BlstCrypto::verify_aggregate(&Signed {
+            msg: ConsensusNetMessage::PrepareVote(proposal.hashed()),
+            signature: AggregateSignature {
+                signature: qc.signature.clone(),
+                validators: qc.validators.clone(),
+            },
+        })
+        .unwrap_or(false)
+    }
+
+    /// Verifies that the evidence holds two messages of the same kind signed by a validator for
+    /// different proposals in the same slot and view.
+    pub(super) fn verify_equivocation_evidence(
+        &self,
+        evidence: &EquivocationEvidence,
+    ) -> Result<()> {
+        if evidence.first.signature.validator != evidence.second.signature.validator {
+            bail!("Equivocation evidence messages are signed by different validators");
+        }
+        let (first, second) = match (
+            &evidence.first.msg,
+            &evidence.second.msg,
+            &evidence.proposals,
+        ) {
+            (
+                ConsensusNetMessage::Prepare(first, _),
+                ConsensusNetMessage::Prepare(second, _),
+                _,
+            ) => (first, second),
+            (
+                ConsensusNetMessage::PrepareVote(first_hash),
+                ConsensusNetMessage::PrepareVote(second_hash),
+                Some((first, second)),
+            )
+            | (
+                ConsensusNetMessage::ConfirmAck(first_hash),
+                ConsensusNetMessage::ConfirmAck(second_hash),
+                Some((first, second)),
+            ) => {
+                if &first.hashed() != first_hash || &second.hashed() != second_hash {
+                    bail!("Equivocation evidence proposals do not match the votes");
+                }
+                (first, second)
+            }
+            (
+                ConsensusNetMessage::Confirm(first_qc),
+                ConsensusNetMessage::Confirm(second_qc),
+                Some((first, second)),
+            ) => {
+                if !Self::certifies(first_qc, first) || !Self::certifies(second_qc, second) {
+                    bail!("Equivocation evidence proposals are not certified by the Confirms");
+                }
+                (first, second)
+            }
+            _ => bail!("Equivocation evidence must hold two messages of the same kind"),
+        };
+        if first.slot != second.slot || first.view != second.view {
+            bail!("Equivocation evidence messages are for different slots or views");
+        }
+        if first.hashed() == second.hashed() {
+            bail!("Equivocation evidence messages are for the same proposal");
+        }
+        if !BlstCrypto::verify(&evidence.first)? || !BlstCrypto::verify(&evidence.second)? {
+            bail!("Equivocation evidence has an invalid signature");
+        }
+        if self
+            .bft_round_state
+            .staking
+            .is_tombstoned(evidence.offender())
+        {
+            bail!("Validator {} was already slashed", evidence.offender());
+        }
+        Ok(())
+    }
+
+    /// Verifies that a validator led enough views that timed out to be jailed
+    pub(super) fn verify_validator_to_jail(&self, validator: &ValidatorPublicKey) -> Result<()> {
+        let jailing = &self.config.consensus.jailing;
+        if !jailing.enabled {
+            bail!("Jailing is disabled");
+        }
+        let staking = &self.bft_round_state.staking;
+        if !staking.is_bonded(validator) {
+            bail!("Jailed validator {} is not bonded", validator);
+        }
+        if staking.bonded().len() <= 1 {
+            bail!("Cannot jail the last bonded validator {}", validator);
+        }
+        let missed = self.missed_views(validator);
+        if missed < jailing.missed_views {
+            bail!(
+                "Validator {} only missed {} views, {} are needed to jail it",
+                validator,
+                missed,
+                jailing.missed_views
+            );
+        }
+        Ok(())
+    }
+
+    fn missed_views(&self, validator: &ValidatorPublicKey) -> u64 {
+        self.store
+            .slashing
+            .missed_views
+            .get(validator)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Staking action punishing the next misbehaving validator, if any: equivocation first, then
+    /// views that timed out when jailing is enabled.
+    pub(super) fn next_punishment(&self) -> Option<ConsensusStakingAction> {
+        if let Some(evidence) = self
+            .store
+            .slashing
+            .pending_evidence
+            .iter()
+            .find(|e| self.verify_equivocation_evidence(e).is_ok())
+        {
+            return Some(ConsensusStakingAction::Slash {
+                evidence: Box::new(evidence.clone()),
+            });
+        }
+        self.bft_round_state
+            .staking
+            .bonded()
+            .iter()
+            .find(|v| self.verify_validator_to_jail(v).is_ok())
+            .map(|validator| ConsensusStakingAction::Jail {
+                validator: validator.clone(),
+            })
+    }
+
+    /// Counts one more view that its leader missed: the view ended with a timeout certificate,
+    /// which needs the timeouts of a quorum and cannot be made up by the next leader.
+    pub(super) fn on_view_timed_out(&mut self, leader: &ValidatorPublicKey) {
+        if !self.store.bft_round_state.staking.is_bonded(leader) {
+            return;
+        }
+        let missed = self
+            .store
+            .slashing
+            .missed_views
+            .entry(leader.clone())
+            .or_default();
+        *missed += 1;
+        debug!(
+            "Validator {} led {} consecutive views that timed out",
+            leader, missed
+        );
+    }
+
+    /// Updates the misbehaviours once a slot is committed: its leader is live again, and messages
+    /// and evidence older than the slot are no longer collected.
+    pub(super) fn on_slot_committed(&mut self, slot: Slot, leader: &ValidatorPublicKey) {
+        let bonded = self.store.bft_round_state.staking.bonded().clone();
+        let slashing = &mut self.store.slashing;
+        slashing
+            .missed_views
+            .retain(|v, _| v != leader && bonded.contains(v));
+
+        slashing.signed.retain(|(_, _, s, _), _| *s > slot);
+        slashing
+            .proposals
+            .retain(|_, proposal| proposal.slot > slot);
+        let staking = &self.store.bft_round_state.staking;
+        slashing.pending_evidence.retain(|e| {
+            let slashed = staking.is_tombstoned(e.offender());
+            if slashed {
+                info!("⚔️ Equivocation of {} punished", e.offender());
+            }
+            !slashed
+        });
+    }
+}
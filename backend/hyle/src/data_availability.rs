@@ -123,7 +123,7 @@ impl DataAvailability {
             listen<GenesisEvent> cmd => {
                 if let GenesisEvent::GenesisBlock(signed_block) = cmd {
                     debug!("🌱  Genesis block received with validators {:?}", signed_block.consensus_proposal.staking_actions.clone());
-                    let _= self.handle_signed_block(signed_block, pool_sender.clone()).await.log_error("Handling GenesisBlock Event");
+                    let _= self.handle_signed_block(*signed_block, pool_sender.clone()).await.log_error("Handling GenesisBlock Event");
                 } else {
                     // TODO: I think this is technically a data race with p2p ?
                     self.need_catchup = true;
@@ -198,7 +198,7 @@ impl DataAvailability {
     ) -> Result<()> {
         match evt {
             MempoolBlockEvent::BuiltSignedBlock(signed_block) => {
-                self.handle_signed_block(signed_block, pool_sender).await?;
+                self.handle_signed_block(*signed_block, pool_sender).await?;
             }
             MempoolBlockEvent::StartedBuildingBlocks(height) => {
                 self.catchup_height = Some(height - 1);
@@ -608,7 +608,7 @@ pub mod tests {
         // Feed Da with blocks, should stream them to the client
         for block in blocks {
             block_sender
-                .send(MempoolBlockEvent::BuiltSignedBlock(block))
+                .send(MempoolBlockEvent::BuiltSignedBlock(Box::new(block)))
                 .unwrap();
         }
 
@@ -642,11 +642,11 @@ pub mod tests {
             ccp.consensus_proposal.parent_hash = ccp.consensus_proposal.hashed();
             ccp.consensus_proposal.slot = i;
             block_sender
-                .send(MempoolBlockEvent::BuiltSignedBlock(SignedBlock {
+                .send(MempoolBlockEvent::BuiltSignedBlock(Box::new(SignedBlock {
                     data_proposals: vec![(LaneId::default(), vec![])],
                     certificate: ccp.certificate.clone(),
                     consensus_proposal: ccp.consensus_proposal.clone(),
-                }))
+                })))
                 .unwrap();
         }
 
@@ -743,11 +743,11 @@ pub mod tests {
             ccp.consensus_proposal.parent_hash = ccp.consensus_proposal.hashed();
             ccp.consensus_proposal.slot = i;
             block_sender
-                .send(MempoolBlockEvent::BuiltSignedBlock(SignedBlock {
+                .send(MempoolBlockEvent::BuiltSignedBlock(Box::new(SignedBlock {
                     data_proposals: vec![(LaneId::default(), vec![])],
                     certificate: ccp.certificate.clone(),
                     consensus_proposal: ccp.consensus_proposal.clone(),
-                }))
+                })))
                 .unwrap();
         }
 
@@ -779,11 +779,11 @@ pub mod tests {
             ccp.consensus_proposal.parent_hash = ccp.consensus_proposal.hashed();
             ccp.consensus_proposal.slot = i;
             block_sender
-                .send(MempoolBlockEvent::BuiltSignedBlock(SignedBlock {
+                .send(MempoolBlockEvent::BuiltSignedBlock(Box::new(SignedBlock {
                     data_proposals: vec![(LaneId::default(), vec![])],
                     certificate: ccp.certificate.clone(),
                     consensus_proposal: ccp.consensus_proposal.clone(),
-                }))
+                })))
                 .unwrap();
         }
 
@@ -52,7 +52,7 @@ pub struct Blocks {
 
 impl Blocks {
     fn decode_item(item: Slice) -> Result<SignedBlock> {
-        borsh::from_slice(&item).map_err(Into::into)
+        crate::model::migration::decode_signed_block(&item).map_err(Into::into)
     }
 
     pub fn new(path: &Path) -> Result<Self> {
@@ -7,7 +7,7 @@ use crate::{
     p2p::network::PeerEvent,
     utils::{conf::SharedConf, crypto::SharedBlstCrypto, modules::Module},
 };
-use anyhow::{Error, Result};
+use anyhow::{bail, Error, Result};
 use client_sdk::{
     contract_states,
     helpers::register_hyle_contract,
@@ -31,7 +31,8 @@ use verifiers::NativeVerifiers;
 #[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
 pub enum GenesisEvent {
     NoGenesis,
-    GenesisBlock(SignedBlock),
+    // Boxed to reduce size of the enum
+    GenesisBlock(Box<SignedBlock>),
 }
 impl BusMessage for GenesisEvent {}
 
@@ -113,6 +114,14 @@ impl Genesis {
             return Ok(());
         }
 
+        // The 'hyle' TLD would refuse the genesis transaction, with all the contracts it registers
+        if self.config.consensus.genesis_params.fee_burn_percent > 100 {
+            bail!(
+                "genesis_params.fee_burn_percent is {}, it can be at most 100",
+                self.config.consensus.genesis_params.fee_burn_percent
+            );
+        }
+
         info!("🌱 Building genesis block");
 
         // We will start from the genesis block.
@@ -167,7 +176,9 @@ impl Genesis {
         let signed_block = self.make_genesis_block(genesis_txs, initial_validators);
 
         // At this point, we can setup the genesis block.
-        _ = self.bus.send(GenesisEvent::GenesisBlock(signed_block));
+        _ = self
+            .bus
+            .send(GenesisEvent::GenesisBlock(Box::new(signed_block)));
 
         Ok(())
     }
@@ -408,6 +419,8 @@ impl Genesis {
         let mut map = BTreeMap::default();
         map.insert("blst".into(), NativeVerifiers::Blst.into());
         map.insert("sha3_256".into(), NativeVerifiers::Sha3_256.into());
+        map.insert("ed25519".into(), NativeVerifiers::Ed25519.into());
+        map.insert("secp256k1".into(), NativeVerifiers::Secp256k1.into());
         map.insert("hyllar".into(), ProgramId(hyllar_program_id.clone()));
         map.insert("hydentity".into(), ProgramId(hydentity_program_id.clone()));
         map.insert("staking".into(), ProgramId(staking_program_id.clone()));
@@ -436,6 +449,24 @@ impl Genesis {
         )
         .expect("register sha3_256");
 
+        register_hyle_contract(
+            &mut register_tx,
+            "ed25519".into(),
+            "ed25519".into(),
+            NativeVerifiers::Ed25519.into(),
+            StateDigest::default(),
+        )
+        .expect("register ed25519");
+
+        register_hyle_contract(
+            &mut register_tx,
+            "secp256k1".into(),
+            "secp256k1".into(),
+            NativeVerifiers::Secp256k1.into(),
+            StateDigest::default(),
+        )
+        .expect("register secp256k1");
+
         register_hyle_contract(
             &mut register_tx,
             "staking".into(),
@@ -472,6 +503,32 @@ impl Genesis {
         )
         .expect("register risc0-recursion");
 
+        if let Some(tld_admin) = &self.config.consensus.genesis_tld_admin {
+            register_tx
+                .add_action(
+                    "hyle".into(),
+                    SetTldAdminAction {
+                        tld_admin: Some(tld_admin.into()),
+                    },
+                    None,
+                    None,
+                    None,
+                )
+                .expect("set TLD admin");
+        }
+
+        register_tx
+            .add_action(
+                "hyle".into(),
+                SetConsensusParamsAction {
+                    params: self.config.consensus.genesis_params.clone(),
+                },
+                None,
+                None,
+                None,
+            )
+            .expect("set consensus params");
+
         let genesis_tx: BlobTransaction = register_tx.into();
 
         (map, vec![genesis_tx.into()], ctx)
@@ -531,6 +588,7 @@ impl Genesis {
                     })
                     .collect(),
                 parent_hash: ConsensusProposalHash("genesis".into()),
+                seed_signature: Signature::default(),
             },
         }
     }
@@ -3,6 +3,8 @@
 mod api;
 pub mod contract_state_indexer;
 pub mod da_listener;
+pub mod opaque_state_indexer;
+pub mod standalone;
 
 use crate::model::*;
 use crate::utils::logger::LogMe;
@@ -731,6 +733,17 @@ impl Indexer {
             .await?;
         }
 
+        for (_, update) in block.updated_contract_programs {
+            sqlx::query(
+                "UPDATE contracts SET verifier = $1, program_id = $2 WHERE contract_name = $3",
+            )
+            .bind(&update.verifier.0)
+            .bind(&update.program_id.0)
+            .bind(&update.contract_name.0)
+            .execute(&mut *transaction)
+            .await?;
+        }
+
         // Handling updated contract state
         for (contract_name, state_digest) in block.updated_states {
             let contract_name = &contract_name.0;
@@ -865,6 +878,7 @@ mod test {
                 program_id: ProgramId(vec![3, 2, 1]),
                 state_digest,
                 contract_name,
+                timeout_window: None,
             }
             .as_blob("hyle".into(), None, None)],
         )
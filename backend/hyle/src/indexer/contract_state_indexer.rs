@@ -1,19 +1,26 @@
-use anyhow::{anyhow, Context, Error, Result};
+use anyhow::{anyhow, bail, Context, Error, Result};
+use axum::Router;
 use borsh::{BorshDeserialize, BorshSerialize};
 use client_sdk::contract_indexer::{ContractHandler, ContractStateStore};
+use futures::future::LocalBoxFuture;
 use hyle_contract_sdk::{BlobIndex, ContractName, TxId};
 use hyle_model::{RegisterContractEffect, TxContext};
 use serde::{Deserialize, Serialize};
 use std::{ops::Deref, path::PathBuf, sync::Arc};
 use tokio::sync::RwLock;
 use tracing::debug;
+use utoipa::openapi::OpenApi;
 
 use crate::{
     bus::BusMessage,
     model::{Blob, BlobTransaction, Block, CommonRunContext, Hashed, Transaction, TransactionData},
     module_handle_messages,
     node_state::module::NodeStateEvent,
-    utils::{conf::Conf, logger::LogMe, modules::Module},
+    utils::{
+        conf::Conf,
+        logger::LogMe,
+        modules::{Module, ModulesHandler},
+    },
 };
 
 use super::indexer_bus_client::IndexerBusClient;
@@ -38,6 +45,148 @@ pub struct ContractStateIndexerCtx {
     pub contract_name: ContractName,
 }
 
+/// A contract state handler that can be picked by name in the `contract_indexers` configuration.
+/// Crates linked into the node binary add their own with [`register_contract_handler!`](crate::register_contract_handler).
+pub struct ContractHandlerKind {
+    pub name: &'static str,
+    build: BuildContractIndexer,
+}
+
+/// Builds the indexer module of a contract
+pub type BuildContractIndexer =
+    for<'a> fn(&'a mut ModulesHandler, ContractStateIndexerCtx) -> LocalBoxFuture<'a, Result<()>>;
+
+inventory::collect!(ContractHandlerKind);
+
+impl ContractHandlerKind {
+    pub const fn new<State>(name: &'static str) -> Self
+    where
+        State: Serialize
+            + Clone
+            + Sync
+            + Send
+            + std::fmt::Debug
+            + ContractHandler
+            + BorshSerialize
+            + BorshDeserialize
+            + 'static,
+    {
+        ContractHandlerKind {
+            name,
+            build: build_contract_state_indexer::<State>,
+        }
+    }
+
+    /// A handler indexing contracts with another module than a [`ContractStateIndexer`]
+    pub const fn with_builder(name: &'static str, build: BuildContractIndexer) -> Self {
+        ContractHandlerKind { name, build }
+    }
+
+    /// Looks up a registered handler by name
+    pub fn find(name: &str) -> Result<&'static ContractHandlerKind> {
+        let mut kinds = inventory::iter::<ContractHandlerKind>().filter(|kind| kind.name == name);
+        let Some(kind) = kinds.next() else {
+            let mut known: Vec<&str> = inventory::iter::<ContractHandlerKind>()
+                .map(|kind| kind.name)
+                .collect();
+            known.sort();
+            bail!(
+                "Unknown contract handler '{name}', registered handlers are: {}",
+                known.join(", ")
+            );
+        };
+        if kinds.next().is_some() {
+            bail!("Contract handler '{name}' is registered more than once");
+        }
+        Ok(kind)
+    }
+}
+
+/// Registers a [`ContractHandler`] under a name, so that the `contract_indexers`
+/// configuration can index contracts with it.
+///
+/// ```ignore
+/// hyle::register_contract_handler!("my_contract", my_contract::MyState);
+/// ```
+#[macro_export]
+macro_rules! register_contract_handler {
+    ($name:expr, $state:ty) => {
+        $crate::inventory::submit! {
+            $crate::indexer::contract_state_indexer::ContractHandlerKind::new::<$state>($name)
+        }
+    };
+}
+
+register_contract_handler!("hyllar", hyllar::Hyllar);
+register_contract_handler!("hydentity", hydentity::Hydentity);
+
+fn build_contract_state_indexer<State>(
+    handler: &mut ModulesHandler,
+    ctx: ContractStateIndexerCtx,
+) -> LocalBoxFuture<'_, Result<()>>
+where
+    State: Serialize
+        + Clone
+        + Sync
+        + Send
+        + std::fmt::Debug
+        + ContractHandler
+        + BorshSerialize
+        + BorshDeserialize
+        + 'static,
+{
+    Box::pin(handler.build_module::<ContractStateIndexer<State>>(ctx))
+}
+
+/// Builds a contract state indexer for each contract listed in `contract_indexers`
+pub async fn build_contract_state_indexers(
+    handler: &mut ModulesHandler,
+    common: &Arc<CommonRunContext>,
+) -> Result<()> {
+    for indexer in common.config.contract_indexers.iter() {
+        let kind = ContractHandlerKind::find(&indexer.handler)
+            .context(format!("Indexing contract {}", indexer.contract_name))?;
+        (kind.build)(
+            handler,
+            ContractStateIndexerCtx {
+                common: common.clone(),
+                contract_name: indexer.contract_name.clone().into(),
+            },
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Serves a contract indexer API under `/v1/indexer/contract/<contract_name>`
+pub fn nest_contract_api(ctx: &ContractStateIndexerCtx, nested: Router<()>, mut api: OpenApi) {
+    if let Ok(mut o) = ctx.common.openapi.lock() {
+        // Deduplicate operation ids
+        for p in api.paths.paths.iter_mut() {
+            p.1.get = p.1.get.take().map(|mut g| {
+                g.operation_id = g.operation_id.map(|o| format!("{}_{o}", ctx.contract_name));
+                g
+            });
+            p.1.post = p.1.post.take().map(|mut g| {
+                g.operation_id = g.operation_id.map(|o| format!("{}_{o}", ctx.contract_name));
+                g
+            });
+        }
+        *o = o
+            .clone()
+            .nest(format!("/v1/indexer/contract/{}", ctx.contract_name), api);
+    }
+
+    if let Ok(mut guard) = ctx.common.router.lock() {
+        if let Some(router) = guard.take() {
+            guard.replace(router.nest(
+                format!("/v1/indexer/contract/{}", ctx.contract_name).as_str(),
+                nested,
+            ));
+        }
+    }
+}
+
 impl<State> Module for ContractStateIndexer<State>
 where
     State: Serialize
@@ -65,32 +214,9 @@ where
         store.contract_name = ctx.contract_name.clone();
         let store = Arc::new(RwLock::new(store));
 
-        let (nested, mut api) = State::api(Arc::clone(&store)).await;
-        if let Ok(mut o) = ctx.common.openapi.lock() {
-            // Deduplicate operation ids
-            for p in api.paths.paths.iter_mut() {
-                p.1.get = p.1.get.take().map(|mut g| {
-                    g.operation_id = g.operation_id.map(|o| format!("{}_{o}", ctx.contract_name));
-                    g
-                });
-                p.1.post = p.1.post.take().map(|mut g| {
-                    g.operation_id = g.operation_id.map(|o| format!("{}_{o}", ctx.contract_name));
-                    g
-                });
-            }
-            *o = o
-                .clone()
-                .nest(format!("/v1/indexer/contract/{}", ctx.contract_name), api);
-        }
+        let (nested, api) = State::api(Arc::clone(&store)).await;
+        nest_contract_api(&ctx, nested, api);
 
-        if let Ok(mut guard) = ctx.common.router.lock() {
-            if let Some(router) = guard.take() {
-                guard.replace(router.nest(
-                    format!("/v1/indexer/contract/{}", ctx.contract_name).as_str(),
-                    nested,
-                ));
-            }
-        }
         let config = ctx.common.config.clone();
 
         Ok(ContractStateIndexer {
@@ -199,7 +325,7 @@ where
     }
 
     async fn handle_register_contract(&self, contract: RegisterContractEffect) -> Result<()> {
-        let state = State::default();
+        let state = State::initial_state(contract.state_digest.clone())?;
         tracing::info!(cn = %self.contract_name, "📝 Registered suppored contract '{}' with initial state '{state:?}'", contract.contract_name);
         self.store.write().await.state = Some(state);
         Ok(())
@@ -244,7 +370,7 @@ mod tests {
     use crate::bus::metrics::BusMetrics;
     use crate::model::SignedBlock;
     use crate::node_state::NodeState;
-    use crate::utils::conf::Conf;
+    use crate::utils::conf::{Conf, ContractIndexerConf};
     use crate::{bus::SharedMessageBus, model::CommonRunContext};
     use std::sync::Arc;
 
@@ -272,6 +398,10 @@ mod tests {
     }
 
     impl ContractHandler for MockState {
+        fn initial_state(state_digest: StateDigest) -> Result<Self> {
+            state_digest.try_into()
+        }
+
         fn handle(
             tx: &BlobTransaction,
             index: BlobIndex,
@@ -287,6 +417,44 @@ mod tests {
         }
     }
 
+    crate::register_contract_handler!("mock", MockState);
+
+    #[test]
+    fn test_find_contract_handler() {
+        assert_eq!(ContractHandlerKind::find("mock").unwrap().name, "mock");
+        assert_eq!(ContractHandlerKind::find("hyllar").unwrap().name, "hyllar");
+        assert!(ContractHandlerKind::find("unknown").is_err());
+    }
+
+    #[test_log::test(tokio::test)]
+    async fn test_build_contract_state_indexers() {
+        let bus = SharedMessageBus::new(BusMetrics::global("global".to_string()));
+        let common = |handler: &str| {
+            Arc::new(CommonRunContext {
+                bus: bus.new_handle(),
+                config: Arc::new(Conf {
+                    contract_indexers: vec![ContractIndexerConf {
+                        contract_name: "test_contract".into(),
+                        handler: handler.into(),
+                    }],
+                    ..Conf::default()
+                }),
+                router: Default::default(),
+                openapi: Default::default(),
+            })
+        };
+        let mut handler = ModulesHandler::new(&bus).await;
+
+        assert!(build_contract_state_indexers(&mut handler, &common("mock"))
+            .await
+            .is_ok());
+        assert!(
+            build_contract_state_indexers(&mut handler, &common("unknown"))
+                .await
+                .is_err()
+        );
+    }
+
     async fn build_indexer(contract_name: ContractName) -> ContractStateIndexer<MockState> {
         let common = Arc::new(CommonRunContext {
             bus: SharedMessageBus::new(BusMetrics::global("global".to_string())),
@@ -304,12 +472,13 @@ mod tests {
     }
 
     async fn register_contract(indexer: &mut ContractStateIndexer<MockState>) {
-        let state_digest = StateDigest::default();
+        let state_digest = StateDigest(vec![4, 5, 6]);
         let rce = RegisterContractEffect {
             contract_name: indexer.contract_name.clone(),
             state_digest,
             verifier: "test".into(),
             program_id: ProgramId(vec![]),
+            timeout_window: None,
         };
         indexer.handle_register_contract(rce).await.unwrap();
     }
@@ -321,8 +490,9 @@ mod tests {
 
         register_contract(&mut indexer).await;
 
+        // Seeded from the registration digest
         let store = indexer.store.read().await;
-        assert!(store.state.is_some());
+        assert_eq!(store.state.clone().unwrap().0, vec![4, 5, 6]);
     }
 
     #[test_log::test(tokio::test)]
@@ -346,7 +516,7 @@ mod tests {
         assert!(store
             .unsettled_blobs
             .contains_key(&TxId(DataProposalHash::default(), tx_hash.clone())));
-        assert!(store.state.clone().unwrap().0.is_empty());
+        assert_eq!(store.state.clone().unwrap().0, vec![4, 5, 6]);
     }
 
     #[test_log::test(tokio::test)]
@@ -0,0 +1,528 @@
+//! Contract indexer for contracts whose code the node does not link.
+//!
+//! Instead of executing the contract, it records what the node state settles:
+//! the state digest history, the settled blobs and the outputs of the proofs that settled them.
+
+use anyhow::{anyhow, Result};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+use client_sdk::contract_indexer::AppError;
+use futures::future::LocalBoxFuture;
+use hyle_contract_sdk::{BlobIndex, BlockHeight, ContractName, HyleOutput, StateDigest, TxHash};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Deref,
+    path::PathBuf,
+    sync::Arc,
+};
+use tokio::sync::RwLock;
+use tracing::debug;
+use utoipa::{IntoParams, ToSchema};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    model::{BlobTransaction, Block, Hashed, Identity, TransactionData},
+    module_handle_messages,
+    node_state::module::NodeStateEvent,
+    utils::modules::{Module, ModulesHandler},
+};
+
+use super::{
+    contract_state_indexer::{nest_contract_api, ContractHandlerKind, ContractStateIndexerCtx},
+    indexer_bus_client::IndexerBusClient,
+};
+
+inventory::submit! {
+    ContractHandlerKind::with_builder("opaque", build_opaque_state_indexer)
+}
+
+fn build_opaque_state_indexer(
+    handler: &mut ModulesHandler,
+    ctx: ContractStateIndexerCtx,
+) -> LocalBoxFuture<'_, Result<()>> {
+    Box::pin(handler.build_module::<OpaqueStateIndexer>(ctx))
+}
+
+/// A blob of the contract, in a transaction settled as successful
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct SettledBlob {
+    pub tx_hash: TxHash,
+    pub blob_index: BlobIndex,
+    pub block_height: BlockHeight,
+    pub identity: Identity,
+    pub data: Vec<u8>,
+    /// Output of the proof that settled the blob, if it needed one
+    pub hyle_output: Option<HyleOutput>,
+}
+
+#[derive(Debug, Default, BorshSerialize, BorshDeserialize)]
+pub struct OpaqueStateStore {
+    pub contract_name: ContractName,
+    /// State digest of the contract at the end of each block that changed it
+    pub states: BTreeMap<BlockHeight, StateDigest>,
+    pub settled_blobs: Vec<SettledBlob>,
+    unsettled_blobs: BTreeMap<TxHash, BlobTransaction>,
+    /// Proven outputs, by blob tx hash, blob index and blob proof output index
+    proof_outputs: BTreeMap<(TxHash, usize, usize), HyleOutput>,
+}
+
+type OpaqueStore = Arc<RwLock<OpaqueStateStore>>;
+
+impl OpaqueStateStore {
+    pub fn handle_block(&mut self, block: Block) {
+        let contract_name = self.contract_name.clone();
+
+        for (_, contract) in block.registered_contracts.iter() {
+            if contract.contract_name == contract_name {
+                self.states
+                    .insert(block.block_height, contract.state_digest.clone());
+            }
+        }
+        if let Some(state_digest) = block.updated_states.get(&contract_name) {
+            self.states.insert(block.block_height, state_digest.clone());
+        }
+
+        for (_, tx) in block.txs {
+            if let TransactionData::Blob(tx) = tx.transaction_data {
+                if tx.blobs.iter().any(|b| b.contract_name == contract_name) {
+                    self.unsettled_blobs.insert(tx.hashed(), tx);
+                }
+            }
+        }
+
+        for output in block.blob_proof_outputs {
+            if output.contract_name == contract_name {
+                self.proof_outputs.insert(
+                    (
+                        output.blob_tx_hash,
+                        output.blob_index.0,
+                        output.blob_proof_output_index,
+                    ),
+                    output.hyle_output,
+                );
+            }
+        }
+
+        // Which proof output settled each blob
+        let settled_with: HashMap<(TxHash, usize), Option<usize>> = block
+            .verified_blobs
+            .into_iter()
+            .map(|(tx_hash, index, proof_output)| ((tx_hash, index.0), proof_output))
+            .collect();
+
+        for tx_hash in block.successful_txs {
+            let Some(tx) = self.unsettled_blobs.remove(&tx_hash) else {
+                continue;
+            };
+            debug!(cn = %contract_name, "🔨 Settling transaction: {}", tx_hash);
+            for (index, blob) in tx.blobs.iter().enumerate() {
+                if blob.contract_name != contract_name {
+                    continue;
+                }
+                let hyle_output = settled_with
+                    .get(&(tx_hash.clone(), index))
+                    .copied()
+                    .flatten()
+                    .and_then(|proof_output| {
+                        self.proof_outputs
+                            .remove(&(tx_hash.clone(), index, proof_output))
+                    });
+                self.settled_blobs.push(SettledBlob {
+                    tx_hash: tx_hash.clone(),
+                    blob_index: BlobIndex(index),
+                    block_height: block.block_height,
+                    identity: tx.identity.clone(),
+                    data: blob.data.0.clone(),
+                    hyle_output,
+                });
+            }
+            self.drop_proof_outputs(&tx_hash);
+        }
+
+        for tx_hash in block.failed_txs.iter().chain(block.timed_out_txs.iter()) {
+            if self.unsettled_blobs.remove(tx_hash).is_some() {
+                self.drop_proof_outputs(tx_hash);
+            }
+        }
+    }
+
+    /// State digest in effect at the end of `height`
+    pub fn state_at(&self, height: BlockHeight) -> Option<(BlockHeight, &StateDigest)> {
+        self.states
+            .range(..=height)
+            .next_back()
+            .map(|(height, digest)| (*height, digest))
+    }
+
+    fn drop_proof_outputs(&mut self, tx_hash: &TxHash) {
+        self.proof_outputs.retain(|(hash, _, _), _| hash != tx_hash);
+    }
+}
+
+pub struct OpaqueStateIndexer {
+    bus: IndexerBusClient,
+    store: OpaqueStore,
+    contract_name: ContractName,
+    file: PathBuf,
+}
+
+impl Module for OpaqueStateIndexer {
+    type Context = ContractStateIndexerCtx;
+
+    async fn build(ctx: Self::Context) -> Result<Self> {
+        let bus = IndexerBusClient::new_from_bus(ctx.common.bus.new_handle()).await;
+        let file = ctx
+            .common
+            .config
+            .data_directory
+            .join(format!("opaque_state_indexer_{}.bin", ctx.contract_name).as_str());
+
+        let mut store = Self::load_from_disk_or_default::<OpaqueStateStore>(file.as_path());
+        store.contract_name = ctx.contract_name.clone();
+        let store = Arc::new(RwLock::new(store));
+
+        let (nested, api) = OpenApiRouter::default()
+            .routes(routes!(get_state))
+            .routes(routes!(get_state_at))
+            .routes(routes!(get_states))
+            .routes(routes!(get_settled_blobs))
+            .routes(routes!(get_settled_blobs_by_tx))
+            .split_for_parts();
+        nest_contract_api(&ctx, nested.with_state(store.clone()), api);
+
+        Ok(OpaqueStateIndexer {
+            bus,
+            store,
+            contract_name: ctx.contract_name,
+            file,
+        })
+    }
+
+    fn run(&mut self) -> impl futures::Future<Output = Result<()>> + Send {
+        self.start()
+    }
+}
+
+impl OpaqueStateIndexer {
+    pub async fn start(&mut self) -> Result<()> {
+        module_handle_messages! {
+            on_bus self.bus,
+            listen<NodeStateEvent> event => {
+                let NodeStateEvent::NewBlock(block) = event;
+                self.store.write().await.handle_block(*block);
+            }
+        };
+
+        if let Err(e) = Self::save_on_disk::<OpaqueStateStore>(
+            self.file.as_path(),
+            self.store.read().await.deref(),
+        ) {
+            tracing::warn!(cn = %self.contract_name, "Failed to save opaque state indexer on disk: {}", e);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct StateResponse {
+    block_height: BlockHeight,
+    /// Hex encoded state digest
+    state_digest: String,
+}
+
+impl StateResponse {
+    fn new(block_height: BlockHeight, state_digest: &StateDigest) -> Self {
+        StateResponse {
+            block_height,
+            state_digest: hex::encode(&state_digest.0),
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct SettledBlobResponse {
+    tx_hash: TxHash,
+    blob_index: BlobIndex,
+    block_height: BlockHeight,
+    identity: Identity,
+    /// Hex encoded blob data
+    data: String,
+    /// Hex encoded program outputs, if the blob was settled by a proof
+    program_outputs: Option<String>,
+    /// Hex encoded state digest after the blob, if the blob was settled by a proof
+    next_state: Option<String>,
+}
+
+impl From<&SettledBlob> for SettledBlobResponse {
+    fn from(blob: &SettledBlob) -> Self {
+        SettledBlobResponse {
+            tx_hash: blob.tx_hash.clone(),
+            blob_index: blob.blob_index,
+            block_height: blob.block_height,
+            identity: blob.identity.clone(),
+            data: hex::encode(&blob.data),
+            program_outputs: blob
+                .hyle_output
+                .as_ref()
+                .map(|output| hex::encode(&output.program_outputs)),
+            next_state: blob
+                .hyle_output
+                .as_ref()
+                .map(|output| hex::encode(&output.next_state.0)),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct HistoryPagination {
+    #[serde(default)]
+    pub offset: usize,
+    /// Defaults to 100, at most 1000
+    pub limit: Option<usize>,
+}
+
+impl HistoryPagination {
+    fn limit(&self) -> usize {
+        self.limit.unwrap_or(100).min(1000)
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/state",
+    tag = "Contract",
+    responses(
+        (status = OK, description = "Latest settled state digest of the contract", body = StateResponse)
+    )
+)]
+async fn get_state(State(store): State<OpaqueStore>) -> Result<Json<StateResponse>, AppError> {
+    let store = store.read().await;
+    store
+        .states
+        .last_key_value()
+        .map(|(height, digest)| Json(StateResponse::new(*height, digest)))
+        .ok_or(AppError(
+            StatusCode::NOT_FOUND,
+            anyhow!("No state found for contract '{}'", store.contract_name),
+        ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/state/{height}",
+    params(
+        ("height" = u64, Path, description = "Block height")
+    ),
+    tag = "Contract",
+    responses(
+        (status = OK, description = "State digest of the contract at the end of a block", body = StateResponse)
+    )
+)]
+async fn get_state_at(
+    Path(height): Path<u64>,
+    State(store): State<OpaqueStore>,
+) -> Result<Json<StateResponse>, AppError> {
+    let store = store.read().await;
+    store
+        .state_at(BlockHeight(height))
+        .map(|(height, digest)| Json(StateResponse::new(height, digest)))
+        .ok_or(AppError(
+            StatusCode::NOT_FOUND,
+            anyhow!(
+                "No state found for contract '{}' at height {height}",
+                store.contract_name
+            ),
+        ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/states",
+    params(HistoryPagination),
+    tag = "Contract",
+    responses(
+        (status = OK, description = "State digest history of the contract, oldest first", body = [StateResponse])
+    )
+)]
+async fn get_states(
+    Query(pagination): Query<HistoryPagination>,
+    State(store): State<OpaqueStore>,
+) -> Json<Vec<StateResponse>> {
+    let store = store.read().await;
+    Json(
+        store
+            .states
+            .iter()
+            .skip(pagination.offset)
+            .take(pagination.limit())
+            .map(|(height, digest)| StateResponse::new(*height, digest))
+            .collect(),
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/blobs",
+    params(HistoryPagination),
+    tag = "Contract",
+    responses(
+        (status = OK, description = "Settled blobs of the contract, oldest first", body = [SettledBlobResponse])
+    )
+)]
+async fn get_settled_blobs(
+    Query(pagination): Query<HistoryPagination>,
+    State(store): State<OpaqueStore>,
+) -> Json<Vec<SettledBlobResponse>> {
+    let store = store.read().await;
+    Json(
+        store
+            .settled_blobs
+            .iter()
+            .skip(pagination.offset)
+            .take(pagination.limit())
+            .map(Into::into)
+            .collect(),
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/blobs/{tx_hash}",
+    params(
+        ("tx_hash" = String, Path, description = "Blob transaction hash")
+    ),
+    tag = "Contract",
+    responses(
+        (status = OK, description = "Settled blobs of the contract in a transaction", body = [SettledBlobResponse])
+    )
+)]
+async fn get_settled_blobs_by_tx(
+    Path(tx_hash): Path<TxHash>,
+    State(store): State<OpaqueStore>,
+) -> Result<Json<Vec<SettledBlobResponse>>, AppError> {
+    let store = store.read().await;
+    let blobs: Vec<SettledBlobResponse> = store
+        .settled_blobs
+        .iter()
+        .filter(|blob| blob.tx_hash == tx_hash)
+        .map(Into::into)
+        .collect();
+    if blobs.is_empty() {
+        return Err(AppError(
+            StatusCode::NOT_FOUND,
+            anyhow!(
+                "No settled blob of '{}' in tx {tx_hash}",
+                store.contract_name
+            ),
+        ));
+    }
+    Ok(Json(blobs))
+}
+
+#[cfg(test)]
+mod tests {
+    use hyle_contract_sdk::BlobData;
+    use hyle_model::HandledBlobProofOutput;
+
+    use super::*;
+    use crate::model::{Blob, Transaction, TxId};
+
+    fn blob_tx(contract_name: &str, data: Vec<u8>) -> BlobTransaction {
+        BlobTransaction::new(
+            "alice.hydentity",
+            vec![Blob {
+                contract_name: contract_name.into(),
+                data: BlobData(data),
+            }],
+        )
+    }
+
+    fn sequenced(height: u64, tx: &BlobTransaction) -> Block {
+        Block {
+            block_height: BlockHeight(height),
+            txs: vec![(
+                TxId(Default::default(), tx.hashed()),
+                Transaction::from(TransactionData::Blob(tx.clone())),
+            )],
+            ..Block::default()
+        }
+    }
+
+    fn store() -> OpaqueStateStore {
+        OpaqueStateStore {
+            contract_name: "opaque".into(),
+            ..OpaqueStateStore::default()
+        }
+    }
+
+    #[test]
+    fn test_settled_blob_keeps_proof_output() {
+        let mut store = store();
+        let tx = blob_tx("opaque", vec![1, 2, 3]);
+        let tx_hash = tx.hashed();
+        store.handle_block(sequenced(1, &tx));
+        assert!(store.settled_blobs.is_empty());
+
+        let hyle_output = HyleOutput {
+            next_state: StateDigest(vec![4]),
+            program_outputs: b"ok".to_vec(),
+            ..HyleOutput::default()
+        };
+        store.handle_block(Block {
+            block_height: BlockHeight(2),
+            blob_proof_outputs: vec![HandledBlobProofOutput {
+                proof_tx_hash: TxHash("proof".into()),
+                blob_tx_hash: tx_hash.clone(),
+                blob_index: BlobIndex(0),
+                contract_name: "opaque".into(),
+                hyle_output: hyle_output.clone(),
+                blob_proof_output_index: 0,
+            }],
+            verified_blobs: vec![(tx_hash.clone(), BlobIndex(0), Some(0))],
+            successful_txs: vec![tx_hash.clone()],
+            updated_states: BTreeMap::from([("opaque".into(), StateDigest(vec![4]))]),
+            ..Block::default()
+        });
+
+        assert_eq!(store.settled_blobs.len(), 1);
+        let settled = store.settled_blobs.first().unwrap();
+        assert_eq!(settled.tx_hash, tx_hash);
+        assert_eq!(settled.data, vec![1, 2, 3]);
+        assert_eq!(settled.hyle_output, Some(hyle_output));
+        assert!(store.unsettled_blobs.is_empty());
+        assert!(store.proof_outputs.is_empty());
+
+        assert_eq!(store.state_at(BlockHeight(1)), None);
+        assert_eq!(
+            store.state_at(BlockHeight(5)),
+            Some((BlockHeight(2), &StateDigest(vec![4])))
+        );
+    }
+
+    #[test]
+    fn test_failed_and_foreign_txs_are_not_recorded() {
+        let mut store = store();
+        let failed = blob_tx("opaque", vec![1]);
+        let foreign = blob_tx("other", vec![2]);
+        store.handle_block(sequenced(1, &failed));
+        store.handle_block(sequenced(1, &foreign));
+        assert_eq!(store.unsettled_blobs.len(), 1);
+
+        store.handle_block(Block {
+            block_height: BlockHeight(2),
+            failed_txs: vec![failed.hashed()],
+            successful_txs: vec![foreign.hashed()],
+            updated_states: BTreeMap::from([("other".into(), StateDigest(vec![4]))]),
+            ..Block::default()
+        });
+
+        assert!(store.settled_blobs.is_empty());
+        assert!(store.unsettled_blobs.is_empty());
+        assert!(store.states.is_empty());
+    }
+}
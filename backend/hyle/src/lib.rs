@@ -36,3 +36,6 @@ pub mod tests;
 
 pub mod model;
 pub mod tools;
+
+#[doc(hidden)]
+pub use inventory;
@@ -17,12 +17,13 @@ use crate::{
     },
 };
 
+use admission::{RecentTxHashes, SubmitTx};
 use anyhow::{bail, Context, Result};
 use api::RestApiMessage;
 use block_construction::BlockUnderConstruction;
 use borsh::{BorshDeserialize, BorshSerialize};
-use client_sdk::tcp::TcpServerMessage;
 use hyle_contract_sdk::{ContractName, ProgramId, Verifier};
+use hyle_model::api::APITxRejection;
 use metrics::MempoolMetrics;
 use serde::{Deserialize, Serialize};
 use staking::state::Staking;
@@ -35,6 +36,7 @@ use std::{
 };
 use storage::{LaneEntry, Storage};
 use tokio::task::JoinSet;
+use verification_pool::VerificationPool;
 use verify_tx::DataProposalVerdict;
 // Pick one of the two implementations
 // use storage_memory::LanesStorage;
@@ -42,6 +44,7 @@ use storage_fjall::LanesStorage;
 use strum_macros::IntoStaticStr;
 use tracing::{debug, info, trace, warn};
 
+pub mod admission;
 pub mod api;
 pub mod block_construction;
 pub mod metrics;
@@ -49,6 +52,7 @@ pub mod own_lane;
 pub mod storage;
 pub mod storage_fjall;
 pub mod storage_memory;
+pub mod verification_pool;
 pub mod verifiers;
 pub mod verify_tx;
 
@@ -72,6 +76,12 @@ impl KnownContracts {
             (verifier.clone(), program_id.clone()),
         );
     }
+
+    #[inline(always)]
+    fn delete_contract(&mut self, contract_name: &ContractName) {
+        debug!("🏊❌ Deleting contract from mempool {:?}", contract_name);
+        self.0.remove(contract_name);
+    }
 }
 
 module_bus_client! {
@@ -81,7 +91,7 @@ struct MempoolBusClient {
     sender(MempoolStatusEvent),
     receiver(SignedByValidator<MempoolNetMessage>),
     receiver(RestApiMessage),
-    receiver(TcpServerMessage),
+    receiver(Query<SubmitTx, Result<TxHash, APITxRejection>>),
     receiver(ConsensusEvent),
     receiver(GenesisEvent),
     receiver(NodeStateEvent),
@@ -112,6 +122,8 @@ pub struct Mempool {
     bus: MempoolBusClient,
     file: Option<PathBuf>,
     running_tasks: JoinSet<Result<InternalMempoolEvent>>,
+    verification: VerificationPool,
+    recent_tx_hashes: RecentTxHashes,
     conf: SharedConf,
     crypto: SharedBlstCrypto,
     metrics: MempoolMetrics,
@@ -177,12 +189,10 @@ impl Module for Mempool {
         let bus = MempoolBusClient::new_from_bus(ctx.common.bus.new_handle()).await;
         let metrics = MempoolMetrics::global(ctx.common.config.id.clone());
 
-        let api = api::api(&ctx.common).await;
-        if let Ok(mut guard) = ctx.common.router.lock() {
-            if let Some(router) = guard.take() {
-                guard.replace(router.nest("/v1/", api));
-            }
-        }
+        info!(
+            "🔎 Proof verifiers: {}",
+            verifiers::registered_verifiers()?.join(", ")
+        );
 
         let attributes = Self::load_from_disk::<MempoolStore>(
             ctx.common
@@ -213,11 +223,25 @@ impl Module for Mempool {
             .entry("hyle".into())
             .or_insert_with(|| (Verifier("hyle".to_owned()), ProgramId(vec![])));
 
+        let verification = VerificationPool::new(
+            &ctx.common.config.mempool,
+            attributes.known_contracts.clone(),
+        );
+
+        let api = api::api(&ctx.common, verification.queue.clone()).await;
+        if let Ok(mut guard) = ctx.common.router.lock() {
+            if let Some(router) = guard.take() {
+                guard.replace(router.nest("/v1/", api));
+            }
+        }
+
         Ok(Mempool {
             bus,
             file: Some(ctx.common.config.data_directory.clone()),
             conf: ctx.common.config.clone(),
             running_tasks: JoinSet::new(),
+            verification,
+            recent_tx_hashes: RecentTxHashes::default(),
             metrics,
             crypto: Arc::clone(&ctx.node.crypto),
             lanes: LanesStorage::new(&ctx.common.config.data_directory, lanes_tip)?,
@@ -242,7 +266,7 @@ impl Mempool {
         module_handle_messages! {
             on_bus self.bus,
             delay_shutdown_until {
-                self.running_tasks.is_empty()
+                self.running_tasks.is_empty() && self.verification.tasks.is_empty()
             },
             listen<SignedByValidator<MempoolNetMessage>> cmd => {
                 let _ = self.handle_net_message(cmd)
@@ -251,28 +275,44 @@ impl Mempool {
             listen<RestApiMessage> cmd => {
                 let _ = self.handle_api_message(cmd).log_error("Handling API Message in Mempool");
             }
-            listen<TcpServerMessage> cmd => {
-                let _ = self.handle_tcp_server_message(cmd).log_error("Handling TCP Server message in Mempool");
-            }
             listen<ConsensusEvent> cmd => {
                 let _ = self.handle_consensus_event(cmd)
                     .log_error("Handling ConsensusEvent in Mempool");
             }
             listen<NodeStateEvent> cmd => {
                 let NodeStateEvent::NewBlock(block) = cmd;
+                // Deletions come first: a contract deleted and registered again in the same block is kept
+                for (_, contract_name) in block.deleted_contracts {
+                    self.handle_contract_deletion(&contract_name);
+                }
                 for (_, contract) in block.registered_contracts {
                     self.handle_contract_registration(contract);
                 }
+                for (_, update) in block.updated_contract_programs {
+                    self.handle_contract_program_update(update);
+                }
             }
             command_response<QueryNewCut, Cut> staking => {
                 self.handle_querynewcut(staking)
             }
+            command_response<SubmitTx, Result<TxHash, APITxRejection>> cmd => {
+                self.on_submitted_tx(cmd.tx.clone(), cmd.sender.clone())
+            }
             Some(event) = self.running_tasks.join_next() => {
                 if let Ok(Ok(event)) = event.log_error("Processing InternalMempoolEvent from Blocker Joinset") {
                     let _ = self.handle_internal_event(event)
                         .log_error("Handling InternalMempoolEvent in Mempool");
                 }
             }
+            _ = self.verification.queue.notified() => {
+                self.start_proof_verifications();
+            }
+            Some(verified) = self.verification.tasks.join_next() => {
+                if let Ok(verified) = verified.log_error("Verifying proof in blocker") {
+                    let _ = self.on_verified_proof(verified)
+                        .log_error("Handling verified proof in Mempool");
+                }
+            }
             _ = interval.tick() => {
                 let _ = self.handle_data_proposal_management()
                     .log_error("Creating Data Proposal on tick");
@@ -304,6 +344,24 @@ impl Mempool {
         );
     }
 
+    fn handle_contract_deletion(&mut self, contract_name: &ContractName) {
+        #[allow(clippy::expect_used, reason = "not held across await")]
+        let mut known_contracts = self.known_contracts.write().expect("logic issue");
+        known_contracts.delete_contract(contract_name);
+    }
+
+    // Unlike registrations, program updates are not applied optimistically:
+    // proofs of transactions sequenced before the update still use the previous program.
+    fn handle_contract_program_update(&mut self, effect: UpdateContractProgramEffect) {
+        #[allow(clippy::expect_used, reason = "not held across await")]
+        let mut known_contracts = self.known_contracts.write().expect("logic issue");
+        known_contracts.register_contract(
+            &effect.contract_name,
+            &effect.verifier,
+            &effect.program_id,
+        );
+    }
+
     // Optimistically parse Hyle tx blobs
     fn handle_hyle_contract_registration(&mut self, blob_tx: &BlobTransaction) {
         #[allow(clippy::expect_used, reason = "not held across await")]
@@ -696,16 +754,22 @@ pub mod test {
             let lanes = LanesStorage::new(&tmp_dir, BTreeMap::default()).unwrap();
             let bus = MempoolBusClient::new_from_bus(shared_bus.new_handle()).await;
 
+            let inner = MempoolStore::default();
+            let conf = SharedConf::default();
+            let verification = VerificationPool::new(&conf.mempool, inner.known_contracts.clone());
+
             // Initialize Mempool
             Mempool {
                 bus,
                 file: None,
-                conf: SharedConf::default(),
+                conf,
                 running_tasks: JoinSet::new(),
+                verification,
+                recent_tx_hashes: RecentTxHashes::default(),
                 crypto: Arc::new(crypto),
                 metrics: MempoolMetrics::global("id".to_string()),
                 lanes,
-                inner: MempoolStore::default(),
+                inner,
             }
         }
 
@@ -1072,6 +1136,7 @@ pub mod test {
                             staking_actions: vec![],
                             timestamp: 777,
                             parent_hash: ConsensusProposalHash("test".to_string()),
+                            seed_signature: Signature::default(),
                         },
                         certificate: AggregateSignature::default(),
                     },
@@ -1089,6 +1154,7 @@ pub mod test {
                 program_id: ProgramId(vec![]),
                 state_digest: StateDigest(vec![0, 1, 2, 3]),
                 contract_name: name,
+                timeout_window: None,
             }
             .as_blob("hyle".into(), None, None)],
         )
@@ -1371,4 +1437,24 @@ pub mod test {
             .unwrap();
         assert_eq!(0, cut.len());
     }
+
+    #[test_log::test(tokio::test)]
+    async fn test_deleted_contracts_are_forgotten() -> Result<()> {
+        let mut ctx = MempoolTestCtx::new("mempool").await;
+        let contract_name = ContractName::new("test1");
+        ctx.submit_tx(&make_register_contract_tx(contract_name.clone()));
+        let is_known = |ctx: &MempoolTestCtx| {
+            ctx.mempool
+                .known_contracts
+                .read()
+                .unwrap()
+                .0
+                .contains_key(&contract_name)
+        };
+        assert!(is_known(&ctx));
+
+        ctx.mempool.handle_contract_deletion(&contract_name);
+        assert!(!is_known(&ctx));
+        Ok(())
+    }
 }
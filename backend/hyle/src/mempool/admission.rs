@@ -0,0 +1,415 @@
+//! Checks of the transactions sent to the node, before they enter the mempool.
+
+use std::collections::{HashSet, VecDeque};
+
+use anyhow::Result;
+use hyle_model::api::{APITxRejection, TxRejectionCode};
+use tracing::{debug, warn};
+
+use crate::{
+    model::{contract_registration::validate_contract_registration_metadata, *},
+    utils::conf::MempoolConf,
+};
+
+use super::{verifiers::validate_verifier, KnownContracts};
+
+/// Asks the mempool to admit a transaction sent to the node.
+/// It answers with the transaction hash, or with the reason it was refused.
+#[derive(Debug, Clone)]
+pub struct SubmitTx {
+    pub tx: Transaction,
+    /// Address the transaction was sent from, to limit the proofs each one queues
+    pub sender: Option<String>,
+}
+
+/// Hashes of the last admitted transactions, oldest first
+#[derive(Default)]
+pub struct RecentTxHashes {
+    hashes: HashSet<TxHash>,
+    order: VecDeque<TxHash>,
+}
+
+impl RecentTxHashes {
+    /// Remembers a hash, forgetting the oldest ones beyond `capacity`.
+    /// Returns false if the hash is already known.
+    fn insert(&mut self, tx_hash: TxHash, capacity: usize) -> bool {
+        if !self.hashes.insert(tx_hash.clone()) {
+            return false;
+        }
+        self.order.push_back(tx_hash);
+        while self.order.len() > capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.hashes.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+/// Checks a blob transaction against the limits of the configuration and the contracts
+/// known to the mempool.
+pub fn check_blob_tx(
+    conf: &MempoolConf,
+    known_contracts: &KnownContracts,
+    tx_hash: &TxHash,
+    blob_tx: &BlobTransaction,
+) -> Result<(), APITxRejection> {
+    let reject = |code, message: String| APITxRejection {
+        tx_hash: tx_hash.clone(),
+        code,
+        message,
+    };
+
+    if blob_tx.blobs.len() > conf.max_blobs_per_tx {
+        return Err(reject(
+            TxRejectionCode::TooManyBlobs,
+            format!(
+                "Transaction has {} blobs, at most {} are allowed",
+                blob_tx.blobs.len(),
+                conf.max_blobs_per_tx
+            ),
+        ));
+    }
+    if let Some((index, blob)) = blob_tx
+        .blobs
+        .iter()
+        .enumerate()
+        .find(|(_, blob)| blob.data.0.len() > conf.max_blob_size)
+    {
+        return Err(reject(
+            TxRejectionCode::BlobTooLarge,
+            format!(
+                "Blob {} is {} bytes, at most {} are allowed",
+                index,
+                blob.data.0.len(),
+                conf.max_blob_size
+            ),
+        ));
+    }
+    let size = blob_tx.estimate_size();
+    if size > conf.max_tx_size {
+        return Err(reject(
+            TxRejectionCode::TxTooLarge,
+            format!(
+                "Transaction is {} bytes, at most {} are allowed",
+                size, conf.max_tx_size
+            ),
+        ));
+    }
+
+    blob_tx.validate_identity().map_err(|e| {
+        reject(
+            TxRejectionCode::InvalidIdentity,
+            format!("Invalid identity for blob tx: {}", e),
+        )
+    })?;
+
+    // Contracts registered by the 'hyle' blobs of the transaction can be used by its other blobs
+    let mut registered = HashSet::new();
+    for blob in blob_tx.blobs.iter() {
+        if blob.contract_name.0 != "hyle" {
+            continue;
+        }
+        if let Ok(tx) = StructuredBlobData::<RegisterContractAction>::try_from(blob.data.clone()) {
+            let parameters = tx.parameters;
+            validate_contract_registration_metadata(
+                &"hyle".into(),
+                &parameters.contract_name,
+                &parameters.verifier,
+                &parameters.program_id,
+                &parameters.state_digest,
+            )
+            .and_then(|_| validate_verifier(&parameters.verifier, &parameters.program_id))
+            .map_err(|e| reject(TxRejectionCode::InvalidContractRegistration, e.to_string()))?;
+            registered.insert(parameters.contract_name);
+        }
+    }
+    if let Some(blob) = blob_tx.blobs.iter().find(|blob| {
+        !known_contracts.0.contains_key(&blob.contract_name)
+            && !registered.contains(&blob.contract_name)
+    }) {
+        return Err(reject(
+            TxRejectionCode::UnknownContract,
+            format!("Contract {} is not registered", blob.contract_name),
+        ));
+    }
+
+    let paid = staking::paid_tx_fees(&blob_tx.blobs)
+        .map_err(|e| reject(TxRejectionCode::InvalidFeePayment, e))?;
+    // The minimum is a policy of this node only. Settlement takes the declared amount as is, so
+    // a transaction admitted here never fails for its fees when the price of a byte moves.
+    let min_fees = conf.min_fee_per_byte as u128 * size as u128;
+    if paid < min_fees {
+        return Err(reject(
+            TxRejectionCode::InsufficientFee,
+            format!(
+                "Transaction pays {} in fees, at least {} are needed for its {} bytes",
+                paid, min_fees, size
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+impl super::Mempool {
+    /// Admits a transaction sent to the node in the mempool, unless it is refused by the
+    /// admission checks. Only blob transactions are checked: proofs are queued for verification,
+    /// unless too many are waiting already.
+    pub(super) fn on_submitted_tx(
+        &mut self,
+        tx: Transaction,
+        sender: Option<String>,
+    ) -> Result<Result<TxHash, APITxRejection>> {
+        let tx_hash = tx.hashed();
+        if let TransactionData::Proof(proof_tx) = tx.transaction_data {
+            return Ok(self
+                .queue_proof_tx(proof_tx, sender)
+                .map(|_| tx_hash.clone())
+                .map_err(|e| APITxRejection {
+                    tx_hash,
+                    code: TxRejectionCode::ProofQueueFull,
+                    message: e.to_string(),
+                }));
+        }
+        if let Err(rejection) = self.admit_tx(&tx_hash, &tx) {
+            warn!(
+                "Refusing transaction {}: {}",
+                rejection.tx_hash, rejection.message
+            );
+            self.metrics.add_rejected_tx(rejection.code.into());
+            return Ok(Err(rejection));
+        }
+        self.on_new_tx(tx)?;
+        Ok(Ok(tx_hash))
+    }
+
+    fn admit_tx(&mut self, tx_hash: &TxHash, tx: &Transaction) -> Result<(), APITxRejection> {
+        let TransactionData::Blob(blob_tx) = &tx.transaction_data else {
+            return Ok(());
+        };
+        {
+            #[allow(clippy::expect_used, reason = "not held across await")]
+            let known_contracts = self.known_contracts.read().expect("logic issue");
+            check_blob_tx(&self.conf.mempool, &known_contracts, tx_hash, blob_tx)?;
+        }
+        if !self
+            .recent_tx_hashes
+            .insert(tx_hash.clone(), self.conf.mempool.recent_tx_hashes)
+        {
+            return Err(APITxRejection {
+                tx_hash: tx_hash.clone(),
+                code: TxRejectionCode::DuplicateTx,
+                message: format!("Transaction {} was already sent", tx_hash),
+            });
+        }
+        debug!("Admitted blob tx {}", tx_hash);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyle_contract_sdk::erc20::ERC20Action;
+    use hyle_model::api::TxRejectionCode;
+
+    use super::{check_blob_tx, RecentTxHashes};
+    use crate::{mempool::KnownContracts, model::*, utils::conf::MempoolConf};
+
+    fn known_contracts() -> KnownContracts {
+        KnownContracts(
+            ["hyle", "hydentity", "hyllar", "staking"]
+                .into_iter()
+                .map(|name| (name.into(), ("test".into(), ProgramId::default())))
+                .collect(),
+        )
+    }
+
+    fn blob(contract_name: &str, size: usize) -> Blob {
+        Blob {
+            contract_name: contract_name.into(),
+            data: BlobData(vec![0; size]),
+        }
+    }
+
+    fn check(conf: &MempoolConf, blob_tx: &BlobTransaction) -> Result<(), TxRejectionCode> {
+        check_blob_tx(conf, &known_contracts(), &blob_tx.hashed(), blob_tx)
+            .map_err(|rejection| rejection.code)
+    }
+
+    #[test]
+    fn test_check_blob_tx() {
+        let conf = MempoolConf::default();
+
+        let tx = BlobTransaction::new("bob.hydentity", vec![blob("hydentity", 10)]);
+        assert_eq!(check(&conf, &tx), Ok(()));
+
+        let tx = BlobTransaction::new("bob", vec![blob("hydentity", 10)]);
+        assert_eq!(check(&conf, &tx), Err(TxRejectionCode::InvalidIdentity));
+        let tx = BlobTransaction::new("bob.hydentity", vec![blob("hyllar", 10)]);
+        assert_eq!(check(&conf, &tx), Err(TxRejectionCode::InvalidIdentity));
+
+        let tx = BlobTransaction::new(
+            "bob.hydentity",
+            vec![blob("hydentity", 10), blob("unknown", 10)],
+        );
+        assert_eq!(check(&conf, &tx), Err(TxRejectionCode::UnknownContract));
+    }
+
+    #[test]
+    fn test_check_blob_tx_registering_contracts() {
+        let conf = MempoolConf::default();
+        let register = |contract_name: &str| {
+            RegisterContractAction {
+                verifier: "test".into(),
+                program_id: ProgramId(vec![1]),
+                state_digest: StateDigest(vec![0]),
+                contract_name: contract_name.into(),
+                timeout_window: None,
+            }
+            .as_blob("hyle".into(), None, None)
+        };
+
+        // The contract registered by the transaction can be used by its other blobs
+        let tx = BlobTransaction::new(
+            "hyle.hyle",
+            vec![register("new_contract"), blob("new_contract", 10)],
+        );
+        assert_eq!(check(&conf, &tx), Ok(()));
+
+        let tx = BlobTransaction::new("hyle.hyle", vec![register("new_contract.hyllar")]);
+        assert_eq!(
+            check(&conf, &tx),
+            Err(TxRejectionCode::InvalidContractRegistration)
+        );
+
+        // This node can't verify proofs of a contract with an unknown verifier
+        let tx = BlobTransaction::new(
+            "hyle.hyle",
+            vec![RegisterContractAction {
+                verifier: "unknown".into(),
+                program_id: ProgramId(vec![1]),
+                state_digest: StateDigest(vec![0]),
+                contract_name: "new_contract".into(),
+                timeout_window: None,
+            }
+            .as_blob("hyle".into(), None, None)],
+        );
+        assert_eq!(
+            check(&conf, &tx),
+            Err(TxRejectionCode::InvalidContractRegistration)
+        );
+    }
+
+    #[test]
+    fn test_check_blob_tx_sizes() {
+        let conf = MempoolConf {
+            max_blobs_per_tx: 3,
+            max_blob_size: 100,
+            max_tx_size: 250,
+            ..MempoolConf::default()
+        };
+
+        let tx = BlobTransaction::new("bob.hydentity", vec![blob("hydentity", 100)]);
+        assert_eq!(check(&conf, &tx), Ok(()));
+
+        let tx = BlobTransaction::new("bob.hydentity", vec![blob("hydentity", 101)]);
+        assert_eq!(check(&conf, &tx), Err(TxRejectionCode::BlobTooLarge));
+
+        let tx = BlobTransaction::new("bob.hydentity", vec![blob("hydentity", 1); 4]);
+        assert_eq!(check(&conf, &tx), Err(TxRejectionCode::TooManyBlobs));
+
+        let tx = BlobTransaction::new("bob.hydentity", vec![blob("hydentity", 100); 3]);
+        assert_eq!(check(&conf, &tx), Err(TxRejectionCode::TxTooLarge));
+    }
+
+    #[test]
+    fn test_check_blob_tx_fees() {
+        let conf = MempoolConf {
+            min_fee_per_byte: 1,
+            ..MempoolConf::default()
+        };
+        let pay_fees = |amount, sponsor: Option<&str>| {
+            StakingAction::PayTxFees {
+                amount,
+                sponsor: sponsor.map(Identity::new),
+            }
+            .as_blob("staking".into(), None, None)
+        };
+        let transfer = |amount| {
+            ERC20Action::Transfer {
+                recipient: "staking".to_string(),
+                amount,
+            }
+            .as_blob("hyllar".into(), None, None)
+        };
+        let transfer_from = |owner: &str, amount| {
+            ERC20Action::TransferFrom {
+                owner: owner.to_string(),
+                recipient: "staking".to_string(),
+                amount,
+            }
+            .as_blob("hyllar".into(), None, None)
+        };
+
+        let tx = BlobTransaction::new("bob.hydentity", vec![blob("hydentity", 10)]);
+        assert_eq!(check(&conf, &tx), Err(TxRejectionCode::InsufficientFee));
+        assert_eq!(check(&MempoolConf::default(), &tx), Ok(()));
+
+        let tx = BlobTransaction::new(
+            "bob.hydentity",
+            vec![blob("hydentity", 10), pay_fees(1000, None), transfer(1000)],
+        );
+        assert_eq!(check(&conf, &tx), Ok(()));
+        let tx = BlobTransaction::new(
+            "bob.hydentity",
+            vec![blob("hydentity", 10), pay_fees(10, None), transfer(10)],
+        );
+        assert_eq!(check(&conf, &tx), Err(TxRejectionCode::InsufficientFee));
+
+        // The fees must be paid by the transfer that follows
+        let tx = BlobTransaction::new(
+            "bob.hydentity",
+            vec![blob("hydentity", 10), pay_fees(1000, None), transfer(10)],
+        );
+        assert_eq!(check(&conf, &tx), Err(TxRejectionCode::InvalidFeePayment));
+        let tx = BlobTransaction::new(
+            "bob.hydentity",
+            vec![blob("hydentity", 10), pay_fees(1000, None)],
+        );
+        assert_eq!(check(&conf, &tx), Err(TxRejectionCode::InvalidFeePayment));
+
+        // A sponsor pays with a transfer from its allowance
+        let tx = BlobTransaction::new(
+            "bob.hydentity",
+            vec![
+                blob("hydentity", 10),
+                pay_fees(1000, Some("resto.hydentity")),
+                transfer_from("resto.hydentity", 1000),
+            ],
+        );
+        assert_eq!(check(&conf, &tx), Ok(()));
+        let tx = BlobTransaction::new(
+            "bob.hydentity",
+            vec![
+                blob("hydentity", 10),
+                pay_fees(1000, Some("resto.hydentity")),
+                transfer(1000),
+            ],
+        );
+        assert_eq!(check(&conf, &tx), Err(TxRejectionCode::InvalidFeePayment));
+    }
+
+    #[test]
+    fn test_recent_tx_hashes() {
+        let mut recent = RecentTxHashes::default();
+        assert!(recent.insert(TxHash::new("a"), 2));
+        assert!(!recent.insert(TxHash::new("a"), 2));
+        assert!(recent.insert(TxHash::new("b"), 2));
+        assert!(recent.insert(TxHash::new("c"), 2));
+
+        // The oldest hash was forgotten
+        assert!(recent.insert(TxHash::new("a"), 2));
+        assert!(!recent.insert(TxHash::new("c"), 2));
+    }
+}
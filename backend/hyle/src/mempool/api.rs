@@ -1,17 +1,29 @@
 use anyhow::anyhow;
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json, Router};
+use axum::{
+    extract::{rejection::ExtensionRejection, ConnectInfo, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json, Router,
+};
 use borsh::{BorshDeserialize, BorshSerialize};
 use hyle_contract_sdk::TxHash;
 use hyle_model::{
-    api::APIRegisterContract, ContractAction, RegisterContractAction, StructuredBlobData,
+    api::{APIRegisterContract, APITxRejection, TxRejectionCode},
+    ContractAction, RegisterContractAction,
 };
 use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
 use tracing::info;
 use utoipa::OpenApi;
 use utoipa_axum::{router::OpenApiRouter, routes};
 
 use crate::{
-    bus::{bus_client, metrics::BusMetrics, BusClientSender, BusMessage},
+    bus::{
+        bus_client,
+        command_response::{CmdRespClient, Query},
+        metrics::BusMetrics,
+        BusMessage,
+    },
     model::{
         contract_registration::validate_contract_registration_metadata, BlobTransaction,
         CommonRunContext, Hashed, ProofTransaction, Transaction, TransactionData,
@@ -19,6 +31,12 @@ use crate::{
     rest::AppError,
 };
 
+use super::{
+    admission::SubmitTx, verification_pool::VerificationQueue, verifiers::validate_verifier,
+};
+
+/// Transactions sent to the mempool without admission checks, by tools and tests.
+/// The REST API submits transactions with [SubmitTx].
 #[derive(Debug, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize)]
 pub enum RestApiMessage {
     NewTx(Transaction),
@@ -27,20 +45,22 @@ impl BusMessage for RestApiMessage {}
 
 bus_client! {
 struct RestBusClient {
-    sender(RestApiMessage),
+    sender(Query<SubmitTx, Result<TxHash, APITxRejection>>),
 }
 }
 
 pub struct RouterState {
     bus: RestBusClient,
+    verification_queue: VerificationQueue,
 }
 
 #[derive(OpenApi)]
 struct MempoolAPI;
 
-pub async fn api(ctx: &CommonRunContext) -> Router<()> {
+pub async fn api(ctx: &CommonRunContext, verification_queue: VerificationQueue) -> Router<()> {
     let state = RouterState {
         bus: RestBusClient::new_from_bus(ctx.bus.new_handle()).await,
+        verification_queue,
     };
 
     let (router, api) = OpenApiRouter::with_openapi(MempoolAPI::openapi())
@@ -58,15 +78,30 @@ pub async fn api(ctx: &CommonRunContext) -> Router<()> {
 async fn handle_send(
     mut state: RouterState,
     payload: TransactionData,
-) -> Result<Json<TxHash>, AppError> {
-    let tx: Transaction = payload.into();
-    let tx_hash = tx.hashed();
-    state
-        .bus
-        .send(RestApiMessage::NewTx(tx))
-        .map(|_| tx_hash)
-        .map(Json)
-        .map_err(|err| AppError(StatusCode::INTERNAL_SERVER_ERROR, anyhow!(err)))
+) -> Result<Response, AppError> {
+    let submit = SubmitTx {
+        tx: payload.into(),
+        sender: None,
+    };
+    match state.bus.request(submit).await? {
+        Ok(tx_hash) => Ok(Json(tx_hash).into_response()),
+        Err(rejection) => Ok((rejection_status(rejection.code), Json(rejection)).into_response()),
+    }
+}
+
+fn rejection_status(code: TxRejectionCode) -> StatusCode {
+    match code {
+        TxRejectionCode::TooManyBlobs
+        | TxRejectionCode::BlobTooLarge
+        | TxRejectionCode::TxTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+        TxRejectionCode::DuplicateTx => StatusCode::CONFLICT,
+        TxRejectionCode::InvalidIdentity
+        | TxRejectionCode::InvalidContractRegistration
+        | TxRejectionCode::UnknownContract
+        | TxRejectionCode::InvalidFeePayment => StatusCode::BAD_REQUEST,
+        TxRejectionCode::InsufficientFee => StatusCode::PAYMENT_REQUIRED,
+        TxRejectionCode::ProofQueueFull => StatusCode::TOO_MANY_REQUESTS,
+    }
 }
 
 #[utoipa::path(
@@ -74,7 +109,10 @@ async fn handle_send(
     path = "/tx/send/blob",
     tag = "Mempool",
     responses(
-        (status = OK, description = "Send blob transaction", body = TxHash)
+        (status = OK, description = "Send blob transaction", body = TxHash),
+        (status = BAD_REQUEST, description = "Invalid identity, contract registration or unknown contract", body = APITxRejection),
+        (status = CONFLICT, description = "The transaction was already sent", body = APITxRejection),
+        (status = PAYLOAD_TOO_LARGE, description = "Too many or too large blobs", body = APITxRejection)
     )
 )]
 pub async fn send_blob_transaction(
@@ -82,32 +120,6 @@ pub async fn send_blob_transaction(
     Json(payload): Json<BlobTransaction>,
 ) -> Result<impl IntoResponse, AppError> {
     info!("Got blob transaction {}", payload.hashed());
-
-    // Filter out incorrect contract-registring transactions
-    for blob in payload.blobs.iter() {
-        if blob.contract_name.0 != "hyle" {
-            continue;
-        }
-        if let Ok(tx) = StructuredBlobData::<RegisterContractAction>::try_from(blob.data.clone()) {
-            let parameters = tx.parameters;
-            validate_contract_registration_metadata(
-                &"hyle".into(),
-                &parameters.contract_name,
-                &parameters.verifier,
-                &parameters.program_id,
-                &parameters.state_digest,
-            )
-            .map_err(|err| AppError(StatusCode::BAD_REQUEST, anyhow!(err)))?;
-        }
-    }
-
-    // Filter out transactions with incorrect identity
-    if let Err(e) = payload.validate_identity() {
-        return Err(AppError(
-            StatusCode::BAD_REQUEST,
-            anyhow!("Invalid identity for blob tx: {}", e),
-        ));
-    }
     handle_send(state, TransactionData::Blob(payload)).await
 }
 
@@ -116,15 +128,28 @@ pub async fn send_blob_transaction(
     path = "/tx/send/proof",
     tag = "Mempool",
     responses(
-        (status = OK, description = "Send proof transaction", body = TxHash)
+        (status = OK, description = "Send proof transaction", body = TxHash),
+        (status = TOO_MANY_REQUESTS, description = "Too many proofs of this contract or from this address are waiting for verification"),
+        (status = SERVICE_UNAVAILABLE, description = "Too many proofs are waiting for verification")
     )
 )]
 pub async fn send_proof_transaction(
     State(state): State<RouterState>,
+    connect_info: Result<ConnectInfo<SocketAddr>, ExtensionRejection>,
     Json(payload): Json<ProofTransaction>,
 ) -> Result<impl IntoResponse, AppError> {
-    info!("Got proof transaction {}", payload.hashed());
-    handle_send(state, TransactionData::Proof(payload)).await
+    let tx_hash = payload.hashed();
+    info!("Got proof transaction {}", tx_hash);
+    // Queued here rather than sent to the mempool, so that the status tells whether the node
+    // or this sender is overloaded
+    let sender = connect_info
+        .ok()
+        .map(|ConnectInfo(addr)| addr.ip().to_string());
+    state
+        .verification_queue
+        .push(payload, sender)
+        .map_err(|err| AppError(err.status_code(), anyhow!(err)))?;
+    Ok(Json(tx_hash))
 }
 
 #[utoipa::path(
@@ -132,7 +157,8 @@ pub async fn send_proof_transaction(
     path = "/contract/register",
     tag = "Mempool",
     responses(
-        (status = OK, description = "Register contract", body = TxHash)
+        (status = OK, description = "Register contract", body = TxHash),
+        (status = CONFLICT, description = "The contract registration was already sent", body = APITxRejection)
     )
 )]
 pub async fn register_contract(
@@ -147,6 +173,7 @@ pub async fn register_contract(
         &payload.program_id,
         &payload.state_digest,
     )
+    .and_then(|_| validate_verifier(&payload.verifier, &payload.program_id))
     .map_err(|err| AppError(StatusCode::BAD_REQUEST, anyhow!(err)))?;
 
     let tx = BlobTransaction::new(
@@ -156,6 +183,7 @@ pub async fn register_contract(
             program_id: payload.program_id,
             state_digest: payload.state_digest,
             contract_name: payload.contract_name,
+            timeout_window: payload.timeout_window,
         }
         .as_blob(owner, None, None)],
     );
@@ -169,8 +197,12 @@ impl Clone for RouterState {
         Self {
             bus: RestBusClient::new(
                 Pick::<BusMetrics>::get(&self.bus).clone(),
-                Pick::<tokio::sync::broadcast::Sender<RestApiMessage>>::get(&self.bus).clone(),
+                Pick::<
+                    tokio::sync::broadcast::Sender<Query<SubmitTx, Result<TxHash, APITxRejection>>>,
+                >::get(&self.bus)
+                .clone(),
             ),
+            verification_queue: self.verification_queue.clone(),
         }
     }
 }
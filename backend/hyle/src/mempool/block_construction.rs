@@ -78,11 +78,11 @@ impl super::Mempool {
             .context("Processing queued committedConsensusProposal")?;
 
         self.bus
-            .send(MempoolBlockEvent::BuiltSignedBlock(SignedBlock {
+            .send(MempoolBlockEvent::BuiltSignedBlock(Box::new(SignedBlock {
                 data_proposals: block_data,
                 certificate: buc.ccp.certificate.clone(),
                 consensus_proposal: buc.ccp.consensus_proposal.clone(),
-            }))?;
+            })))?;
 
         Ok(())
     }
@@ -325,6 +325,7 @@ pub mod test {
                         staking_actions: vec![],
                         timestamp: 777,
                         parent_hash: ConsensusProposalHash("test".to_string()),
+                        seed_signature: Signature::default(),
                     },
                     certificate: AggregateSignature::default(),
                 },
@@ -370,6 +371,7 @@ pub mod test {
                         staking_actions: vec![],
                         timestamp: 888,
                         parent_hash: ConsensusProposalHash("test".to_string()),
+                        seed_signature: Signature::default(),
                     },
                     certificate: AggregateSignature::default(),
                 },
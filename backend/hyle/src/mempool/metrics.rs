@@ -1,5 +1,5 @@
 use opentelemetry::{
-    metrics::{Counter, Gauge},
+    metrics::{Counter, Gauge, Histogram},
     InstrumentationScope, KeyValue,
 };
 
@@ -10,6 +10,7 @@ use super::QueryNewCut;
 pub struct MempoolMetrics {
     signature_error: Counter<u64>,
     api_tx: Counter<u64>,
+    rejected_tx: Counter<u64>,
     data_proposal: Counter<u64>,
     proposed_txs: Counter<u64>,
     data_vote: Counter<u64>,
@@ -17,6 +18,9 @@ pub struct MempoolMetrics {
     sync_reply: Counter<u64>,
     pending_tx: Gauge<u64>,
     new_cut: Counter<u64>,
+    verification_queue: Gauge<u64>,
+    verification_refused: Counter<u64>,
+    verification_latency: Histogram<f64>,
 }
 
 impl MempoolMetrics {
@@ -31,6 +35,9 @@ impl MempoolMetrics {
                 .u64_counter(format!("{mempool}_signature_error"))
                 .build(),
             api_tx: my_meter.u64_counter(format!("{mempool}_api_tx")).build(),
+            rejected_tx: my_meter
+                .u64_counter(format!("{mempool}_rejected_tx"))
+                .build(),
             data_proposal: my_meter
                 .u64_counter(format!("{mempool}_data_proposal"))
                 .build(),
@@ -46,6 +53,16 @@ impl MempoolMetrics {
                 .build(),
             pending_tx: my_meter.u64_gauge(format!("{mempool}_pending_tx")).build(),
             new_cut: my_meter.u64_counter(format!("{mempool}_new_cut")).build(),
+            verification_queue: my_meter
+                .u64_gauge(format!("{mempool}_verification_queue"))
+                .build(),
+            verification_refused: my_meter
+                .u64_counter(format!("{mempool}_verification_refused"))
+                .build(),
+            verification_latency: my_meter
+                .f64_histogram(format!("{mempool}_verification_latency"))
+                .with_unit("s")
+                .build(),
         }
     }
 
@@ -56,10 +73,27 @@ impl MempoolMetrics {
     pub fn add_api_tx(&self, kind: &'static str) {
         self.api_tx.add(1, &[KeyValue::new("tx_kind", kind)]);
     }
+    pub fn add_rejected_tx(&self, code: &'static str) {
+        self.rejected_tx.add(1, &[KeyValue::new("code", code)]);
+    }
     pub fn snapshot_pending_tx(&self, nb: usize) {
         self.pending_tx
             .record(nb as u64, &[KeyValue::new("status", "pending")])
     }
+    pub fn snapshot_verification_queue(&self, queued: usize, running: usize) {
+        self.verification_queue
+            .record(queued as u64, &[KeyValue::new("status", "queued")]);
+        self.verification_queue
+            .record(running as u64, &[KeyValue::new("status", "running")]);
+    }
+    pub fn add_verification_refused(&self, reason: &'static str) {
+        self.verification_refused
+            .add(1, &[KeyValue::new("reason", reason)]);
+    }
+    pub fn record_verification_latency(&self, verifier: String, seconds: f64) {
+        self.verification_latency
+            .record(seconds, &[KeyValue::new("verifier", verifier)]);
+    }
     pub fn add_new_cut(&self, nc: &QueryNewCut) {
         self.new_cut.add(
             1,
@@ -1,14 +1,13 @@
 //! Logic for processing the API inbound TXs in the mempool.
 
-use crate::{bus::BusClientSender, mempool::InternalMempoolEvent, model::*};
+use crate::{bus::BusClientSender, model::*};
 
 use anyhow::{bail, Context, Result};
-use client_sdk::tcp::TcpServerMessage;
 use std::collections::HashSet;
 use std::sync::Arc;
 use tracing::{debug, trace};
 
-use super::verifiers::{verify_proof, verify_recursive_proof};
+use super::verifiers::{is_recursive, verify_proof, verify_recursive_proof};
 use super::{api::RestApiMessage, storage::Storage};
 use super::{KnownContracts, MempoolNetMessage};
 
@@ -19,12 +18,6 @@ impl super::Mempool {
         }
     }
 
-    pub(super) fn handle_tcp_server_message(&mut self, command: TcpServerMessage) -> Result<()> {
-        match command {
-            TcpServerMessage::NewTx(tx) => self.on_new_tx(tx).context("New Tx"),
-        }
-    }
-
     fn get_last_data_prop_hash_in_own_lane(&self) -> Option<DataProposalHash> {
         self.lanes.get_lane_hash_tip(&self.own_lane_id()).cloned()
     }
@@ -205,22 +198,16 @@ impl super::Mempool {
         match tx.transaction_data {
             TransactionData::Blob(ref blob_tx) => {
                 debug!("Got new blob tx {}", tx_hash);
-                // TODO: we should check if the registration handler contract exists.
                 // TODO: would be good to not need to clone here.
                 self.handle_hyle_contract_registration(blob_tx);
             }
-            TransactionData::Proof(ref proof_tx) => {
+            TransactionData::Proof(proof_tx) => {
                 debug!(
                     "Got new proof tx {} for {}",
-                    tx.hashed(),
-                    proof_tx.contract_name
+                    tx_hash, proof_tx.contract_name
                 );
-                let kc = self.known_contracts.clone();
-                self.running_tasks.spawn_blocking(move || {
-                    let tx =
-                        Self::process_proof_tx(kc, tx).context("Processing proof tx in blocker")?;
-                    Ok(InternalMempoolEvent::OnProcessedNewTx(tx))
-                });
+                // Refusals are logged, there is no one to answer them to
+                let _ = self.queue_proof_tx(proof_tx, None);
 
                 return Ok(());
             }
@@ -253,7 +240,7 @@ impl super::Mempool {
         Ok(())
     }
 
-    fn process_proof_tx(
+    pub(super) fn process_proof_tx(
         known_contracts: Arc<std::sync::RwLock<KnownContracts>>,
         mut tx: Transaction,
     ) -> Result<Transaction> {
@@ -270,7 +257,7 @@ impl super::Mempool {
             .context("Contract unknown")?
             .clone();
 
-        let is_recursive = proof_transaction.contract_name.0 == "risc0-recursion";
+        let is_recursive = is_recursive(&verifier, &program_id);
 
         let (hyle_outputs, program_ids) = if is_recursive {
             let (program_ids, hyle_outputs) =
@@ -331,9 +318,45 @@ pub mod test {
         utils::crypto::BlstCrypto,
     };
     use anyhow::Result;
+    use hyle_model::api::TxRejectionCode;
 
     use crate::mempool::test::*;
 
+    #[test_log::test(tokio::test)]
+    async fn test_submitted_tx_admission() -> Result<()> {
+        let mut ctx = MempoolTestCtx::new("mempool").await;
+        ctx.mempool
+            .known_contracts
+            .write()
+            .unwrap()
+            .register_contract(&"hyle".into(), &"hyle".into(), &ProgramId(vec![]));
+
+        let register_tx = make_register_contract_tx(ContractName::new("test1"));
+        assert_eq!(
+            ctx.mempool.on_submitted_tx(register_tx.clone(), None)?,
+            Ok(register_tx.hashed())
+        );
+
+        let rejection = ctx.mempool.on_submitted_tx(register_tx, None)?.unwrap_err();
+        assert_eq!(rejection.code, TxRejectionCode::DuplicateTx);
+
+        // The contract registered by the first transaction can be used right away
+        let blob = |contract_name: &str| Blob {
+            contract_name: contract_name.into(),
+            data: BlobData(vec![1, 2, 3]),
+        };
+        let blob_tx: Transaction = BlobTransaction::new("bob.test1", vec![blob("test1")]).into();
+        assert!(ctx.mempool.on_submitted_tx(blob_tx, None)?.is_ok());
+
+        let blob_tx = BlobTransaction::new("bob.test2", vec![blob("test2")]).into();
+        let rejection = ctx.mempool.on_submitted_tx(blob_tx, None)?.unwrap_err();
+        assert_eq!(rejection.code, TxRejectionCode::UnknownContract);
+
+        assert_eq!(ctx.mempool.waiting_dissemination_txs.len(), 2);
+
+        Ok(())
+    }
+
     #[test_log::test(tokio::test)]
     async fn test_single_mempool_receiving_new_tx() -> Result<()> {
         let mut ctx = MempoolTestCtx::new("mempool").await;
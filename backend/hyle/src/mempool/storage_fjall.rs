@@ -1,6 +1,7 @@
 use std::{collections::BTreeMap, path::Path, sync::Arc};
 
 use anyhow::{bail, Result};
+use borsh::BorshDeserialize;
 use fjall::{
     Config, Keyspace, KvSeparationOptions, PartitionCreateOptions, PartitionHandle, Slice,
 };
@@ -8,7 +9,7 @@ use hyle_model::{LaneId, Signed, SignedByValidator, ValidatorSignature};
 use tracing::info;
 
 use crate::{
-    model::{DataProposalHash, Hashed},
+    model::{migration::DataProposalV0, DataProposalHash, Hashed},
     utils::logger::LogMe,
 };
 
@@ -231,8 +232,27 @@ impl Storage for LanesStorage {
     }
 }
 
+/// Layout of the lane entries stored before proposals carried a seed signature, see
+/// [`crate::model::migration`]. Stored signatures are data votes, whose layout is unchanged.
+#[derive(BorshDeserialize)]
+struct LaneEntryV0 {
+    data_proposal: DataProposalV0,
+    cumul_size: LaneBytesSize,
+    signatures: Vec<SignedByValidator<MempoolNetMessage>>,
+}
+
 fn decode_from_item(item: Slice) -> Result<LaneEntry> {
-    borsh::from_slice(&item).map_err(Into::into)
+    borsh::from_slice(&item)
+        .or_else(|err| {
+            borsh::from_slice::<LaneEntryV0>(&item)
+                .map(|entry| LaneEntry {
+                    data_proposal: entry.data_proposal.into(),
+                    cumul_size: entry.cumul_size,
+                    signatures: entry.signatures,
+                })
+                .map_err(|_| err)
+        })
+        .map_err(Into::into)
 }
 
 fn encode_to_item(lane_entry: LaneEntry) -> Result<Slice> {
@@ -52,7 +52,7 @@ async fn impl_test_mempool_isnt_blocked_by_proof_verification() -> Result<()> {
 
     let contract_name = ContractName::new("test1");
 
-    node_client.send(GenesisEvent::GenesisBlock(SignedBlock {
+    node_client.send(GenesisEvent::GenesisBlock(Box::new(SignedBlock {
         data_proposals: vec![(
             LaneId(node_modules.crypto.validator_pubkey().clone()),
             vec![DataProposal::new(
@@ -64,6 +64,7 @@ async fn impl_test_mempool_isnt_blocked_by_proof_verification() -> Result<()> {
                         program_id: ProgramId(vec![]),
                         state_digest: StateDigest(vec![0, 1, 2, 3]),
                         contract_name: contract_name.clone(),
+                        timeout_window: None,
                     }
                     .as_blob("hyle".into(), None, None)],
                 )
@@ -72,7 +73,7 @@ async fn impl_test_mempool_isnt_blocked_by_proof_verification() -> Result<()> {
         )],
         certificate: AggregateSignature::default(),
         consensus_proposal: ConsensusProposal::default(),
-    }))?;
+    })))?;
 
     // Wait until we process the genesis block
     node_modules.wait_for_processed_genesis().await?;
@@ -0,0 +1,488 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Display,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use axum::http::StatusCode;
+use hyle_model::{ContractName, Identity, ProofTransaction};
+use tokio::{sync::Notify, task::JoinSet};
+use tracing::{debug, warn};
+
+use crate::{model::Transaction, utils::conf::MempoolConf};
+
+use super::{verifiers::find_verifier, InternalMempoolEvent, KnownContracts};
+
+/// Why the verification queue refused a proof
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueueFull {
+    /// Too many proofs are waiting for verification
+    Full,
+    /// Too many proofs of this contract are waiting for verification
+    Contract(ContractName),
+    /// Too many proofs sent from this address are waiting for verification
+    Sender(String),
+}
+
+impl QueueFull {
+    /// The node is overloaded as a whole, or a single contract or address sends too many proofs
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            QueueFull::Full => StatusCode::SERVICE_UNAVAILABLE,
+            QueueFull::Contract(_) | QueueFull::Sender(_) => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+
+    pub(super) fn reason(&self) -> &'static str {
+        match self {
+            QueueFull::Full => "full",
+            QueueFull::Contract(_) => "contract",
+            QueueFull::Sender(_) => "sender",
+        }
+    }
+}
+
+impl Display for QueueFull {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueueFull::Full => write!(f, "Too many proofs are waiting for verification"),
+            QueueFull::Contract(contract_name) => write!(
+                f,
+                "Too many proofs of contract {contract_name} are waiting for verification"
+            ),
+            QueueFull::Sender(sender) => write!(
+                f,
+                "Too many proofs sent from {sender} are waiting for verification"
+            ),
+        }
+    }
+}
+
+/// Proof transactions waiting for verification. It is shared between the REST API, which refuses
+/// proofs when it is full, and the mempool, which verifies them.
+///
+/// Proofs are limited per contract and per address they were sent from. They are served
+/// round-robin between contracts, and between the identities claimed by the proofs of each
+/// contract, so that a burst of proofs does not hold back the others. Claimed identities are not
+/// verified yet, so they only order the proofs and never get them refused.
+#[derive(Clone)]
+pub struct VerificationQueue {
+    pending: Arc<Mutex<PendingProofs>>,
+    notify: Arc<Notify>,
+    known_contracts: Arc<std::sync::RwLock<KnownContracts>>,
+    max_size: usize,
+    max_size_per_key: usize,
+}
+
+#[derive(Default)]
+struct PendingProofs {
+    /// Contracts with proofs ready for verification, in the order they are served
+    contracts: VecDeque<ContractName>,
+    by_contract: HashMap<ContractName, ContractProofs>,
+    /// Proofs accepted in the queue, including those whose claimed identity is being decoded
+    per_contract: HashMap<ContractName, usize>,
+    per_sender: HashMap<String, usize>,
+    len: usize,
+}
+
+#[derive(Default)]
+struct ContractProofs {
+    /// Identities with pending proofs, in the order they are served
+    identities: VecDeque<Identity>,
+    by_identity: HashMap<Identity, VecDeque<QueuedProof>>,
+}
+
+struct QueuedProof {
+    proof_tx: ProofTransaction,
+    sender: Option<String>,
+}
+
+impl VerificationQueue {
+    pub fn new(
+        conf: &MempoolConf,
+        known_contracts: Arc<std::sync::RwLock<KnownContracts>>,
+    ) -> Self {
+        VerificationQueue {
+            pending: Default::default(),
+            notify: Default::default(),
+            known_contracts,
+            max_size: conf.verification_queue_size,
+            max_size_per_key: conf.verification_queue_size_per_key,
+        }
+    }
+
+    /// Queues a proof for verification, unless too many proofs are waiting already.
+    /// `sender` is the address the proof was sent from, proofs without one are only limited
+    /// by their contract.
+    ///
+    /// The identity claimed by the proof is decoded in a blocking task, the proof can be taken
+    /// for verification once it is done.
+    pub fn push(
+        &self,
+        proof_tx: ProofTransaction,
+        sender: Option<String>,
+    ) -> Result<(), QueueFull> {
+        self.reserve(&proof_tx.contract_name, sender.as_deref())?;
+        let queue = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let identity = queue.claimed_identity(&proof_tx);
+            queue.insert(QueuedProof { proof_tx, sender }, identity);
+        });
+        Ok(())
+    }
+
+    /// Counts a proof in the queue limits
+    fn reserve(&self, contract_name: &ContractName, sender: Option<&str>) -> Result<(), QueueFull> {
+        #[allow(clippy::expect_used, reason = "not held across await")]
+        let mut pending = self.pending.lock().expect("logic error");
+        if pending.len >= self.max_size {
+            return Err(QueueFull::Full);
+        }
+        let queued = pending.per_contract.get(contract_name).copied();
+        if queued.unwrap_or(0) >= self.max_size_per_key {
+            return Err(QueueFull::Contract(contract_name.clone()));
+        }
+        if let Some(sender) = sender {
+            let queued = pending.per_sender.get(sender).copied();
+            if queued.unwrap_or(0) >= self.max_size_per_key {
+                return Err(QueueFull::Sender(sender.to_string()));
+            }
+            *pending.per_sender.entry(sender.to_string()).or_default() += 1;
+        }
+        *pending
+            .per_contract
+            .entry(contract_name.clone())
+            .or_default() += 1;
+        pending.len += 1;
+        Ok(())
+    }
+
+    /// Makes a reserved proof available for verification
+    fn insert(&self, queued: QueuedProof, identity: Identity) {
+        {
+            #[allow(clippy::expect_used, reason = "not held across await")]
+            let mut pending = self.pending.lock().expect("logic error");
+            let pending = &mut *pending;
+            let contract_name = queued.proof_tx.contract_name.clone();
+            if !pending.by_contract.contains_key(&contract_name) {
+                pending.contracts.push_back(contract_name.clone());
+            }
+            let contract = pending.by_contract.entry(contract_name).or_default();
+            if !contract.by_identity.contains_key(&identity) {
+                contract.identities.push_back(identity.clone());
+            }
+            contract
+                .by_identity
+                .entry(identity)
+                .or_default()
+                .push_back(queued);
+        }
+        self.notify.notify_one();
+    }
+
+    /// Takes the next proof to verify
+    pub fn pop(&self) -> Option<ProofTransaction> {
+        #[allow(clippy::expect_used, reason = "not held across await")]
+        let mut pending = self.pending.lock().expect("logic error");
+        let pending = &mut *pending;
+
+        let contract_name = pending.contracts.pop_front()?;
+        let contract = pending.by_contract.get_mut(&contract_name)?;
+        let identity = contract.identities.pop_front()?;
+        let proofs = contract.by_identity.get_mut(&identity)?;
+        let queued = proofs.pop_front()?;
+
+        if proofs.is_empty() {
+            contract.by_identity.remove(&identity);
+        } else {
+            contract.identities.push_back(identity);
+        }
+        if contract.identities.is_empty() {
+            pending.by_contract.remove(&contract_name);
+        } else {
+            pending.contracts.push_back(contract_name.clone());
+        }
+        release(&mut pending.per_contract, &contract_name);
+        if let Some(sender) = &queued.sender {
+            release(&mut pending.per_sender, sender);
+        }
+        pending.len -= 1;
+
+        Some(queued.proof_tx)
+    }
+
+    /// Number of proofs in the queue, including those whose claimed identity is being decoded
+    pub fn len(&self) -> usize {
+        self.pending.lock().map_or(0, |pending| pending.len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Resolves when proofs were queued since the last call
+    pub async fn notified(&self) {
+        self.notify.notified().await
+    }
+
+    fn claimed_identity(&self, proof_tx: &ProofTransaction) -> Identity {
+        let verifier = self
+            .known_contracts
+            .read()
+            .ok()
+            .and_then(|known_contracts| {
+                known_contracts
+                    .0
+                    .get(&proof_tx.contract_name)
+                    .map(|(verifier, _)| verifier.clone())
+            });
+        verifier
+            .and_then(|verifier| find_verifier(&verifier).ok())
+            .and_then(|verifier| {
+                verifier
+                    .claimed_identities(&proof_tx.proof)
+                    .into_iter()
+                    .next()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Forgets a proof counted under `key`
+fn release<K: std::hash::Hash + Eq>(counts: &mut HashMap<K, usize>, key: &K) {
+    if let Some(count) = counts.get_mut(key) {
+        *count -= 1;
+        if *count == 0 {
+            counts.remove(key);
+        }
+    }
+}
+
+/// Verifies the queued proofs, with at most `workers` blocking tasks at a time so that
+/// proofs do not starve the verification of data proposals.
+pub struct VerificationPool {
+    pub queue: VerificationQueue,
+    pub tasks: JoinSet<VerifiedProof>,
+    workers: usize,
+}
+
+pub struct VerifiedProof {
+    verifier: String,
+    elapsed: Duration,
+    result: Result<Transaction>,
+}
+
+impl VerificationPool {
+    pub fn new(
+        conf: &MempoolConf,
+        known_contracts: Arc<std::sync::RwLock<KnownContracts>>,
+    ) -> Self {
+        VerificationPool {
+            queue: VerificationQueue::new(conf, known_contracts),
+            tasks: JoinSet::new(),
+            workers: conf.verification_workers.max(1),
+        }
+    }
+}
+
+impl super::Mempool {
+    /// Queues a proof sent from `sender`, verifications start once its claimed identity is decoded
+    pub(super) fn queue_proof_tx(
+        &mut self,
+        proof_tx: ProofTransaction,
+        sender: Option<String>,
+    ) -> Result<(), QueueFull> {
+        let queued = self.verification.queue.push(proof_tx, sender);
+        if let Err(e) = &queued {
+            warn!("Refusing proof transaction: {}", e);
+            self.metrics.add_verification_refused(e.reason());
+        }
+        queued
+    }
+
+    /// Starts verifying queued proofs, as long as workers are available
+    pub(super) fn start_proof_verifications(&mut self) {
+        while self.verification.tasks.len() < self.verification.workers {
+            let Some(proof_tx) = self.verification.queue.pop() else {
+                break;
+            };
+            let kc = self.known_contracts.clone();
+            let verifier = kc
+                .read()
+                .ok()
+                .and_then(|known_contracts| {
+                    known_contracts
+                        .0
+                        .get(&proof_tx.contract_name)
+                        .map(|(verifier, _)| verifier.0.clone())
+                })
+                .unwrap_or_else(|| "unknown".to_string());
+            self.verification.tasks.spawn_blocking(move || {
+                let start = Instant::now();
+                let result = Self::process_proof_tx(kc, proof_tx.into())
+                    .context("Processing proof tx in blocker");
+                VerifiedProof {
+                    verifier,
+                    elapsed: start.elapsed(),
+                    result,
+                }
+            });
+        }
+        self.metrics.snapshot_verification_queue(
+            self.verification.queue.len(),
+            self.verification.tasks.len(),
+        );
+    }
+
+    pub(super) fn on_verified_proof(&mut self, verified: VerifiedProof) -> Result<()> {
+        debug!(
+            "Proof verified with {} in {:?}",
+            verified.verifier, verified.elapsed
+        );
+        self.metrics
+            .record_verification_latency(verified.verifier, verified.elapsed.as_secs_f64());
+        self.start_proof_verifications();
+        self.handle_internal_event(InternalMempoolEvent::OnProcessedNewTx(verified.result?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use hyle_model::{ContractName, HyleOutput, Identity, ProgramId, ProofData, ProofTransaction};
+
+    use super::{QueueFull, QueuedProof, VerificationQueue};
+    use crate::{mempool::KnownContracts, utils::conf::MempoolConf};
+
+    fn queue(max_size: usize, max_size_per_key: usize) -> VerificationQueue {
+        let known_contracts = KnownContracts(
+            ["c1", "c2", "c3"]
+                .into_iter()
+                .map(|name| (name.into(), ("test".into(), ProgramId::default())))
+                .collect(),
+        );
+        VerificationQueue::new(
+            &MempoolConf {
+                verification_workers: 1,
+                verification_queue_size: max_size,
+                verification_queue_size_per_key: max_size_per_key,
+                ..MempoolConf::default()
+            },
+            Arc::new(std::sync::RwLock::new(known_contracts)),
+        )
+    }
+
+    fn proof(contract_name: &str, identity: &str, nonce: u32) -> ProofTransaction {
+        let outputs = vec![HyleOutput {
+            identity: identity.into(),
+            index: hyle_model::BlobIndex(nonce as usize),
+            ..HyleOutput::default()
+        }];
+        ProofTransaction {
+            contract_name: contract_name.into(),
+            proof: ProofData(borsh::to_vec(&outputs).unwrap()),
+        }
+    }
+
+    fn popped(queue: &VerificationQueue) -> Vec<(ContractName, Identity)> {
+        std::iter::from_fn(|| queue.pop())
+            .map(|proof_tx| {
+                let outputs: Vec<HyleOutput> = borsh::from_slice(&proof_tx.proof.0).unwrap();
+                let identity = outputs
+                    .into_iter()
+                    .next()
+                    .map(|output| output.identity)
+                    .unwrap_or_default();
+                (proof_tx.contract_name, identity)
+            })
+            .collect()
+    }
+
+    /// Queues a proof like [`VerificationQueue::push`], decoding its claimed identity right away
+    fn push(
+        queue: &VerificationQueue,
+        proof_tx: ProofTransaction,
+        sender: Option<&str>,
+    ) -> Result<(), QueueFull> {
+        queue.reserve(&proof_tx.contract_name, sender)?;
+        let identity = queue.claimed_identity(&proof_tx);
+        let sender = sender.map(str::to_string);
+        queue.insert(QueuedProof { proof_tx, sender }, identity);
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_robin_between_contracts_and_identities() {
+        let queue = queue(100, 100);
+        for nonce in 0..3 {
+            push(&queue, proof("c1", "alice.c1", nonce), None).unwrap();
+        }
+        push(&queue, proof("c1", "bob.c1", 0), None).unwrap();
+        push(&queue, proof("c2", "carol.c2", 0), None).unwrap();
+        push(&queue, proof("c2", "carol.c2", 1), None).unwrap();
+        assert_eq!(queue.len(), 6);
+
+        let expected: Vec<(ContractName, Identity)> = [
+            ("c1", "alice.c1"),
+            ("c2", "carol.c2"),
+            ("c1", "bob.c1"),
+            ("c2", "carol.c2"),
+            ("c1", "alice.c1"),
+            ("c1", "alice.c1"),
+        ]
+        .into_iter()
+        .map(|(c, i)| (c.into(), i.into()))
+        .collect();
+        assert_eq!(popped(&queue), expected);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_queue_limits() {
+        let queue = queue(5, 2);
+        push(&queue, proof("c1", "alice.c1", 0), Some("1.1.1.1")).unwrap();
+        push(&queue, proof("c1", "bob.c1", 0), None).unwrap();
+        assert_eq!(
+            push(&queue, proof("c1", "carol.c1", 0), None),
+            Err(QueueFull::Contract("c1".into()))
+        );
+        push(&queue, proof("c2", "alice.c1", 1), Some("1.1.1.1")).unwrap();
+        assert_eq!(
+            push(&queue, proof("c3", "bob.c3", 0), Some("1.1.1.1")),
+            Err(QueueFull::Sender("1.1.1.1".into()))
+        );
+        // Claimed identities are not verified, they never get proofs refused
+        push(&queue, proof("c3", "alice.c1", 2), Some("2.2.2.2")).unwrap();
+        push(&queue, proof("c3", "carol.c3", 0), None).unwrap();
+        assert_eq!(
+            push(&queue, proof("c2", "dave.c2", 0), None),
+            Err(QueueFull::Full)
+        );
+        assert_eq!(QueueFull::Full.status_code(), 503);
+        assert_eq!(QueueFull::Contract("c1".into()).status_code(), 429);
+        assert_eq!(QueueFull::Sender("1.1.1.1".into()).status_code(), 429);
+
+        // Room is made as proofs are taken for verification
+        assert!(queue.pop().is_some());
+        push(&queue, proof("c2", "dave.c2", 0), Some("1.1.1.1")).unwrap();
+        assert_eq!(queue.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_push_decodes_identity_in_background() {
+        let queue = queue(5, 2);
+        queue.push(proof("c1", "alice.c1", 0), None).unwrap();
+        // Counted in the limits right away, verified once the identity is decoded
+        assert_eq!(queue.len(), 1);
+        tokio::time::timeout(std::time::Duration::from_secs(5), queue.notified())
+            .await
+            .unwrap();
+        assert_eq!(
+            popped(&queue),
+            vec![(ContractName::from("c1"), Identity::from("alice.c1"))]
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use hyle_model::{Identity, ProofData, Signed, ValidatorSignature};
 use sha3::Digest;
 
@@ -7,51 +7,119 @@ use hyle_contract_sdk::{Blob, BlobIndex, HyleOutput, ProgramId, StateDigest, TxH
 use hyle_verifiers::{noir_proof_verifier, risc0_proof_verifier, validate_risc0_program_id};
 
 use crate::{
-    model::verifiers::{BlstSignatureBlob, NativeVerifiers, ShaBlob},
+    model::verifiers::{
+        BlstSignatureBlob, Ed25519SignatureBlob, NativeVerifiers, Secp256k1SignatureBlob, ShaBlob,
+    },
     utils::crypto::BlstCrypto,
 };
 
+/// A proof system contracts can be registered with, picked by the `verifier` of the contract.
+/// Proof systems linked into the node are added with [`register_proof_verifier!`](crate::register_proof_verifier).
+/// The mempool refuses contract registrations with a verifier that is not registered,
+/// settlement doesn't depend on the verifiers the node was built with.
+pub trait ProofVerifier: Sync {
+    /// Name of the verifier, as set on contracts
+    fn name(&self) -> &'static str;
+
+    /// Verifies a proof of `program_id` and returns the outputs it commits to
+    fn verify(&self, proof: &ProofData, program_id: &ProgramId) -> Result<Vec<HyleOutput>>;
+
+    /// Verifies a proof aggregating proofs of other programs, and returns the program id of each output
+    fn verify_recursive(
+        &self,
+        _proof: &ProofData,
+        _program_id: &ProgramId,
+    ) -> Result<(Vec<ProgramId>, Vec<HyleOutput>)> {
+        bail!("{} recursive verifier not implemented yet", self.name())
+    }
+
+    /// Checks that a contract can be registered with this program id
+    fn validate_program_id(&self, _program_id: &ProgramId) -> Result<()> {
+        Ok(())
+    }
+
+    /// Whether proofs of `program_id` aggregate proofs of other programs, and are verified with
+    /// [`verify_recursive`](ProofVerifier::verify_recursive)
+    fn is_recursive(&self, _program_id: &ProgramId) -> bool {
+        false
+    }
+
+    /// Rough estimate of the time it takes to verify a proof, in milliseconds
+    fn cost_hint(&self, _proof: &ProofData) -> u64 {
+        1
+    }
+
+    /// Identities claimed by the outputs of a proof, read without verifying it.
+    /// They can be forged, so they are only used to share verification between identities.
+    fn claimed_identities(&self, _proof: &ProofData) -> Vec<Identity> {
+        vec![]
+    }
+}
+
+pub struct ProofVerifierKind(pub &'static dyn ProofVerifier);
+
+inventory::collect!(ProofVerifierKind);
+
+/// Registers a [`ProofVerifier`], so that contracts can be registered with its name.
+///
+/// ```ignore
+/// hyle::register_proof_verifier!(MyVerifier);
+/// ```
+#[macro_export]
+macro_rules! register_proof_verifier {
+    ($verifier:expr) => {
+        $crate::inventory::submit! {
+            $crate::mempool::verifiers::ProofVerifierKind(&$verifier)
+        }
+    };
+}
+
+#[cfg(any(test, feature = "test_verifier"))]
+register_proof_verifier!(TestVerifier);
+#[cfg(test)]
+register_proof_verifier!(TestSlowVerifier);
+register_proof_verifier!(Risc0Verifier);
+register_proof_verifier!(NoirVerifier);
+#[cfg(feature = "sp1")]
+register_proof_verifier!(Sp1Verifier);
+
+/// Looks up a registered verifier by name
+pub fn find_verifier(verifier: &Verifier) -> Result<&'static dyn ProofVerifier> {
+    let mut kinds =
+        inventory::iter::<ProofVerifierKind>().filter(|kind| kind.0.name() == verifier.0);
+    let Some(kind) = kinds.next() else {
+        bail!(
+            "Unknown verifier '{verifier}', registered verifiers are: {}",
+            registered_verifiers()?.join(", ")
+        );
+    };
+    if kinds.next().is_some() {
+        bail!("Verifier '{verifier}' is registered more than once");
+    }
+    Ok(kind.0)
+}
+
+/// Names of the registered verifiers, checked for duplicates
+pub fn registered_verifiers() -> Result<Vec<&'static str>> {
+    let mut names: Vec<&str> = inventory::iter::<ProofVerifierKind>()
+        .map(|kind| kind.0.name())
+        .collect();
+    names.sort();
+    if let Some(name) = names.windows(2).find_map(|w| match w {
+        [a, b] if a == b => Some(a),
+        _ => None,
+    }) {
+        bail!("Verifier '{name}' is registered more than once");
+    }
+    Ok(names)
+}
+
 pub fn verify_proof(
     proof: &ProofData,
     verifier: &Verifier,
-    #[allow(unused_variables)] program_id: &ProgramId,
+    program_id: &ProgramId,
 ) -> Result<Vec<HyleOutput>> {
-    let hyle_outputs = match verifier.0.as_str() {
-        // TODO: add #[cfg(test)]
-        "test" => borsh::from_slice::<Vec<HyleOutput>>(&proof.0).context("parsing test proof"),
-        #[cfg(test)]
-        "test-slow" => {
-            tracing::info!("Sleeping for 2 seconds to simulate a slow verifier");
-            std::thread::sleep(std::time::Duration::from_secs(2));
-            tracing::info!("Woke up from sleep");
-            Ok(serde_json::from_slice(&proof.0)?)
-        }
-        hyle_verifiers::versions::RISC0_1 => {
-            let journal = risc0_proof_verifier(&proof.0, &program_id.0)?;
-            // First try to decode it as a single HyleOutput
-            Ok(match journal.decode::<HyleOutput>() {
-                Ok(ho) => vec![ho],
-                Err(_) => {
-                    let hyle_output = journal
-                        .decode::<Vec<Vec<u8>>>()
-                        .context("Failed to extract HyleOuput from Risc0's journal")?;
-
-                    // Doesn't actually work to just deserialize in one go.
-                    hyle_output
-                        .iter()
-                        .map(|o| hyle_verifiers::risc0::from_slice::<HyleOutput, _>(o))
-                        .collect::<Result<Vec<_>, _>>()
-                        .context("Failed to decode HyleOutput")?
-                }
-            })
-        }
-        hyle_verifiers::versions::NOIR => noir_proof_verifier(&proof.0, &program_id.0),
-        #[cfg(feature = "sp1")]
-        hyle_verifiers::versions::SP1_4 => {
-            hyle_verifiers::sp1_proof_verifier(&proof.0, &program_id.0)
-        }
-        _ => Err(anyhow::anyhow!("{} verifier not implemented yet", verifier)),
-    }?;
+    let hyle_outputs = find_verifier(verifier)?.verify(proof, program_id)?;
     hyle_outputs.iter().for_each(|hyle_output| {
         tracing::debug!(
             "🔎 {}",
@@ -69,30 +137,7 @@ pub fn verify_recursive_proof(
     verifier: &Verifier,
     program_id: &ProgramId,
 ) -> Result<(Vec<ProgramId>, Vec<HyleOutput>)> {
-    use risc0_recursion::{Risc0Journal, Risc0ProgramId};
-
-    let outputs = match verifier.0.as_str() {
-        hyle_verifiers::versions::RISC0_1 => {
-            let journal = risc0_proof_verifier(&proof.0, &program_id.0)?;
-            let mut output = journal
-                .decode::<Vec<(Risc0ProgramId, Risc0Journal)>>()
-                .context("Failed to extract HyleOuput from Risc0's journal")?;
-
-            // Doesn't actually work to just deserialize in one go.
-            output
-                .drain(..)
-                .map(|o| {
-                    hyle_verifiers::risc0::from_slice::<HyleOutput, _>(&o.1)
-                        .map(|h| (ProgramId(o.0.to_vec()), h))
-                })
-                .collect::<Result<(Vec<_>, Vec<_>), _>>()
-                .context("Failed to decode HyleOutput")
-        }
-        _ => Err(anyhow::anyhow!(
-            "{} recursive verifier not implemented yet",
-            verifier
-        )),
-    }?;
+    let outputs = find_verifier(verifier)?.verify_recursive(proof, program_id)?;
     outputs.1.iter().for_each(|hyle_output| {
         tracing::debug!(
             "🔎 {}",
@@ -105,6 +150,167 @@ pub fn verify_recursive_proof(
     Ok(outputs)
 }
 
+/// Accepts the borsh-encoded outputs as proof, only for tests and load tests
+#[cfg(any(test, feature = "test_verifier"))]
+struct TestVerifier;
+
+#[cfg(any(test, feature = "test_verifier"))]
+impl ProofVerifier for TestVerifier {
+    fn name(&self) -> &'static str {
+        "test"
+    }
+
+    fn verify(&self, proof: &ProofData, _program_id: &ProgramId) -> Result<Vec<HyleOutput>> {
+        borsh::from_slice::<Vec<HyleOutput>>(&proof.0).context("parsing test proof")
+    }
+
+    fn claimed_identities(&self, proof: &ProofData) -> Vec<Identity> {
+        self.verify(proof, &ProgramId::default())
+            .map(|outputs| outputs.into_iter().map(|o| o.identity).collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+struct TestSlowVerifier;
+
+#[cfg(test)]
+impl ProofVerifier for TestSlowVerifier {
+    fn name(&self) -> &'static str {
+        "test-slow"
+    }
+
+    fn verify(&self, proof: &ProofData, _program_id: &ProgramId) -> Result<Vec<HyleOutput>> {
+        tracing::info!("Sleeping for 2 seconds to simulate a slow verifier");
+        std::thread::sleep(std::time::Duration::from_secs(2));
+        tracing::info!("Woke up from sleep");
+        Ok(serde_json::from_slice(&proof.0)?)
+    }
+
+    fn cost_hint(&self, _proof: &ProofData) -> u64 {
+        2000
+    }
+
+    fn claimed_identities(&self, proof: &ProofData) -> Vec<Identity> {
+        serde_json::from_slice::<Vec<HyleOutput>>(&proof.0)
+            .map(|outputs| outputs.into_iter().map(|o| o.identity).collect())
+            .unwrap_or_default()
+    }
+}
+
+struct Risc0Verifier;
+
+impl ProofVerifier for Risc0Verifier {
+    fn name(&self) -> &'static str {
+        hyle_verifiers::versions::RISC0_1
+    }
+
+    fn verify(&self, proof: &ProofData, program_id: &ProgramId) -> Result<Vec<HyleOutput>> {
+        let journal = risc0_proof_verifier(&proof.0, &program_id.0)?;
+        decode_risc0_outputs(&journal)
+    }
+
+    fn verify_recursive(
+        &self,
+        proof: &ProofData,
+        program_id: &ProgramId,
+    ) -> Result<(Vec<ProgramId>, Vec<HyleOutput>)> {
+        use risc0_recursion::{Risc0Journal, Risc0ProgramId};
+
+        let journal = risc0_proof_verifier(&proof.0, &program_id.0)?;
+        let mut output = journal
+            .decode::<Vec<(Risc0ProgramId, Risc0Journal)>>()
+            .context("Failed to extract HyleOuput from Risc0's journal")?;
+
+        // Doesn't actually work to just deserialize in one go.
+        output
+            .drain(..)
+            .map(|o| {
+                hyle_verifiers::risc0::from_slice::<HyleOutput, _>(&o.1)
+                    .map(|h| (ProgramId(o.0.to_vec()), h))
+            })
+            .collect::<Result<(Vec<_>, Vec<_>), _>>()
+            .context("Failed to decode HyleOutput")
+    }
+
+    fn validate_program_id(&self, program_id: &ProgramId) -> Result<()> {
+        validate_risc0_program_id(program_id)
+    }
+
+    fn is_recursive(&self, program_id: &ProgramId) -> bool {
+        program_id.0 == hyle_contracts::RISC0_RECURSION_ID
+    }
+
+    fn cost_hint(&self, _proof: &ProofData) -> u64 {
+        100
+    }
+
+    fn claimed_identities(&self, proof: &ProofData) -> Vec<Identity> {
+        hyle_verifiers::risc0_unverified_journal(&proof.0)
+            .and_then(|journal| decode_risc0_outputs(&journal))
+            .map(|outputs| outputs.into_iter().map(|o| o.identity).collect())
+            .unwrap_or_default()
+    }
+}
+
+fn decode_risc0_outputs(journal: &hyle_verifiers::risc0::Journal) -> Result<Vec<HyleOutput>> {
+    // First try to decode it as a single HyleOutput
+    Ok(match journal.decode::<HyleOutput>() {
+        Ok(ho) => vec![ho],
+        Err(_) => {
+            let hyle_output = journal
+                .decode::<Vec<Vec<u8>>>()
+                .context("Failed to extract HyleOuput from Risc0's journal")?;
+
+            // Doesn't actually work to just deserialize in one go.
+            hyle_output
+                .iter()
+                .map(|o| hyle_verifiers::risc0::from_slice::<HyleOutput, _>(o))
+                .collect::<Result<Vec<_>, _>>()
+                .context("Failed to decode HyleOutput")?
+        }
+    })
+}
+
+struct NoirVerifier;
+
+impl ProofVerifier for NoirVerifier {
+    fn name(&self) -> &'static str {
+        hyle_verifiers::versions::NOIR
+    }
+
+    fn verify(&self, proof: &ProofData, program_id: &ProgramId) -> Result<Vec<HyleOutput>> {
+        noir_proof_verifier(&proof.0, &program_id.0)
+    }
+
+    fn cost_hint(&self, _proof: &ProofData) -> u64 {
+        // Verified by running bb
+        500
+    }
+}
+
+#[cfg(feature = "sp1")]
+struct Sp1Verifier;
+
+#[cfg(feature = "sp1")]
+impl ProofVerifier for Sp1Verifier {
+    fn name(&self) -> &'static str {
+        hyle_verifiers::versions::SP1_4
+    }
+
+    fn verify(&self, proof: &ProofData, program_id: &ProgramId) -> Result<Vec<HyleOutput>> {
+        hyle_verifiers::sp1_proof_verifier(&proof.0, &program_id.0)
+    }
+
+    fn validate_program_id(&self, program_id: &ProgramId) -> Result<()> {
+        hyle_verifiers::validate_sp1_program_id(program_id)
+    }
+
+    fn cost_hint(&self, _proof: &ProofData) -> u64 {
+        1000
+    }
+}
+
 pub fn verify_native(
     tx_hash: TxHash,
     index: BlobIndex,
@@ -172,14 +378,104 @@ pub fn verify_native_impl(
 
             Ok((blob.identity, res == blob.sha))
         }
+        NativeVerifiers::Ed25519 => {
+            let blob = borsh::from_slice::<Ed25519SignatureBlob>(&blob.data.0)?;
+
+            let msg = [blob.data, blob.identity.0.as_bytes().to_vec()].concat();
+            let public_key: &[u8; 32] = blob
+                .public_key
+                .as_slice()
+                .try_into()
+                .context("Ed25519 public key must be 32 bytes")?;
+            let public_key = ed25519_dalek::VerifyingKey::from_bytes(public_key)?;
+            let signature = ed25519_dalek::Signature::from_slice(&blob.signature)?;
+
+            Ok((
+                blob.identity,
+                public_key.verify_strict(&msg, &signature).is_ok(),
+            ))
+        }
+        NativeVerifiers::Secp256k1 => {
+            use k256::ecdsa::signature::Verifier;
+
+            let blob = borsh::from_slice::<Secp256k1SignatureBlob>(&blob.data.0)?;
+
+            let msg = [blob.data, blob.identity.0.as_bytes().to_vec()].concat();
+            let public_key = k256::ecdsa::VerifyingKey::from_sec1_bytes(&blob.public_key)?;
+            let signature = k256::ecdsa::Signature::from_slice(&blob.signature)?;
+            // Only accept the normalized signature, so that signatures can't be malleated
+            if signature.normalize_s().is_some() {
+                bail!("Secp256k1 signature must be low-s normalized");
+            }
+
+            Ok((blob.identity, public_key.verify(&msg, &signature).is_ok()))
+        }
+    }
+}
+
+/// Checks that proofs of contracts registered with this verifier and program id can be verified
+/// by this node.
+/// Only run when transactions are admitted in the mempool, settlement doesn't depend on the
+/// verifiers linked into the node.
+pub fn validate_verifier(verifier: &Verifier, program_id: &ProgramId) -> Result<()> {
+    // Native verifiers are run by the node when blobs are sequenced, they have no proofs
+    if NativeVerifiers::try_from(verifier).is_ok() {
+        return Ok(());
     }
+    find_verifier(verifier)?.validate_program_id(program_id)
+}
+
+/// Whether proofs of this program aggregate proofs of other programs
+pub fn is_recursive(verifier: &Verifier, program_id: &ProgramId) -> bool {
+    find_verifier(verifier)
+        .map(|verifier| verifier.is_recursive(program_id))
+        .unwrap_or(false)
 }
 
-pub fn validate_program_id(verifier: &Verifier, program_id: &ProgramId) -> Result<()> {
-    match verifier.0.as_str() {
-        hyle_verifiers::versions::RISC0_1 => validate_risc0_program_id(program_id),
-        #[cfg(feature = "sp1")]
-        hyle_verifiers::versions::SP1_4 => hyle_verifiers::validate_sp1_program_id(program_id),
-        _ => Ok(()),
+#[cfg(test)]
+mod tests {
+    use hyle_model::{ProgramId, Verifier};
+
+    use super::{find_verifier, is_recursive, registered_verifiers, validate_verifier};
+
+    #[test]
+    fn test_registered_verifiers() {
+        let names = registered_verifiers().unwrap();
+        for name in [
+            "test",
+            "test-slow",
+            hyle_verifiers::versions::RISC0_1,
+            hyle_verifiers::versions::NOIR,
+        ] {
+            assert!(names.contains(&name), "{name} is not registered");
+            assert_eq!(find_verifier(&name.into()).unwrap().name(), name);
+        }
+    }
+
+    #[test]
+    fn test_validate_verifier() {
+        let risc0: Verifier = hyle_verifiers::versions::RISC0_1.into();
+        assert!(validate_verifier(&risc0, &ProgramId(vec![0; 32])).is_ok());
+        assert!(validate_verifier(&risc0, &ProgramId(vec![0; 31])).is_err());
+        assert!(validate_verifier(&"test".into(), &ProgramId(vec![])).is_ok());
+        // Native verifiers have no proof system, but contracts can be registered with them
+        assert!(validate_verifier(&"blst".into(), &ProgramId(vec![])).is_ok());
+
+        let err = validate_verifier(&"risc0".into(), &ProgramId(vec![])).unwrap_err();
+        assert!(err.to_string().contains("Unknown verifier 'risc0'"));
+    }
+
+    #[test]
+    fn test_is_recursive() {
+        let risc0: Verifier = hyle_verifiers::versions::RISC0_1.into();
+        assert!(is_recursive(
+            &risc0,
+            &ProgramId(hyle_contracts::RISC0_RECURSION_ID.to_vec())
+        ));
+        assert!(!is_recursive(
+            &risc0,
+            &ProgramId(hyle_contracts::HYLLAR_ID.to_vec())
+        ));
+        assert!(!is_recursive(&"test".into(), &ProgramId(vec![])));
     }
 }
@@ -15,7 +15,7 @@ use crate::{
 use super::KnownContracts;
 use super::{
     storage::{CanBePutOnTop, Storage},
-    verifiers::{verify_proof, verify_recursive_proof},
+    verifiers::{is_recursive, verify_proof, verify_recursive_proof},
 };
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -211,8 +211,7 @@ impl super::Mempool {
                             }
                         }
                     };
-                    // TODO: figure out how to generalize this
-                    let is_recursive = proof_tx.contract_name.0 == "risc0-recursion";
+                    let is_recursive = is_recursive(&verifier, &program_id);
 
                     if is_recursive {
                         match verify_recursive_proof(proof, &verifier, &program_id) {
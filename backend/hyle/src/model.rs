@@ -10,6 +10,7 @@ pub use hyle_model::*;
 
 pub mod contract_registration;
 mod indexer;
+pub mod migration;
 pub mod verifiers;
 
 pub use indexer::*;
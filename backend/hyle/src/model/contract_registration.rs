@@ -1,7 +1,7 @@
 use anyhow::{bail, Result};
 use hyle_model::{ContractName, ProgramId, StateDigest, Verifier};
 
-use crate::mempool::verifiers::validate_program_id;
+use hyle_verifiers::validate_risc0_program_id;
 
 /// Check that the new contract name is:
 /// - a valid subdomain of the owner contract name.
@@ -58,6 +58,16 @@ pub fn validate_state_digest_size(state_digest: &StateDigest) -> Result<()> {
     Ok(())
 }
 
+/// Check the program id of the verifiers known to the protocol.
+/// This is run at settlement, so it must not depend on the verifiers linked into the node: the
+/// mempool checks the other ones when transactions are admitted.
+pub fn validate_program_id(verifier: &Verifier, program_id: &ProgramId) -> Result<()> {
+    match verifier.0.as_str() {
+        hyle_verifiers::versions::RISC0_1 => validate_risc0_program_id(program_id),
+        _ => Ok(()),
+    }
+}
+
 pub fn validate_contract_registration_metadata(
     owner: &ContractName,
     new_contract_name: &ContractName,
@@ -71,13 +81,36 @@ pub fn validate_contract_registration_metadata(
     Ok(())
 }
 
+pub fn validate_contract_program_update(
+    owner: &ContractName,
+    contract_name: &ContractName,
+    verifier: &Verifier,
+    program_id: &ProgramId,
+) -> Result<()> {
+    validate_contract_name_registration(owner, contract_name)?;
+    validate_program_id(verifier, program_id)?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
-    use hyle_model::StateDigest;
+    use hyle_model::{ProgramId, StateDigest, Verifier};
 
     use crate::model::contract_registration::validate_state_digest_size;
 
-    use super::validate_contract_name_registration;
+    use super::{validate_contract_name_registration, validate_program_id};
+
+    #[test]
+    fn test_validate_program_id() {
+        let risc0: Verifier = hyle_verifiers::versions::RISC0_1.into();
+        assert!(validate_program_id(&risc0, &ProgramId(vec![0; 32])).is_ok());
+        assert!(validate_program_id(&risc0, &ProgramId(vec![0; 31])).is_err());
+
+        // Settlement doesn't depend on the verifiers linked into the node
+        assert!(validate_program_id(&"test".into(), &ProgramId(vec![])).is_ok());
+        assert!(validate_program_id(&"sp1-4".into(), &ProgramId(vec![])).is_ok());
+        assert!(validate_program_id(&"risc0".into(), &ProgramId(vec![])).is_ok());
+    }
 
     #[test]
     fn test_validate_contract_registration_valid_subdomain() {
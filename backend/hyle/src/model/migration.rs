@@ -0,0 +1,329 @@
+//! Layouts of the data written before proposals carried a seed signature and contracts declared
+//! a timeout window.
+//!
+//! Stored blocks and lane entries have no version header: the `V0` structs below mirror their
+//! layout, and are only tried when the current layout fails to decode.
+//! The hashes of the converted data are unchanged, as the new fields are empty.
+
+use borsh::BorshDeserialize;
+use hyle_model::{
+    AggregateSignature, BlobIndex, BlobProofOutput, BlobTransaction, ConsensusProposal,
+    ConsensusProposalHash, ConsensusStakingAction, ContractName, Cut, DataProposal,
+    DataProposalHash, HyleOutput, Identity, LaneId, OnchainEffect, ProgramId, ProofData,
+    ProofDataHash, ProofTransaction, RegisterContractEffect, Signature, SignedBlock, Slot,
+    StateDigest, Transaction, TransactionData, TxContext, TxHash, ValidatorPublicKey,
+    VerifiedProofTransaction, Verifier, View,
+};
+
+#[derive(BorshDeserialize)]
+pub struct SignedBlockV0 {
+    data_proposals: Vec<(LaneId, Vec<DataProposalV0>)>,
+    certificate: AggregateSignature,
+    consensus_proposal: ConsensusProposalV0,
+}
+
+#[derive(BorshDeserialize)]
+struct ConsensusProposalV0 {
+    slot: Slot,
+    view: View,
+    round_leader: ValidatorPublicKey,
+    cut: Cut,
+    staking_actions: Vec<ConsensusStakingAction>,
+    timestamp: u64,
+    parent_hash: ConsensusProposalHash,
+}
+
+#[derive(BorshDeserialize)]
+pub struct DataProposalV0 {
+    parent_data_proposal_hash: Option<DataProposalHash>,
+    txs: Vec<TransactionV0>,
+}
+
+#[derive(BorshDeserialize)]
+struct TransactionV0 {
+    version: u32,
+    transaction_data: TransactionDataV0,
+}
+
+#[derive(BorshDeserialize)]
+enum TransactionDataV0 {
+    Blob(BlobTransaction),
+    Proof(ProofTransaction),
+    VerifiedProof(VerifiedProofTransactionV0),
+}
+
+#[derive(BorshDeserialize)]
+struct VerifiedProofTransactionV0 {
+    contract_name: ContractName,
+    proof: Option<ProofData>,
+    proof_hash: ProofDataHash,
+    proof_size: usize,
+    proven_blobs: Vec<BlobProofOutputV0>,
+    is_recursive: bool,
+}
+
+#[derive(BorshDeserialize)]
+struct BlobProofOutputV0 {
+    blob_tx_hash: TxHash,
+    original_proof_hash: ProofDataHash,
+    hyle_output: HyleOutputV0,
+    program_id: ProgramId,
+}
+
+#[derive(BorshDeserialize)]
+pub struct HyleOutputV0 {
+    version: u32,
+    initial_state: StateDigest,
+    next_state: StateDigest,
+    identity: Identity,
+    index: BlobIndex,
+    blobs: Vec<u8>,
+    tx_hash: TxHash,
+    success: bool,
+    tx_ctx: Option<TxContext>,
+    onchain_effects: Vec<OnchainEffectV0>,
+    program_outputs: Vec<u8>,
+}
+
+#[derive(BorshDeserialize)]
+enum OnchainEffectV0 {
+    RegisterContract(RegisterContractEffectV0),
+    DeleteContract(ContractName),
+}
+
+#[derive(BorshDeserialize)]
+struct RegisterContractEffectV0 {
+    verifier: Verifier,
+    program_id: ProgramId,
+    state_digest: StateDigest,
+    contract_name: ContractName,
+}
+
+/// Blocks proposed before the seed signature have none, the leader seed is not updated by them.
+impl From<SignedBlockV0> for SignedBlock {
+    fn from(block: SignedBlockV0) -> Self {
+        let proposal = block.consensus_proposal;
+        SignedBlock {
+            data_proposals: block
+                .data_proposals
+                .into_iter()
+                .map(|(lane_id, dps)| (lane_id, dps.into_iter().map(Into::into).collect()))
+                .collect(),
+            certificate: block.certificate,
+            consensus_proposal: ConsensusProposal {
+                slot: proposal.slot,
+                view: proposal.view,
+                round_leader: proposal.round_leader,
+                cut: proposal.cut,
+                staking_actions: proposal.staking_actions,
+                timestamp: proposal.timestamp,
+                parent_hash: proposal.parent_hash,
+                seed_signature: Signature::default(),
+            },
+        }
+    }
+}
+
+impl From<DataProposalV0> for DataProposal {
+    fn from(dp: DataProposalV0) -> Self {
+        DataProposal::new(
+            dp.parent_data_proposal_hash,
+            dp.txs.into_iter().map(Into::into).collect(),
+        )
+    }
+}
+
+impl From<TransactionV0> for Transaction {
+    fn from(tx: TransactionV0) -> Self {
+        Transaction {
+            version: tx.version,
+            transaction_data: match tx.transaction_data {
+                TransactionDataV0::Blob(tx) => TransactionData::Blob(tx),
+                TransactionDataV0::Proof(tx) => TransactionData::Proof(tx),
+                TransactionDataV0::VerifiedProof(tx) => {
+                    TransactionData::VerifiedProof(VerifiedProofTransaction {
+                        contract_name: tx.contract_name,
+                        proof: tx.proof,
+                        proof_hash: tx.proof_hash,
+                        proof_size: tx.proof_size,
+                        proven_blobs: tx
+                            .proven_blobs
+                            .into_iter()
+                            .map(|output| BlobProofOutput {
+                                blob_tx_hash: output.blob_tx_hash,
+                                original_proof_hash: output.original_proof_hash,
+                                hyle_output: output.hyle_output.into(),
+                                program_id: output.program_id,
+                            })
+                            .collect(),
+                        is_recursive: tx.is_recursive,
+                    })
+                }
+            },
+        }
+    }
+}
+
+/// Contracts registered before the timeout window use the protocol default.
+impl From<HyleOutputV0> for HyleOutput {
+    fn from(output: HyleOutputV0) -> Self {
+        HyleOutput {
+            version: output.version,
+            initial_state: output.initial_state,
+            next_state: output.next_state,
+            identity: output.identity,
+            index: output.index,
+            blobs: output.blobs,
+            tx_hash: output.tx_hash,
+            success: output.success,
+            tx_ctx: output.tx_ctx,
+            onchain_effects: output
+                .onchain_effects
+                .into_iter()
+                .map(|effect| match effect {
+                    OnchainEffectV0::RegisterContract(effect) => {
+                        OnchainEffect::RegisterContract(RegisterContractEffect {
+                            verifier: effect.verifier,
+                            program_id: effect.program_id,
+                            state_digest: effect.state_digest,
+                            contract_name: effect.contract_name,
+                            timeout_window: None,
+                        })
+                    }
+                    OnchainEffectV0::DeleteContract(name) => OnchainEffect::DeleteContract(name),
+                })
+                .collect(),
+            program_outputs: output.program_outputs,
+        }
+    }
+}
+
+/// Decodes the current layout, falling back to the layout written before this migration.
+pub fn decode_signed_block(bytes: &[u8]) -> std::io::Result<SignedBlock> {
+    borsh::from_slice(bytes).or_else(|err| {
+        borsh::from_slice::<SignedBlockV0>(bytes)
+            .map(Into::into)
+            .map_err(|_| err)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::indexing_slicing)]
+
+    use borsh::BorshSerialize;
+    use hyle_model::{Blob, Hashed, LaneBytesSize};
+
+    use super::*;
+
+    #[derive(BorshSerialize)]
+    struct LegacyRegisterContractEffect {
+        verifier: Verifier,
+        program_id: ProgramId,
+        state_digest: StateDigest,
+        contract_name: ContractName,
+    }
+
+    fn legacy_hyle_output(bytes: &mut Vec<u8>) {
+        1u32.serialize(bytes).unwrap();
+        StateDigest(vec![1]).serialize(bytes).unwrap();
+        StateDigest(vec![2]).serialize(bytes).unwrap();
+        Identity::new("id").serialize(bytes).unwrap();
+        BlobIndex(0).serialize(bytes).unwrap();
+        Vec::<u8>::new().serialize(bytes).unwrap();
+        TxHash::new("blob_tx").serialize(bytes).unwrap();
+        true.serialize(bytes).unwrap();
+        Option::<TxContext>::None.serialize(bytes).unwrap();
+        // One RegisterContract effect
+        1u32.serialize(bytes).unwrap();
+        0u8.serialize(bytes).unwrap();
+        LegacyRegisterContractEffect {
+            verifier: Verifier("test".into()),
+            program_id: ProgramId(vec![3]),
+            state_digest: StateDigest(vec![4]),
+            contract_name: "c1".into(),
+        }
+        .serialize(bytes)
+        .unwrap();
+        Vec::<u8>::new().serialize(bytes).unwrap();
+    }
+
+    #[test]
+    fn test_decode_legacy_signed_block() {
+        let blob_tx = BlobTransaction::new("id", vec![Blob::default()]);
+
+        // Layout of the blocks stored before this migration, with a blob and a verified proof
+        let mut bytes = vec![];
+        1u32.serialize(&mut bytes).unwrap();
+        LaneId::default().serialize(&mut bytes).unwrap();
+        1u32.serialize(&mut bytes).unwrap();
+        Option::<DataProposalHash>::None
+            .serialize(&mut bytes)
+            .unwrap();
+        2u32.serialize(&mut bytes).unwrap();
+        Transaction::from(blob_tx.clone())
+            .serialize(&mut bytes)
+            .unwrap();
+        0u32.serialize(&mut bytes).unwrap();
+        2u8.serialize(&mut bytes).unwrap();
+        ContractName::from("c1").serialize(&mut bytes).unwrap();
+        Option::<ProofData>::None.serialize(&mut bytes).unwrap();
+        ProofDataHash("proof".into()).serialize(&mut bytes).unwrap();
+        10usize.serialize(&mut bytes).unwrap();
+        1u32.serialize(&mut bytes).unwrap();
+        TxHash::new("blob_tx").serialize(&mut bytes).unwrap();
+        ProofDataHash("proof".into()).serialize(&mut bytes).unwrap();
+        legacy_hyle_output(&mut bytes);
+        ProgramId(vec![3]).serialize(&mut bytes).unwrap();
+        false.serialize(&mut bytes).unwrap();
+        AggregateSignature::default().serialize(&mut bytes).unwrap();
+        5u64.serialize(&mut bytes).unwrap();
+        0u64.serialize(&mut bytes).unwrap();
+        ValidatorPublicKey::default().serialize(&mut bytes).unwrap();
+        vec![(
+            LaneId::default(),
+            DataProposalHash("dp".into()),
+            LaneBytesSize(10),
+            hyle_model::PoDA::default(),
+        )]
+        .serialize(&mut bytes)
+        .unwrap();
+        Vec::<ConsensusStakingAction>::new()
+            .serialize(&mut bytes)
+            .unwrap();
+        42u64.serialize(&mut bytes).unwrap();
+        ConsensusProposalHash("parent".into())
+            .serialize(&mut bytes)
+            .unwrap();
+
+        assert!(borsh::from_slice::<SignedBlock>(&bytes).is_err());
+        let block = decode_signed_block(&bytes).unwrap();
+
+        assert_eq!(block.consensus_proposal.slot, 5);
+        assert_eq!(block.consensus_proposal.timestamp, 42);
+        assert_eq!(
+            block.consensus_proposal.seed_signature,
+            Signature::default()
+        );
+        let (_, dps) = &block.data_proposals[0];
+        assert_eq!(dps[0].txs[0].hashed(), blob_tx.hashed());
+        let TransactionData::VerifiedProof(proof_tx) = &dps[0].txs[1].transaction_data else {
+            panic!("expected a verified proof");
+        };
+        assert_eq!(
+            proof_tx.proven_blobs[0].hyle_output.onchain_effects,
+            vec![OnchainEffect::RegisterContract(RegisterContractEffect {
+                verifier: Verifier("test".into()),
+                program_id: ProgramId(vec![3]),
+                state_digest: StateDigest(vec![4]),
+                contract_name: "c1".into(),
+                timeout_window: None,
+            })]
+        );
+
+        // The current layout still decodes as is
+        let bytes = borsh::to_vec(&block).unwrap();
+        let decoded = decode_signed_block(&bytes).unwrap();
+        assert_eq!(decoded.hashed(), block.hashed());
+    }
+}
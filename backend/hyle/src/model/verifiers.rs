@@ -6,6 +6,8 @@ use hyle_contract_sdk::{
 pub enum NativeVerifiers {
     Blst,
     Sha3_256,
+    Ed25519,
+    Secp256k1,
 }
 
 impl From<NativeVerifiers> for ProgramId {
@@ -13,6 +15,8 @@ impl From<NativeVerifiers> for ProgramId {
         match value {
             NativeVerifiers::Blst => ProgramId("blst".as_bytes().to_vec()),
             NativeVerifiers::Sha3_256 => ProgramId("sha3_256".as_bytes().to_vec()),
+            NativeVerifiers::Ed25519 => ProgramId("ed25519".as_bytes().to_vec()),
+            NativeVerifiers::Secp256k1 => ProgramId("secp256k1".as_bytes().to_vec()),
         }
     }
 }
@@ -23,6 +27,8 @@ impl TryFrom<&Verifier> for NativeVerifiers {
         match value.0.as_str() {
             "blst" => Ok(Self::Blst),
             "sha3_256" => Ok(Self::Sha3_256),
+            "ed25519" => Ok(Self::Ed25519),
+            "secp256k1" => Ok(Self::Secp256k1),
             _ => Err(format!("Unknown native verifier: {}", value)),
         }
     }
@@ -87,3 +93,68 @@ impl ContractAction for ShaBlob {
         }
     }
 }
+
+/// Format of the BlobData for native contract "ed25519"
+#[derive(Debug, borsh::BorshSerialize, borsh::BorshDeserialize)]
+pub struct Ed25519SignatureBlob {
+    pub identity: Identity,
+    pub data: Vec<u8>,
+    /// Signature for concatenated data + identity.as_bytes(), 64 bytes
+    pub signature: Vec<u8>,
+    /// 32 bytes public key
+    pub public_key: Vec<u8>,
+}
+
+impl Ed25519SignatureBlob {
+    pub fn as_blob(&self) -> Blob {
+        <Self as ContractAction>::as_blob(self, "ed25519".into(), None, None)
+    }
+}
+
+impl ContractAction for Ed25519SignatureBlob {
+    fn as_blob(
+        &self,
+        contract_name: ContractName,
+        _caller: Option<BlobIndex>,
+        _callees: Option<Vec<BlobIndex>>,
+    ) -> Blob {
+        #[allow(clippy::expect_used)]
+        Blob {
+            contract_name,
+            data: BlobData(borsh::to_vec(self).expect("failed to encode Ed25519SignatureBlob")),
+        }
+    }
+}
+
+/// Format of the BlobData for native contract "secp256k1"
+#[derive(Debug, borsh::BorshSerialize, borsh::BorshDeserialize)]
+pub struct Secp256k1SignatureBlob {
+    pub identity: Identity,
+    pub data: Vec<u8>,
+    /// ECDSA signature over the SHA-256 of concatenated data + identity.as_bytes().
+    /// 64 bytes (r || s), with a normalized (low) s.
+    pub signature: Vec<u8>,
+    /// SEC1 encoded public key, compressed or not
+    pub public_key: Vec<u8>,
+}
+
+impl Secp256k1SignatureBlob {
+    pub fn as_blob(&self) -> Blob {
+        <Self as ContractAction>::as_blob(self, "secp256k1".into(), None, None)
+    }
+}
+
+impl ContractAction for Secp256k1SignatureBlob {
+    fn as_blob(
+        &self,
+        contract_name: ContractName,
+        _caller: Option<BlobIndex>,
+        _callees: Option<Vec<BlobIndex>>,
+    ) -> Blob {
+        #[allow(clippy::expect_used)]
+        Blob {
+            contract_name,
+            data: BlobData(borsh::to_vec(self).expect("failed to encode Secp256k1SignatureBlob")),
+        }
+    }
+}
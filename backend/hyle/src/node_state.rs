@@ -1,7 +1,8 @@
 //! State required for participation in consensus by the node.
 
 use crate::model::contract_registration::{
-    validate_contract_name_registration, validate_state_digest_size,
+    validate_contract_name_registration, validate_contract_program_update,
+    validate_state_digest_size,
 };
 use crate::model::verifiers::NativeVerifiers;
 use crate::model::*;
@@ -22,6 +23,7 @@ use tracing::{debug, error, info, trace};
 
 mod api;
 mod hyle_tld;
+mod migration;
 pub mod module;
 mod ordered_tx_map;
 mod timeouts;
@@ -39,6 +41,8 @@ enum SideEffect {
     Register(Contract),
     // Pass a full Contract because it's simpler in the settlement logic
     UpdateState(Contract),
+    // The full contract with its new program, so that later blobs are checked against it
+    UpdateProgram(Contract),
     Delete(ContractName),
 }
 
@@ -49,10 +53,38 @@ impl SideEffect {
             (SideEffect::Register(reg), SideEffect::UpdateState(contract)) => {
                 reg.state = contract.state
             }
-            (SideEffect::Delete(_), SideEffect::UpdateState(_)) => {}
+            (SideEffect::Register(reg), SideEffect::UpdateProgram(contract)) => {
+                reg.verifier = contract.verifier;
+                reg.program_id = contract.program_id;
+            }
+            (SideEffect::UpdateProgram(upd), SideEffect::UpdateState(contract)) => {
+                upd.state = contract.state
+            }
+            (SideEffect::Delete(_), SideEffect::UpdateState(_) | SideEffect::UpdateProgram(_)) => {}
             (me, other) => *me = other,
         }
     }
+
+    fn contract(&self) -> Option<&Contract> {
+        match self {
+            SideEffect::Register(c) | SideEffect::UpdateState(c) | SideEffect::UpdateProgram(c) => {
+                Some(c)
+            }
+            SideEffect::Delete(_) => None,
+        }
+    }
+}
+
+/// Returns the contract as it is after the changes already settled in the current transaction.
+fn current_contract<'a>(
+    contracts: &'a HashMap<ContractName, Contract>,
+    contract_changes: &'a BTreeMap<ContractName, SideEffect>,
+    contract_name: &ContractName,
+) -> Option<&'a Contract> {
+    match contract_changes.get(contract_name) {
+        Some(side_effect) => side_effect.contract(),
+        None => contracts.get(contract_name),
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -71,6 +103,9 @@ pub struct NodeState {
     // This field is public for testing purposes
     pub contracts: HashMap<ContractName, Contract>,
     unsettled_transactions: OrderedTxMap,
+    /// Identity that can change the contracts without owner through the 'hyle' TLD,
+    /// set by a [`SetTldAdminAction`] blob
+    tld_admin: Option<Identity>,
 }
 
 // TODO: we should register the 'hyle' TLD in the genesis block.
@@ -81,6 +116,7 @@ impl Default for NodeState {
             current_height: BlockHeight(0),
             contracts: HashMap::new(),
             unsettled_transactions: OrderedTxMap::default(),
+            tld_admin: None,
         };
         // Insert a default hyle-TLD contract
         ret.contracts.insert(
@@ -90,6 +126,8 @@ impl Default for NodeState {
                 program_id: ProgramId(vec![]),
                 state: StateDigest(vec![0]),
                 verifier: Verifier("hyle".to_owned()),
+                timeout_window: None,
+                owner: None,
             },
         );
         ret
@@ -130,12 +168,33 @@ impl NodeState {
                     _ => None,
                 })
                 .collect(),
+            unbonded_validators: signed_block
+                .consensus_proposal
+                .staking_actions
+                .iter()
+                .filter_map(|v| match v {
+                    ConsensusStakingAction::Unbond { validator }
+                    | ConsensusStakingAction::Jail { validator } => Some(validator.clone()),
+                    _ => None,
+                })
+                .collect(),
+            slashed_validators: signed_block
+                .consensus_proposal
+                .staking_actions
+                .iter()
+                .filter_map(|v| match v {
+                    ConsensusStakingAction::Slash { evidence } => Some(evidence.offender().clone()),
+                    _ => None,
+                })
+                .collect(),
             timed_out_txs: vec![], // Added below as it needs the block
             registered_contracts: vec![],
             deleted_contracts: vec![],
+            updated_contract_programs: vec![],
             updated_states: BTreeMap::new(),
             transactions_events: BTreeMap::new(),
             dp_hashes: BTreeMap::new(),
+            consensus_params: None,
         };
 
         // We'll need to remember some data to validate transactions proofs.
@@ -232,8 +291,11 @@ impl NodeState {
                 if self.unsettled_transactions.is_next_to_settle(unsettled_tx) {
                     if let Some(tx) = self.unsettled_transactions.get(unsettled_tx) {
                         // Timeout starts from when the blob tx was sequenced
-                        self.timeouts
-                            .set(unsettled_tx.clone(), tx.tx_context.block_height);
+                        self.timeouts.set(
+                            unsettled_tx.clone(),
+                            tx.tx_context.block_height,
+                            tx.timeout_window,
+                        );
                     }
                 }
             }
@@ -252,6 +314,8 @@ impl NodeState {
                 program_id: tx.program_id.clone(),
                 state: tx.state_digest.clone(),
                 verifier: tx.verifier.clone(),
+                timeout_window: tx.timeout_window,
+                owner: None,
             },
         );
     }
@@ -311,6 +375,12 @@ impl NodeState {
             })
             .collect();
 
+        let timeout_window = self.timeouts.window(tx.blobs.iter().map(|blob| {
+            self.contracts
+                .get(&blob.contract_name)
+                .and_then(|contract| contract.timeout_window)
+        }));
+
         // If we're behind other pending transactions, we can't settle yet.
         should_try_and_settle = self.unsettled_transactions.add(UnsettledBlobTransaction {
             identity: tx.identity.clone(),
@@ -319,11 +389,13 @@ impl NodeState {
             tx_context,
             blobs_hash,
             blobs,
+            timeout_window,
         }) && should_try_and_settle;
 
         if self.unsettled_transactions.is_next_to_settle(&blob_tx_hash) {
             // Update timeouts
-            self.timeouts.set(blob_tx_hash.clone(), self.current_height);
+            self.timeouts
+                .set(blob_tx_hash.clone(), self.current_height, timeout_window);
         }
 
         if should_try_and_settle {
@@ -480,6 +552,8 @@ impl NodeState {
 
         let result = match Self::settle_blobs_recursively(
             &self.contracts,
+            self.tld_admin.as_ref(),
+            unsettled_tx,
             updated_contracts,
             unsettled_tx.blobs.iter(),
             vec![],
@@ -507,6 +581,8 @@ impl NodeState {
 
     fn settle_blobs_recursively<'a>(
         contracts: &HashMap<ContractName, Contract>,
+        tld_admin: Option<&Identity>,
+        tx: &UnsettledBlobTransaction,
         mut contract_changes: BTreeMap<ContractName, SideEffect>,
         mut blob_iter: impl Iterator<Item = &'a UnsettledBlobMetadata> + Clone,
         mut blob_proof_output_indices: Vec<usize>,
@@ -536,12 +612,16 @@ impl NodeState {
             return match handle_blob_for_hyle_tld(
                 contracts,
                 &mut contract_changes,
+                tld_admin,
+                tx,
                 &current_blob.blob,
             ) {
                 Ok(()) => {
                     tracing::trace!("Settlement - OK side effect");
                     Self::settle_blobs_recursively(
                         contracts,
+                        tld_admin,
+                        tx,
                         contract_changes,
                         blob_iter.clone(),
                         blob_proof_output_indices.clone(),
@@ -563,6 +643,7 @@ impl NodeState {
             .and_then(|c| match c {
                 SideEffect::Register(c) => Some(c),
                 SideEffect::UpdateState(c) => Some(c),
+                SideEffect::UpdateProgram(c) => Some(c),
                 _ => None,
             })
             .or(contracts.get(contract_name))
@@ -585,9 +666,12 @@ impl NodeState {
 
             // TODO: ideally make this CoW
             let mut current_contracts = contract_changes.clone();
-            if let Err(msg) =
-                Self::process_proof(&mut current_contracts, proof_metadata, known_contract_state)
-            {
+            if let Err(msg) = Self::process_proof(
+                contracts,
+                &mut current_contracts,
+                proof_metadata,
+                known_contract_state,
+            ) {
                 // Not a valid proof, log it and try the next one.
                 let msg = format!(
                     "Could not settle blob proof output #{} for contract '{}': {}",
@@ -609,6 +693,8 @@ impl NodeState {
             tracing::trace!("Settlement - OK blob");
             match Self::settle_blobs_recursively(
                 contracts,
+                tld_admin,
+                tx,
                 current_contracts,
                 blob_iter.clone(),
                 blob_proof_output_indices.clone(),
@@ -687,6 +773,24 @@ impl NodeState {
             })
             .collect::<BTreeSet<_>>();
 
+        // Take note of the TLD admin and consensus parameters, the 'hyle' TLD checked who could
+        // set them when settling
+        for blob_metadata in settled_tx.blobs.iter() {
+            if blob_metadata.blob.contract_name.0 != "hyle" {
+                continue;
+            }
+            if let Ok(set) = StructuredBlobData::<SetConsensusParamsAction>::try_from(
+                blob_metadata.blob.data.clone(),
+            ) {
+                block_under_construction.consensus_params = Some(set.parameters.params);
+            } else if let Ok(set) =
+                StructuredBlobData::<SetTldAdminAction>::try_from(blob_metadata.blob.data.clone())
+            {
+                info!("🔑 TLD admin set to {:?}", set.parameters.tld_admin);
+                self.tld_admin = set.parameters.tld_admin;
+            }
+        }
+
         // Take note of staking
         for blob_metadata in settled_tx.blobs.into_iter() {
             let blob = blob_metadata.blob;
@@ -695,9 +799,11 @@ impl NodeState {
                 if let Some(structured_blob) = parse_structured_blob(&[blob], &BlobIndex(0)) {
                     let staking_action: StakingAction = structured_blob.data.parameters;
 
-                    block_under_construction
-                        .staking_actions
-                        .push((settled_tx.identity.clone(), staking_action));
+                    block_under_construction.staking_actions.push((
+                        settled_tx.identity.clone(),
+                        staking_action,
+                        settled_tx.tx_context.block_height,
+                    ));
                 } else {
                     error!("Failed to parse StakingAction");
                 }
@@ -738,6 +844,7 @@ impl NodeState {
                             program_id: contract.program_id.clone(),
                             state_digest: contract.state.clone(),
                             verifier: contract.verifier.clone(),
+                            timeout_window: contract.timeout_window,
                         },
                     ));
 
@@ -746,6 +853,35 @@ impl NodeState {
                         .updated_states
                         .insert(contract.name, contract.state);
                 }
+                SideEffect::UpdateProgram(contract) => {
+                    let Some(existing) = self.contracts.get_mut(&contract.name) else {
+                        // We presume this was because it's been deleted so everything is OK.
+                        debug!(
+                            "🪦 Updating contract {} program cannot happen - no longer exists",
+                            &contract.name
+                        );
+                        continue;
+                    };
+                    debug!(
+                        "🔁 Update {} contract program: {:?} ({})",
+                        &contract.name, contract.program_id, contract.verifier
+                    );
+                    existing.verifier = contract.verifier.clone();
+                    existing.program_id = contract.program_id.clone();
+                    existing.state = contract.state.clone();
+
+                    block_under_construction.updated_contract_programs.push((
+                        bth.clone(),
+                        UpdateContractProgramEffect {
+                            contract_name: contract.name.clone(),
+                            verifier: contract.verifier,
+                            program_id: contract.program_id,
+                        },
+                    ));
+                    block_under_construction
+                        .updated_states
+                        .insert(contract.name, contract.state);
+                }
                 // clippy lint set here because setting it on expressions is experimental
                 #[allow(clippy::unwrap_used, reason = "we check existence before get_mut")]
                 SideEffect::UpdateState(contract) => {
@@ -828,6 +964,7 @@ impl NodeState {
     // Called when trying to actually settle a blob TX - processes a proof for settlement.
     // verify_hyle_output has already been called at this point.
     fn process_proof(
+        contracts: &HashMap<ContractName, Contract>,
         contract_changes: &mut BTreeMap<ContractName, SideEffect>,
         proof_metadata: &(ProgramId, HyleOutput),
         contract: &Contract,
@@ -865,6 +1002,12 @@ impl NodeState {
                         &effect.program_id,
                         &effect.state_digest,
                     )?;
+                    // Contracts registered by a proof have no owner: through the 'hyle' TLD,
+                    // only the TLD admin can delete them or update their program.
+                    // A contract registered again keeps its owner
+                    let owner =
+                        current_contract(contracts, contract_changes, &effect.contract_name)
+                            .and_then(|c| c.owner.clone());
                     contract_changes.insert(
                         effect.contract_name.clone(),
                         SideEffect::Register(Contract {
@@ -872,6 +1015,8 @@ impl NodeState {
                             program_id: effect.program_id.clone(),
                             state: effect.state_digest.clone(),
                             verifier: effect.verifier.clone(),
+                            timeout_window: effect.timeout_window,
+                            owner,
                         }),
                     );
                 }
@@ -880,6 +1025,28 @@ impl NodeState {
                     validate_contract_name_registration(&contract.name, cn)?;
                     contract_changes.insert(cn.clone(), SideEffect::Delete(cn.clone()));
                 }
+                OnchainEffect::UpdateContractProgram(effect) => {
+                    validate_contract_program_update(
+                        &contract.name,
+                        &effect.contract_name,
+                        &effect.verifier,
+                        &effect.program_id,
+                    )?;
+                    let Some(target) =
+                        current_contract(contracts, contract_changes, &effect.contract_name)
+                    else {
+                        bail!("Contract {} is not registered", effect.contract_name);
+                    };
+                    let update = SideEffect::UpdateProgram(Contract {
+                        verifier: effect.verifier.clone(),
+                        program_id: effect.program_id.clone(),
+                        ..target.clone()
+                    });
+                    contract_changes
+                        .entry(effect.contract_name.clone())
+                        .and_modify(|c| c.apply(update.clone()))
+                        .or_insert(update);
+                }
             }
         }
 
@@ -933,8 +1100,14 @@ impl NodeState {
                 // For each transaction that could not be settled, if it is the next one to be settled, reset its timeout
                 for unsettled_tx in next_unsettled_txs {
                     if self.unsettled_transactions.is_next_to_settle(&unsettled_tx) {
-                        // Timeout starts from current_height
-                        self.timeouts.set(unsettled_tx.clone(), self.current_height);
+                        if let Some(tx) = self.unsettled_transactions.get(&unsettled_tx) {
+                            // Timeout starts from current_height
+                            self.timeouts.set(
+                                unsettled_tx.clone(),
+                                self.current_height,
+                                tx.timeout_window,
+                            );
+                        }
                     }
                 }
 
@@ -978,6 +1151,7 @@ pub mod test {
                 program_id: ProgramId(vec![]),
                 state_digest: StateDigest(vec![0, 1, 2, 3]),
                 contract_name: name,
+                timeout_window: None,
             }
             .as_blob("hyle".into(), None, None)],
         )
@@ -989,6 +1163,7 @@ pub mod test {
             program_id: ProgramId(vec![]),
             state_digest: StateDigest(vec![0, 1, 2, 3]),
             contract_name,
+            timeout_window: None,
         }
     }
 
@@ -1635,6 +1810,74 @@ pub mod test {
         assert!(state.unsettled_transactions.get(&blob_tx_hash).is_none());
     }
 
+    #[test_log::test(tokio::test)]
+    async fn test_tx_timeout_declared_window() {
+        let mut state = new_node_state().await;
+        let c1 = ContractName::new("c1");
+        let c2 = ContractName::new("c2");
+        let c3 = ContractName::new("c3");
+        let register = |name: &ContractName, timeout_window: u64| {
+            BlobTransaction::new(
+                "hyle.hyle",
+                vec![RegisterContractAction {
+                    verifier: "test".into(),
+                    program_id: ProgramId(vec![]),
+                    state_digest: StateDigest(vec![0, 1, 2, 3]),
+                    contract_name: name.clone(),
+                    timeout_window: Some(BlockHeight(timeout_window)),
+                }
+                .as_blob("hyle".into(), None, None)],
+            )
+        };
+
+        // c2 declares a window above the protocol maximum, it is clamped.
+        state.handle_signed_block(&craft_signed_block(
+            1,
+            vec![
+                register(&c1, 20).into(),
+                register(&c2, 50_000).into(),
+                make_register_contract_tx(c3.clone()).into(),
+            ],
+        ));
+
+        let blob_tx_c1 = BlobTransaction::new(Identity::new("test.c1"), vec![new_blob(&c1.0)]);
+        let blob_tx_both = BlobTransaction::new(
+            Identity::new("test.c2"),
+            vec![new_blob(&c2.0), new_blob(&c3.0)],
+        );
+        let hash_c1 = blob_tx_c1.hashed();
+        let hash_both = blob_tx_both.hashed();
+
+        state.handle_signed_block(&craft_signed_block(
+            3,
+            vec![blob_tx_c1.into(), blob_tx_both.into()],
+        ));
+
+        assert_eq!(
+            timeouts::tests::get(&state.timeouts, &hash_c1),
+            Some(BlockHeight(23))
+        );
+        assert_eq!(
+            state
+                .unsettled_transactions
+                .get(&hash_c1)
+                .unwrap()
+                .timeout_window,
+            BlockHeight(20)
+        );
+        // A transaction touching several contracts waits for the longest window.
+        assert_eq!(
+            timeouts::tests::get(&state.timeouts, &hash_both),
+            Some(BlockHeight(3) + timeouts::MAX_TIMEOUT_WINDOW)
+        );
+
+        let timed_out_tx_hashes = state
+            .handle_signed_block(&craft_signed_block(23, vec![]))
+            .timed_out_txs;
+        assert!(timed_out_tx_hashes.contains(&hash_c1));
+        assert!(!timed_out_tx_hashes.contains(&hash_both));
+    }
+
     #[test_log::test(tokio::test)]
     async fn test_tx_no_timeout_once_settled() {
         let mut state = new_node_state().await;
@@ -1853,23 +2096,28 @@ pub mod test {
     mod contract_registration {
         use std::collections::HashSet;
 
+        use sha3::Digest;
+
         use super::*;
+        use crate::model::verifiers::ShaBlob;
+
+        pub fn make_register_blob(tld: ContractName, name: ContractName) -> Blob {
+            RegisterContractAction {
+                verifier: "test".into(),
+                program_id: ProgramId(vec![]),
+                state_digest: StateDigest(vec![0, 1, 2, 3]),
+                contract_name: name,
+                timeout_window: None,
+            }
+            .as_blob(tld, None, None)
+        }
 
         pub fn make_register_tx(
             sender: Identity,
             tld: ContractName,
             name: ContractName,
         ) -> BlobTransaction {
-            BlobTransaction::new(
-                sender,
-                vec![RegisterContractAction {
-                    verifier: "test".into(),
-                    program_id: ProgramId(vec![]),
-                    state_digest: StateDigest(vec![0, 1, 2, 3]),
-                    contract_name: name,
-                }
-                .as_blob(tld, None, None)],
-            )
+            BlobTransaction::new(sender, vec![make_register_blob(tld, name)])
         }
 
         #[test_log::test(tokio::test)]
@@ -1972,6 +2220,7 @@ pub mod test {
                         program_id: ProgramId(vec![]),
                         state_digest: StateDigest(vec![0, 1, 2, 3]),
                         contract_name: "c1".into(),
+                        timeout_window: None,
                     }
                     .as_blob("hyle".into(), None, None),
                     Blob {
@@ -2079,12 +2328,36 @@ pub mod test {
             )
         }
 
+        /// Sends `blob` from `owner`, an identity of the `wallet` contract.
+        /// The returned proof of the `wallet` blob proves the identity without changing the wallet state.
+        pub fn make_owned_tx(
+            owner: &str,
+            blob: Blob,
+        ) -> (BlobTransaction, VerifiedProofTransaction) {
+            let tx = BlobTransaction::new(owner, vec![new_blob("wallet"), blob]);
+            let output =
+                make_hyle_output_with_state(tx.clone(), BlobIndex(0), &[0, 1, 2, 3], &[0, 1, 2, 3]);
+            let proof = new_proof_tx(&"wallet".into(), &output, &tx.hashed());
+            (tx, proof)
+        }
+
+        fn make_delete_blob(contract_name: &str) -> Blob {
+            DeleteContractAction {
+                contract_name: contract_name.into(),
+            }
+            .as_blob("hyle".into(), None, None)
+        }
+
         #[test_log::test(tokio::test)]
         async fn test_register_contract_and_delete_hyle() {
             let mut state = new_node_state().await;
 
             let register_c1 = make_register_tx("hyle.hyle".into(), "hyle".into(), "c1".into());
-            let register_c2 = make_register_tx("hyle.hyle".into(), "hyle".into(), "c2.hyle".into());
+            let register_wallet = make_register_contract_tx("wallet".into());
+            let (register_c2, register_c2_owner_proof) = make_owned_tx(
+                "alice.wallet",
+                make_register_blob("hyle".into(), "c2.hyle".into()),
+            );
             // This technically doesn't matter as it's actually the proof that does the work
             let register_sub_c2 = make_register_tx(
                 "toto.c2.hyle".into(),
@@ -2100,14 +2373,17 @@ pub mod test {
                     program_id: ProgramId(vec![1]),
                     state_digest: StateDigest(vec![0, 1, 2, 3]),
                     contract_name: "sub.c2.hyle".into(),
+                    timeout_window: None,
                 }));
             let sub_c2_proof = new_proof_tx(&"c2.hyle".into(), &output, &register_sub_c2.hashed());
 
             let block = state.handle_signed_block(&craft_signed_block(
                 1,
                 vec![
+                    register_wallet.into(),
                     register_c1.into(),
                     register_c2.into(),
+                    register_c2_owner_proof.into(),
                     register_sub_c2.into(),
                     sub_c2_proof.into(),
                 ],
@@ -2118,9 +2394,21 @@ pub mod test {
                     .iter()
                     .map(|(_, rce)| rce.contract_name.0.clone())
                     .collect::<Vec<_>>(),
-                vec!["c1", "c2.hyle", "sub.c2.hyle"]
+                vec!["wallet", "c1", "c2.hyle", "sub.c2.hyle"]
+            );
+            assert_eq!(state.contracts.len(), 5);
+            assert_eq!(
+                state
+                    .contracts
+                    .get(&ContractName::new("c2.hyle"))
+                    .unwrap()
+                    .owner,
+                Some("alice.wallet".into())
+            );
+            assert_eq!(
+                state.contracts.get(&ContractName::new("c1")).unwrap().owner,
+                None
             );
-            assert_eq!(state.contracts.len(), 4);
 
             // Now delete them.
             let self_delete_tx = make_delete_tx("c1.c1".into(), "c1".into(), "c1".into());
@@ -2129,7 +2417,11 @@ pub mod test {
                 "c2.hyle".into(),
                 "sub.c2.hyle".into(),
             );
-            let delete_tx = make_delete_tx("hyle.hyle".into(), "hyle".into(), "c2.hyle".into());
+            // Only its owner can delete c2.hyle through the 'hyle' TLD
+            let not_owner_delete_tx =
+                make_delete_tx("hyle.hyle".into(), "hyle".into(), "c2.hyle".into());
+            let (delete_tx, delete_owner_proof) =
+                make_owned_tx("alice.wallet", make_delete_blob("c2.hyle"));
 
             let mut output = make_hyle_output(self_delete_tx.clone(), BlobIndex(0));
             output
@@ -2153,7 +2445,9 @@ pub mod test {
                     delete_sub_tx.into(),
                     delete_self_proof.into(),
                     delete_sub_proof.into(),
+                    not_owner_delete_tx.clone().into(),
                     delete_tx.into(),
+                    delete_owner_proof.into(),
                 ],
             ));
 
@@ -2165,14 +2459,19 @@ pub mod test {
                     .collect::<Vec<_>>(),
                 vec!["c1", "sub.c2.hyle", "c2.hyle"]
             );
-            assert_eq!(state.contracts.len(), 1);
+            assert_eq!(block.failed_txs, vec![not_owner_delete_tx.hashed()]);
+            assert_eq!(state.contracts.len(), 2);
         }
 
         #[test_log::test(tokio::test)]
         async fn test_hyle_sub_delete() {
             let mut state = new_node_state().await;
 
-            let register_c2 = make_register_tx("hyle.hyle".into(), "hyle".into(), "c2.hyle".into());
+            let register_wallet = make_register_contract_tx("wallet".into());
+            let (register_c2, register_c2_owner_proof) = make_owned_tx(
+                "alice.wallet",
+                make_register_blob("hyle".into(), "c2.hyle".into()),
+            );
             // This technically doesn't matter as it's actually the proof that does the work
             let register_sub_c2 = make_register_tx(
                 "toto.c2.hyle".into(),
@@ -2188,27 +2487,120 @@ pub mod test {
                     program_id: ProgramId(vec![1]),
                     state_digest: StateDigest(vec![0, 1, 2, 3]),
                     contract_name: "sub.c2.hyle".into(),
+                    timeout_window: None,
                 }));
             let sub_c2_proof = new_proof_tx(&"c2.hyle".into(), &output, &register_sub_c2.hashed());
 
             state.handle_signed_block(&craft_signed_block(
                 1,
                 vec![
+                    register_wallet.into(),
                     register_c2.into(),
+                    register_c2_owner_proof.into(),
                     register_sub_c2.into(),
                     sub_c2_proof.into(),
                 ],
             ));
+            assert_eq!(state.contracts.len(), 4);
+
+            // Now delete the intermediate contract first, then try to delete the sub-contract via hyle.
+            // The sub-contract was registered by c2.hyle, so it has no owner for the 'hyle' TLD.
+            let (delete_tx, delete_owner_proof) =
+                make_owned_tx("alice.wallet", make_delete_blob("c2.hyle"));
+            let (delete_sub_tx, delete_sub_owner_proof) =
+                make_owned_tx("alice.wallet", make_delete_blob("sub.c2.hyle"));
+
+            let block = state.handle_signed_block(&craft_signed_block(
+                2,
+                vec![
+                    delete_tx.into(),
+                    delete_sub_tx.clone().into(),
+                    delete_owner_proof.into(),
+                    delete_sub_owner_proof.into(),
+                ],
+            ));
+
+            assert_eq!(
+                block
+                    .deleted_contracts
+                    .iter()
+                    .map(|(_, dce)| dce.0.clone())
+                    .collect::<Vec<_>>(),
+                vec!["c2.hyle"]
+            );
+            assert_eq!(block.failed_txs, vec![delete_sub_tx.hashed()]);
             assert_eq!(state.contracts.len(), 3);
+        }
 
-            // Now delete the intermediate contract first, then delete the sub-contract via hyle
-            let delete_tx = make_delete_tx("hyle.hyle".into(), "hyle".into(), "c2.hyle".into());
-            let delete_sub_tx =
-                make_delete_tx("hyle.hyle".into(), "hyle".into(), "sub.c2.hyle".into());
+        #[test_log::test(tokio::test)]
+        async fn test_tld_admin_changes_contracts_without_owner() {
+            let mut state = new_node_state().await;
+
+            // The TLD admin is set in the genesis block
+            let genesis_tx = BlobTransaction::new(
+                "hyle.hyle",
+                vec![SetTldAdminAction {
+                    tld_admin: Some("admin.wallet".into()),
+                }
+                .as_blob("hyle".into(), None, None)],
+            );
+            state.handle_signed_block(&craft_signed_block(0, vec![genesis_tx.into()]));
+            assert_eq!(state.tld_admin, Some("admin.wallet".into()));
+
+            let register_wallet = make_register_contract_tx("wallet".into());
+            let register_c1 = make_register_tx("hyle.hyle".into(), "hyle".into(), "c1".into());
+            let (register_c2, register_c2_owner_proof) = make_owned_tx(
+                "alice.wallet",
+                make_register_blob("hyle".into(), "c2.hyle".into()),
+            );
+            // Later on, only the admin can set the TLD admin
+            let set_admin_blob = SetTldAdminAction {
+                tld_admin: Some("bob.wallet".into()),
+            }
+            .as_blob("hyle".into(), None, None);
+            let hyle_set_admin_tx = BlobTransaction::new("hyle.hyle", vec![set_admin_blob.clone()]);
+            let (bob_set_admin_tx, bob_set_admin_proof) =
+                make_owned_tx("bob.wallet", set_admin_blob);
+            let block = state.handle_signed_block(&craft_signed_block(
+                1,
+                vec![
+                    register_wallet.into(),
+                    register_c1.into(),
+                    register_c2.into(),
+                    register_c2_owner_proof.into(),
+                    hyle_set_admin_tx.clone().into(),
+                    bob_set_admin_tx.clone().into(),
+                    bob_set_admin_proof.into(),
+                ],
+            ));
+            assert_eq!(
+                block.failed_txs,
+                vec![hyle_set_admin_tx.hashed(), bob_set_admin_tx.hashed()]
+            );
+            assert_eq!(state.tld_admin, Some("admin.wallet".into()));
+            assert_eq!(
+                state.contracts.get(&ContractName::new("c1")).unwrap().owner,
+                None
+            );
+
+            // The admin deletes c1, which has no owner, but not c2.hyle, which is owned by alice
+            let (not_admin_delete_tx, not_admin_proof) =
+                make_owned_tx("bob.wallet", make_delete_blob("c1"));
+            let (delete_c1_tx, delete_c1_proof) =
+                make_owned_tx("admin.wallet", make_delete_blob("c1"));
+            let (delete_c2_tx, delete_c2_proof) =
+                make_owned_tx("admin.wallet", make_delete_blob("c2.hyle"));
 
             let block = state.handle_signed_block(&craft_signed_block(
                 2,
-                vec![delete_tx.into(), delete_sub_tx.into()],
+                vec![
+                    not_admin_delete_tx.clone().into(),
+                    not_admin_proof.into(),
+                    delete_c1_tx.into(),
+                    delete_c1_proof.into(),
+                    delete_c2_tx.clone().into(),
+                    delete_c2_proof.into(),
+                ],
             ));
 
             assert_eq!(
@@ -2217,15 +2609,264 @@ pub mod test {
                     .iter()
                     .map(|(_, dce)| dce.0.clone())
                     .collect::<Vec<_>>(),
-                vec!["c2.hyle", "sub.c2.hyle"]
+                vec!["c1"]
+            );
+            assert_eq!(
+                block.failed_txs,
+                vec![not_admin_delete_tx.hashed(), delete_c2_tx.hashed()]
+            );
+            assert!(state.contracts.contains_key(&ContractName::new("c2.hyle")));
+        }
+
+        #[test_log::test(tokio::test)]
+        async fn test_consensus_params_set_in_genesis() {
+            let mut state = new_node_state().await;
+
+            let params = ConsensusParams {
+                leader_election: LeaderElection::RoundRobin,
+                fee_burn_percent: 30,
+                fee_treasury: Some(ValidatorPublicKey::new_for_tests("treasury")),
+            };
+            let set_params_blob = SetConsensusParamsAction {
+                params: params.clone(),
+            }
+            .as_blob("hyle".into(), None, None);
+            let genesis_tx = BlobTransaction::new("hyle.hyle", vec![set_params_blob.clone()]);
+            let block = state.handle_signed_block(&craft_signed_block(0, vec![genesis_tx.into()]));
+            assert_eq!(block.consensus_params, Some(params));
+
+            // They can't change once the chain runs
+            let set_params_tx = BlobTransaction::new("hyle.hyle", vec![set_params_blob]);
+            let block = state
+                .handle_signed_block(&craft_signed_block(1, vec![set_params_tx.clone().into()]));
+            assert_eq!(block.failed_txs, vec![set_params_tx.hashed()]);
+            assert_eq!(block.consensus_params, None);
+
+            // More than all the fees can't be burned
+            let mut state = new_node_state().await;
+            let genesis_tx = BlobTransaction::new(
+                "hyle.hyle",
+                vec![SetConsensusParamsAction {
+                    params: ConsensusParams {
+                        fee_burn_percent: 101,
+                        ..ConsensusParams::default()
+                    },
+                }
+                .as_blob("hyle".into(), None, None)],
+            );
+            let block =
+                state.handle_signed_block(&craft_signed_block(0, vec![genesis_tx.clone().into()]));
+            assert_eq!(block.failed_txs, vec![genesis_tx.hashed()]);
+            assert_eq!(block.consensus_params, None);
+        }
+
+        #[test_log::test(tokio::test)]
+        async fn test_native_identities_do_not_own_contracts() {
+            let mut state = new_node_state().await;
+
+            let register_sha3 = BlobTransaction::new(
+                "hyle.hyle",
+                vec![RegisterContractAction {
+                    verifier: "sha3_256".into(),
+                    program_id: NativeVerifiers::Sha3_256.into(),
+                    state_digest: StateDigest::default(),
+                    contract_name: "sha3_256".into(),
+                    timeout_window: None,
+                }
+                .as_blob("hyle".into(), None, None)],
+            );
+
+            // Anyone can hash some data with someone else's identity
+            let data = vec![1, 2, 3];
+            let mut hasher = sha3::Sha3_256::new();
+            hasher.update(&data);
+            let identity_blob = ShaBlob {
+                identity: "alice.sha3_256".into(),
+                data,
+                sha: hasher.finalize().to_vec(),
+            }
+            .as_blob("sha3_256".into());
+            let register_c1 = BlobTransaction::new(
+                "alice.sha3_256",
+                vec![
+                    identity_blob,
+                    make_register_blob("hyle".into(), "c1".into()),
+                ],
+            );
+
+            let block = state.handle_signed_block(&craft_signed_block(
+                1,
+                vec![register_sha3.into(), register_c1.clone().into()],
+            ));
+            assert_eq!(block.successful_txs.len(), 2);
+            assert_eq!(
+                state.contracts.get(&ContractName::new("c1")).unwrap().owner,
+                None
+            );
+        }
+
+        #[test_log::test(tokio::test)]
+        async fn test_update_contract_program() {
+            let mut state = new_node_state().await;
+
+            let register_wallet = make_register_contract_tx("wallet".into());
+            let (register_c1, register_c1_owner_proof) = make_owned_tx(
+                "alice.wallet",
+                make_register_blob("hyle".into(), "c1".into()),
+            );
+            let register_c2 = make_register_tx("hyle.hyle".into(), "hyle".into(), "c2.hyle".into());
+            let register_sub_c2 = make_register_tx(
+                "toto.c2.hyle".into(),
+                "c2.hyle".into(),
+                "sub.c2.hyle".into(),
+            );
+
+            let mut output = make_hyle_output(register_sub_c2.clone(), BlobIndex(0));
+            output
+                .onchain_effects
+                .push(OnchainEffect::RegisterContract(RegisterContractEffect {
+                    verifier: "test".into(),
+                    program_id: ProgramId(vec![]),
+                    state_digest: StateDigest(vec![0, 1, 2, 3]),
+                    contract_name: "sub.c2.hyle".into(),
+                    timeout_window: None,
+                }));
+            let sub_c2_proof = new_proof_tx(&"c2.hyle".into(), &output, &register_sub_c2.hashed());
+
+            state.handle_signed_block(&craft_signed_block(
+                1,
+                vec![
+                    register_wallet.into(),
+                    register_c1.into(),
+                    register_c1_owner_proof.into(),
+                    register_c2.into(),
+                    register_sub_c2.into(),
+                    sub_c2_proof.into(),
+                ],
+            ));
+            assert_eq!(state.contracts.len(), 5);
+
+            let update_effect = |contract_name: &str, program_id: u8| {
+                OnchainEffect::UpdateContractProgram(UpdateContractProgramEffect {
+                    contract_name: contract_name.into(),
+                    verifier: "test".into(),
+                    program_id: ProgramId(vec![program_id]),
+                })
+            };
+
+            // A contract cannot update the program of its TLD
+            let forbidden_tx = BlobTransaction::new(
+                Identity::new("toto.sub.c2.hyle"),
+                vec![new_blob("sub.c2.hyle")],
+            );
+            let mut output = make_hyle_output(forbidden_tx.clone(), BlobIndex(0));
+            output.onchain_effects.push(update_effect("c2.hyle", 9));
+            let forbidden_proof =
+                new_proof_tx(&"sub.c2.hyle".into(), &output, &forbidden_tx.hashed());
+
+            // The owner of c1 updates it through Hylé, c2.hyle updates itself and its subdomain
+            let update_c1_blob = |program_id: u8| {
+                UpdateContractProgramAction {
+                    contract_name: "c1".into(),
+                    verifier: "test".into(),
+                    program_id: ProgramId(vec![program_id]),
+                }
+                .as_blob("hyle".into(), None, None)
+            };
+            let (not_owner_update_tx, not_owner_proof) =
+                make_owned_tx("bob.wallet", update_c1_blob(8));
+            let (update_c1_tx, update_c1_owner_proof) =
+                make_owned_tx("alice.wallet", update_c1_blob(1));
+            let update_c2_tx =
+                BlobTransaction::new(Identity::new("toto.c2.hyle"), vec![new_blob("c2.hyle")]);
+            let mut output =
+                make_hyle_output_with_state(update_c2_tx.clone(), BlobIndex(0), &[4, 5, 6], &[7]);
+            output.onchain_effects.push(update_effect("c2.hyle", 2));
+            output.onchain_effects.push(update_effect("sub.c2.hyle", 3));
+            let update_c2_proof = new_proof_tx(&"c2.hyle".into(), &output, &update_c2_tx.hashed());
+
+            let block = state.handle_signed_block(&craft_signed_block(
+                2,
+                vec![
+                    forbidden_tx.clone().into(),
+                    forbidden_proof.into(),
+                    not_owner_update_tx.clone().into(),
+                    not_owner_proof.into(),
+                    update_c1_tx.into(),
+                    update_c1_owner_proof.into(),
+                    update_c2_tx.clone().into(),
+                    update_c2_proof.into(),
+                ],
+            ));
+
+            assert_eq!(
+                block
+                    .updated_contract_programs
+                    .iter()
+                    .map(|(_, effect)| (effect.contract_name.0.clone(), effect.program_id.clone()))
+                    .collect::<Vec<_>>(),
+                vec![
+                    ("c1".to_string(), ProgramId(vec![1])),
+                    ("c2.hyle".to_string(), ProgramId(vec![2])),
+                    ("sub.c2.hyle".to_string(), ProgramId(vec![3])),
+                ]
+            );
+            assert!(!block.successful_txs.contains(&forbidden_tx.hashed()));
+            assert_eq!(block.failed_txs, vec![not_owner_update_tx.hashed()]);
+            assert!(block.successful_txs.contains(&update_c2_tx.hashed()));
+
+            // States are kept, and the state update of the upgrading proof is applied
+            let c1 = state.contracts.get(&ContractName::new("c1")).unwrap();
+            assert_eq!(c1.program_id, ProgramId(vec![1]));
+            assert_eq!(c1.state, StateDigest(vec![0, 1, 2, 3]));
+            let c2 = state.contracts.get(&ContractName::new("c2.hyle")).unwrap();
+            assert_eq!(c2.program_id, ProgramId(vec![2]));
+            assert_eq!(c2.state, StateDigest(vec![7]));
+            let sub_c2 = state
+                .contracts
+                .get(&ContractName::new("sub.c2.hyle"))
+                .unwrap();
+            assert_eq!(sub_c2.program_id, ProgramId(vec![3]));
+            assert_eq!(sub_c2.state, StateDigest(vec![0, 1, 2, 3]));
+
+            // Proofs of the previous program are no longer accepted
+            let next_tx =
+                BlobTransaction::new(Identity::new("toto.c2.hyle"), vec![new_blob("c2.hyle")]);
+            let output = make_hyle_output_with_state(next_tx.clone(), BlobIndex(0), &[7], &[8]);
+            let old_program_proof = new_proof_tx(&"c2.hyle".into(), &output, &next_tx.hashed());
+            let mut new_program_proof = new_proof_tx(&"c2.hyle".into(), &output, &next_tx.hashed());
+            new_program_proof
+                .proven_blobs
+                .iter_mut()
+                .for_each(|proven_blob| proven_blob.program_id = ProgramId(vec![2]));
+
+            let block = state.handle_signed_block(&craft_signed_block(
+                3,
+                vec![next_tx.clone().into(), old_program_proof.into()],
+            ));
+            assert!(block.successful_txs.is_empty());
+
+            let block =
+                state.handle_signed_block(&craft_signed_block(4, vec![new_program_proof.into()]));
+            assert_eq!(block.successful_txs, vec![next_tx.hashed()]);
+            assert_eq!(
+                state
+                    .contracts
+                    .get(&ContractName::new("c2.hyle"))
+                    .unwrap()
+                    .state,
+                StateDigest(vec![8])
             );
-            assert_eq!(state.contracts.len(), 1);
         }
 
         #[test_log::test(tokio::test)]
         async fn test_register_update_delete_combinations_hyle() {
-            let register_tx = make_register_tx("hyle.hyle".into(), "hyle".into(), "c.hyle".into());
-            let delete_tx = make_delete_tx("hyle.hyle".into(), "hyle".into(), "c.hyle".into());
+            let (register_tx, register_owner_proof) = make_owned_tx(
+                "alice.wallet",
+                make_register_blob("hyle".into(), "c.hyle".into()),
+            );
+            let (delete_tx, delete_owner_proof) =
+                make_owned_tx("alice.wallet", make_delete_blob("c.hyle"));
             let delete_self_tx =
                 make_delete_tx("hyle.c.hyle".into(), "c.hyle".into(), "c.hyle".into());
             let update_tx =
@@ -2244,21 +2885,36 @@ pub mod test {
                 .push(OnchainEffect::DeleteContract("c.hyle".into()));
             let proof_delete = new_proof_tx(&"c.hyle".into(), &output, &delete_self_tx.hashed());
 
+            // Owner proofs are sent right after their transaction, so that it settles right away
+            let owner_proofs = &[&register_owner_proof, &delete_owner_proof];
+
             async fn test_combination(
+                owner_proofs: &[&VerifiedProofTransaction],
                 proofs: Option<&[&VerifiedProofTransaction]>,
                 txs: &[&BlobTransaction],
                 expected_ct: usize,
                 expected_txs: usize,
             ) {
                 let mut state = new_node_state().await;
+                state.handle_signed_block(&craft_signed_block(
+                    1,
+                    vec![make_register_contract_tx("wallet".into()).into()],
+                ));
+
                 let mut txs = txs
                     .iter()
-                    .map(|tx| (*tx).clone().into())
+                    .flat_map(|tx| {
+                        let owner_proof = owner_proofs
+                            .iter()
+                            .find(|p| p.proven_blobs.iter().any(|b| b.blob_tx_hash == tx.hashed()));
+                        std::iter::once(Transaction::from((*tx).clone()))
+                            .chain(owner_proof.map(|p| Transaction::from((*p).clone())))
+                    })
                     .collect::<Vec<_>>();
                 if let Some(proofs) = proofs {
                     txs.extend(proofs.iter().map(|p| (*p).clone().into()));
                 }
-                let block = state.handle_signed_block(&craft_signed_block(1, txs));
+                let block = state.handle_signed_block(&craft_signed_block(2, txs));
 
                 assert_eq!(state.contracts.len(), expected_ct);
                 assert_eq!(block.successful_txs.len(), expected_txs);
@@ -2266,23 +2922,32 @@ pub mod test {
             }
 
             // Test all combinations
-            test_combination(None, &[&register_tx], 2, 1).await;
-            test_combination(None, &[&delete_tx], 1, 0).await;
-            test_combination(None, &[&register_tx, &delete_tx], 1, 2).await;
-            test_combination(Some(&[&proof_update]), &[&register_tx, &update_tx], 2, 2).await;
+            test_combination(owner_proofs, None, &[&register_tx], 3, 1).await;
+            test_combination(owner_proofs, None, &[&delete_tx], 2, 0).await;
+            test_combination(owner_proofs, None, &[&register_tx, &delete_tx], 2, 2).await;
+            test_combination(
+                owner_proofs,
+                Some(&[&proof_update]),
+                &[&register_tx, &update_tx],
+                3,
+                2,
+            )
+            .await;
             // TODO: This actually deletes right away before update is settled, as it isn't blocked.
             // This is arguably a bug and should be fixed.
             test_combination(
+                owner_proofs,
                 Some(&[&proof_update]),
                 &[&register_tx, &update_tx, &delete_tx],
-                1,
+                2,
                 2,
             )
             .await;
             test_combination(
+                owner_proofs,
                 Some(&[&proof_update, &proof_delete]),
                 &[&register_tx, &update_tx, &delete_self_tx],
-                1,
+                2,
                 3,
             )
             .await;
@@ -1,35 +1,160 @@
-use crate::model::contract_registration::validate_contract_registration_metadata;
+use crate::model::contract_registration::{
+    validate_contract_program_update, validate_contract_registration_metadata,
+};
+use crate::model::verifiers::NativeVerifiers;
 use crate::model::*;
 use anyhow::{bail, Result};
 use std::collections::{BTreeMap, HashMap};
 
-use super::SideEffect;
+use super::{current_contract, SideEffect};
 
+/// Handles a blob of the 'hyle' TLD.
+/// Apart from identities of the 'hyle' contract itself, the identity of `tx` is proven by a blob
+/// of its identity contract, which settles along with this one, so it can be used to check
+/// ownership, see [`owner_from_identity`].
+/// `tld_admin` can delete or update the contracts that have no owner.
 pub fn handle_blob_for_hyle_tld(
     contracts: &HashMap<ContractName, Contract>,
     contract_changes: &mut BTreeMap<ContractName, SideEffect>,
+    tld_admin: Option<&Identity>,
+    tx: &UnsettledBlobTransaction,
     current_blob: &Blob,
 ) -> Result<()> {
-    // TODO: check the identity of the caller here.
-
     // TODO: support unstructured blobs as well ?
     if let Ok(reg) =
         StructuredBlobData::<RegisterContractAction>::try_from(current_blob.data.clone())
     {
-        handle_register_blob(contracts, contract_changes, &reg.parameters)?;
+        handle_register_blob(contracts, contract_changes, &tx.identity, &reg.parameters)?;
     } else if let Ok(reg) =
         StructuredBlobData::<DeleteContractAction>::try_from(current_blob.data.clone())
     {
-        handle_delete_blob(contracts, contract_changes, &reg.parameters)?;
+        handle_delete_blob(
+            contracts,
+            contract_changes,
+            tld_admin,
+            &tx.identity,
+            &reg.parameters,
+        )?;
+    } else if let Ok(reg) =
+        StructuredBlobData::<UpdateContractProgramAction>::try_from(current_blob.data.clone())
+    {
+        handle_update_program_blob(
+            contracts,
+            contract_changes,
+            tld_admin,
+            &tx.identity,
+            &reg.parameters,
+        )?;
+    } else if let Ok(set) =
+        StructuredBlobData::<SetConsensusParamsAction>::try_from(current_blob.data.clone())
+    {
+        handle_set_consensus_params_blob(tx, &set.parameters)?;
+    } else if let Ok(reg) =
+        StructuredBlobData::<SetTldAdminAction>::try_from(current_blob.data.clone())
+    {
+        handle_set_tld_admin_blob(contracts, contract_changes, tld_admin, tx, &reg.parameters)?;
     } else {
         bail!("Invalid blob data for TLD");
     }
     Ok(())
 }
 
+/// Returns the owner recorded for contracts registered by `identity`.
+/// Identities of the 'hyle' contract are not proven by any blob, and the ones of native verifier
+/// contracts (e.g. "bob.ed25519") are not bound to the key that signed their blob, so neither can
+/// own contracts or act as TLD admin.
+fn owner_from_identity(
+    contracts: &HashMap<ContractName, Contract>,
+    contract_changes: &BTreeMap<ContractName, SideEffect>,
+    identity: &Identity,
+) -> Option<Identity> {
+    let (_, identity_contract) = identity.0.split_once('.')?;
+    if identity_contract == "hyle" {
+        return None;
+    }
+    match current_contract(contracts, contract_changes, &identity_contract.into()) {
+        Some(contract) if NativeVerifiers::try_from(&contract.verifier).is_ok() => None,
+        _ => Some(identity.clone()),
+    }
+}
+
+fn check_owner(
+    contracts: &HashMap<ContractName, Contract>,
+    contract_changes: &BTreeMap<ContractName, SideEffect>,
+    contract: &Contract,
+    tld_admin: Option<&Identity>,
+    identity: &Identity,
+) -> Result<()> {
+    let identity_owner = owner_from_identity(contracts, contract_changes, identity);
+    match &contract.owner {
+        Some(owner) if identity_owner.as_ref() == Some(owner) => Ok(()),
+        Some(owner) => bail!(
+            "Contract {} is owned by {}, not by {}",
+            contract.name.0,
+            owner.0,
+            identity.0
+        ),
+        None if tld_admin.is_some() && identity_owner.as_ref() == tld_admin => Ok(()),
+        None => bail!(
+            "Contract {} has no owner, it can only be changed by its own proofs or the TLD admin",
+            contract.name.0
+        ),
+    }
+}
+
+/// The new admin is taken into account by [`NodeState`](super::NodeState) once the transaction
+/// settles.
+fn handle_set_tld_admin_blob(
+    contracts: &HashMap<ContractName, Contract>,
+    contract_changes: &BTreeMap<ContractName, SideEffect>,
+    tld_admin: Option<&Identity>,
+    tx: &UnsettledBlobTransaction,
+    set: &SetTldAdminAction,
+) -> Result<()> {
+    if let Some(new_admin) = &set.tld_admin {
+        if owner_from_identity(contracts, contract_changes, new_admin).is_none() {
+            bail!("{} cannot be the TLD admin", new_admin.0);
+        }
+    }
+    // Anyone can send transactions as 'hyle.hyle', only the genesis block is trusted for them
+    if tx.tx_context.block_height == BlockHeight(0) {
+        return Ok(());
+    }
+    let identity_owner = owner_from_identity(contracts, contract_changes, &tx.identity);
+    if tld_admin.is_none() || identity_owner.as_ref() != tld_admin {
+        bail!(
+            "Only the genesis block or the TLD admin can set the TLD admin, not {}",
+            tx.identity.0
+        );
+    }
+    Ok(())
+}
+
+/// Consensus parameters are applied by all validators, so they can't change once the chain runs.
+/// The consensus takes them from the settled block.
+fn handle_set_consensus_params_blob(
+    tx: &UnsettledBlobTransaction,
+    set: &SetConsensusParamsAction,
+) -> Result<()> {
+    if tx.tx_context.block_height != BlockHeight(0) {
+        bail!(
+            "Consensus parameters {:?} can only be set in the genesis block",
+            set.params
+        );
+    }
+    if set.params.fee_burn_percent > 100 {
+        bail!(
+            "Fee burn percentage {} is above 100",
+            set.params.fee_burn_percent
+        );
+    }
+    Ok(())
+}
+
 fn handle_register_blob(
     contracts: &HashMap<ContractName, Contract>,
     contract_changes: &mut BTreeMap<ContractName, SideEffect>,
+    identity: &Identity,
     reg: &RegisterContractAction,
 ) -> Result<()> {
     // Check name, it's either a direct subdomain or a TLD
@@ -55,6 +180,8 @@ fn handle_register_blob(
             program_id: reg.program_id.clone(),
             state: reg.state_digest.clone(),
             verifier: reg.verifier.clone(),
+            timeout_window: reg.timeout_window,
+            owner: owner_from_identity(contracts, contract_changes, identity),
         }),
     );
     Ok(())
@@ -63,23 +190,57 @@ fn handle_register_blob(
 fn handle_delete_blob(
     contracts: &HashMap<ContractName, Contract>,
     contract_changes: &mut BTreeMap<ContractName, SideEffect>,
+    tld_admin: Option<&Identity>,
+    identity: &Identity,
     delete: &DeleteContractAction,
 ) -> Result<()> {
-    // For now, Hylé is allowed to delete all contracts but itself
     if delete.contract_name.0 == "hyle" {
         bail!("Cannot delete Hylé contract");
     }
 
     // Check it's registered
-    if contracts.contains_key(&delete.contract_name)
-        || contract_changes.contains_key(&delete.contract_name)
-    {
-        contract_changes.insert(
-            delete.contract_name.clone(),
-            SideEffect::Delete(delete.contract_name.clone()),
-        );
-        Ok(())
-    } else {
-        bail!("Contract {} is already registered", delete.contract_name.0);
-    }
+    let Some(contract) = current_contract(contracts, contract_changes, &delete.contract_name)
+    else {
+        bail!("Contract {} is not registered", delete.contract_name.0);
+    };
+    check_owner(contracts, contract_changes, contract, tld_admin, identity)?;
+
+    contract_changes.insert(
+        delete.contract_name.clone(),
+        SideEffect::Delete(delete.contract_name.clone()),
+    );
+    Ok(())
+}
+
+fn handle_update_program_blob(
+    contracts: &HashMap<ContractName, Contract>,
+    contract_changes: &mut BTreeMap<ContractName, SideEffect>,
+    tld_admin: Option<&Identity>,
+    identity: &Identity,
+    update: &UpdateContractProgramAction,
+) -> Result<()> {
+    // Hylé can update the programs of the TLDs and of its direct subdomains, like it registers them
+    validate_contract_program_update(
+        &"hyle".into(),
+        &update.contract_name,
+        &update.verifier,
+        &update.program_id,
+    )?;
+
+    let Some(contract) = current_contract(contracts, contract_changes, &update.contract_name)
+    else {
+        bail!("Contract {} is not registered", update.contract_name.0);
+    };
+    check_owner(contracts, contract_changes, contract, tld_admin, identity)?;
+
+    let side_effect = SideEffect::UpdateProgram(Contract {
+        verifier: update.verifier.clone(),
+        program_id: update.program_id.clone(),
+        ..contract.clone()
+    });
+    contract_changes
+        .entry(update.contract_name.clone())
+        .and_modify(|c| c.apply(side_effect.clone()))
+        .or_insert(side_effect);
+    Ok(())
 }
@@ -0,0 +1,200 @@
+//! Decoding of `node_state.bin` files written before contracts declared a timeout window
+//! and recorded their owner.
+//!
+//! These files have no version header: [`NodeStateV0`] mirrors their layout, and is only tried
+//! when the current layout fails to decode.
+
+use borsh::BorshDeserialize;
+use hyle_model::{
+    Blob, BlobsHash, BlockHeight, Contract, ContractName, DataProposalHash, Identity, ProgramId,
+    StateDigest, TxContext, TxHash, UnsettledBlobMetadata, UnsettledBlobTransaction, Verifier,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+
+use super::{ordered_tx_map::OrderedTxMap, timeouts::Timeouts, NodeState};
+use crate::model::migration::HyleOutputV0;
+
+#[derive(BorshDeserialize)]
+pub struct NodeStateV0 {
+    timeouts: TimeoutsV0,
+    current_height: BlockHeight,
+    contracts: HashMap<ContractName, ContractV0>,
+    unsettled_transactions: OrderedTxMapV0,
+}
+
+#[derive(BorshDeserialize)]
+struct TimeoutsV0 {
+    timeout_window: BlockHeight,
+    by_block: HashMap<BlockHeight, Vec<TxHash>>,
+}
+
+#[derive(BorshDeserialize)]
+struct ContractV0 {
+    name: ContractName,
+    program_id: ProgramId,
+    state: StateDigest,
+    verifier: Verifier,
+}
+
+#[derive(BorshDeserialize)]
+struct OrderedTxMapV0 {
+    map: HashMap<TxHash, UnsettledBlobTransactionV0>,
+    tx_order: HashMap<ContractName, VecDeque<TxHash>>,
+}
+
+#[derive(BorshDeserialize)]
+struct UnsettledBlobTransactionV0 {
+    identity: Identity,
+    parent_dp_hash: DataProposalHash,
+    hash: TxHash,
+    tx_context: Arc<TxContext>,
+    blobs_hash: BlobsHash,
+    blobs: Vec<UnsettledBlobMetadataV0>,
+}
+
+#[derive(BorshDeserialize)]
+struct UnsettledBlobMetadataV0 {
+    blob: Blob,
+    possible_proofs: Vec<(ProgramId, HyleOutputV0)>,
+}
+
+impl NodeStateV0 {
+    /// Existing contracts keep the node default timeout window and have no owner, `tld_admin` can
+    /// change them through the 'hyle' TLD.
+    /// Pending transactions keep the timeout window they were sequenced with.
+    pub fn into_current(self, tld_admin: Option<Identity>) -> NodeState {
+        let timeout_window = self.timeouts.timeout_window;
+        NodeState {
+            timeouts: Timeouts::from_by_block(self.timeouts.by_block),
+            current_height: self.current_height,
+            contracts: self
+                .contracts
+                .into_iter()
+                .map(|(name, contract)| {
+                    (
+                        name,
+                        Contract {
+                            name: contract.name,
+                            program_id: contract.program_id,
+                            state: contract.state,
+                            verifier: contract.verifier,
+                            timeout_window: None,
+                            owner: None,
+                        },
+                    )
+                })
+                .collect(),
+            unsettled_transactions: OrderedTxMap::from_parts(
+                self.unsettled_transactions
+                    .map
+                    .into_iter()
+                    .map(|(hash, tx)| (hash, tx.into_current(timeout_window)))
+                    .collect(),
+                self.unsettled_transactions.tx_order,
+            ),
+            tld_admin,
+        }
+    }
+}
+
+impl UnsettledBlobTransactionV0 {
+    fn into_current(self, timeout_window: BlockHeight) -> UnsettledBlobTransaction {
+        UnsettledBlobTransaction {
+            identity: self.identity,
+            parent_dp_hash: self.parent_dp_hash,
+            hash: self.hash,
+            tx_context: self.tx_context,
+            blobs_hash: self.blobs_hash,
+            blobs: self
+                .blobs
+                .into_iter()
+                .map(|metadata| UnsettledBlobMetadata {
+                    blob: metadata.blob,
+                    possible_proofs: metadata
+                        .possible_proofs
+                        .into_iter()
+                        .map(|(program_id, output)| (program_id, output.into()))
+                        .collect(),
+                })
+                .collect(),
+            timeout_window,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use borsh::BorshSerialize;
+    use hyle_model::HyleOutput;
+
+    use super::*;
+
+    #[derive(BorshSerialize)]
+    struct LegacyContract {
+        name: ContractName,
+        program_id: ProgramId,
+        state: StateDigest,
+        verifier: Verifier,
+    }
+
+    #[test]
+    fn test_decode_legacy_node_state() {
+        let tx_hash = TxHash::new("tx");
+        let tx_order: HashMap<ContractName, VecDeque<TxHash>> =
+            HashMap::from([("c1".into(), VecDeque::from([tx_hash.clone()]))]);
+
+        // Layout of the files written before this migration, with one pending transaction
+        let mut bytes = vec![];
+        BlockHeight(100).serialize(&mut bytes).unwrap();
+        HashMap::from([(BlockHeight(110), vec![tx_hash.clone()])])
+            .serialize(&mut bytes)
+            .unwrap();
+        BlockHeight(10).serialize(&mut bytes).unwrap();
+        HashMap::from([(
+            ContractName::from("c1"),
+            LegacyContract {
+                name: "c1".into(),
+                program_id: ProgramId(vec![1]),
+                state: StateDigest(vec![2]),
+                verifier: Verifier("test".into()),
+            },
+        )])
+        .serialize(&mut bytes)
+        .unwrap();
+        1u32.serialize(&mut bytes).unwrap();
+        tx_hash.serialize(&mut bytes).unwrap();
+        Identity::new("id").serialize(&mut bytes).unwrap();
+        DataProposalHash::default().serialize(&mut bytes).unwrap();
+        tx_hash.serialize(&mut bytes).unwrap();
+        Arc::new(TxContext::default())
+            .serialize(&mut bytes)
+            .unwrap();
+        BlobsHash::default().serialize(&mut bytes).unwrap();
+        vec![(Blob::default(), Vec::<(ProgramId, HyleOutput)>::new())]
+            .serialize(&mut bytes)
+            .unwrap();
+        tx_order.serialize(&mut bytes).unwrap();
+
+        let state: NodeState = borsh::from_slice::<NodeStateV0>(&bytes)
+            .unwrap()
+            .into_current(Some("admin.wallet".into()));
+
+        assert_eq!(state.current_height, BlockHeight(10));
+        let contract = state.contracts.get(&"c1".into()).unwrap();
+        assert_eq!(contract.program_id, ProgramId(vec![1]));
+        assert_eq!(contract.timeout_window, None);
+        assert_eq!(contract.owner, None);
+        assert_eq!(state.tld_admin, Some("admin.wallet".into()));
+        let tx = state.unsettled_transactions.get(&tx_hash).unwrap();
+        assert_eq!(tx.timeout_window, BlockHeight(100));
+        assert_eq!(
+            state
+                .unsettled_transactions
+                .get_next_unsettled_tx(&"c1".into()),
+            Some(&tx_hash)
+        );
+    }
+}
@@ -1,10 +1,10 @@
 //! State required for participation in consensus by the node.
 
-use super::NodeState;
+use super::{migration::NodeStateV0, NodeState};
 use crate::bus::{command_response::Query, BusClientSender, BusMessage};
 use crate::data_availability::DataEvent;
 use crate::model::Contract;
-use crate::model::{Block, BlockHeight, CommonRunContext, ContractName};
+use crate::model::{Block, BlockHeight, CommonRunContext, ContractName, Identity};
 use crate::module_handle_messages;
 use crate::utils::conf::SharedConf;
 use crate::utils::logger::LogMe;
@@ -62,9 +62,23 @@ impl Module for NodeStateModule {
             }
         }
 
-        let storage = Self::load_from_disk_or_default::<NodeState>(
-            ctx.config.data_directory.join("node_state.bin").as_path(),
-        );
+        let file = ctx.config.data_directory.join("node_state.bin");
+        let storage = match Self::load_from_disk::<NodeState>(file.as_path()) {
+            Some(storage) => storage,
+            None => match Self::load_from_disk::<NodeStateV0>(file.as_path()) {
+                Some(legacy) => {
+                    info!("Migrated node state from the layout without contract timeout windows and owners");
+                    legacy.into_current(
+                        ctx.config
+                            .consensus
+                            .genesis_tld_admin
+                            .clone()
+                            .map(Identity::new),
+                    )
+                }
+                None => NodeState::default(),
+            },
+        };
 
         for name in storage.contracts.keys() {
             info!("📝 Loaded contract state for {}", name);
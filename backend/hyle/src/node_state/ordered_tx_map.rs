@@ -15,6 +15,13 @@ pub struct OrderedTxMap {
 }
 
 impl OrderedTxMap {
+    pub(super) fn from_parts(
+        map: HashMap<TxHash, UnsettledBlobTransaction>,
+        tx_order: HashMap<ContractName, VecDeque<TxHash>>,
+    ) -> Self {
+        OrderedTxMap { map, tx_order }
+    }
+
     #[allow(dead_code)]
     pub fn get(&self, hash: &TxHash) -> Option<&UnsettledBlobTransaction> {
         self.map.get(hash)
@@ -142,6 +149,7 @@ mod tests {
                 possible_proofs: vec![],
             }],
             tx_context: Arc::new(TxContext::default()),
+            timeout_window: Default::default(),
         }
     }
 
@@ -7,7 +7,7 @@ use tracing::info;
 use crate::{
     bus::{bus_client, BusClientReceiver, BusClientSender},
     mempool::api::RestApiMessage,
-    model::verifiers::{BlstSignatureBlob, ShaBlob},
+    model::verifiers::{BlstSignatureBlob, Ed25519SignatureBlob, Secp256k1SignatureBlob, ShaBlob},
     rest::RestApi,
     utils::{crypto::BlstCrypto, integration_test::NodeIntegrationCtxBuilder},
 };
@@ -66,6 +66,60 @@ async fn test_sha3_256_native_verifier() {
     assert_ok!(res);
 }
 
+#[test_log::test(tokio::test)]
+async fn test_ed25519_native_verifier() {
+    use ed25519_dalek::Signer;
+
+    let contract_name: ContractName = "ed25519".into();
+    let identity: Identity = format!("bob.{contract_name}").into();
+
+    let signing_key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+
+    let data = vec![1, 2, 3, 4, 5, 6];
+
+    let signature = signing_key.sign(&[data.clone(), identity.0.as_bytes().to_vec()].concat());
+
+    let blob = Ed25519SignatureBlob {
+        identity: identity.clone(),
+        data,
+        signature: signature.to_bytes().to_vec(),
+        public_key: signing_key.verifying_key().to_bytes().to_vec(),
+    };
+
+    let res = scenario(identity, blob.as_blob()).await;
+    assert_ok!(res);
+}
+
+#[test_log::test(tokio::test)]
+async fn test_secp256k1_native_verifier() {
+    use k256::ecdsa::signature::Signer;
+
+    let contract_name: ContractName = "secp256k1".into();
+    let identity: Identity = format!("bob.{contract_name}").into();
+
+    let signing_key = k256::ecdsa::SigningKey::from_slice(&[7; 32]).expect("signing key");
+
+    let data = vec![1, 2, 3, 4, 5, 6];
+
+    // k256 always produces low-s signatures
+    let signature: k256::ecdsa::Signature =
+        signing_key.sign(&[data.clone(), identity.0.as_bytes().to_vec()].concat());
+
+    let blob = Secp256k1SignatureBlob {
+        identity: identity.clone(),
+        data,
+        signature: signature.to_bytes().to_vec(),
+        public_key: signing_key
+            .verifying_key()
+            .to_encoded_point(true)
+            .as_bytes()
+            .to_vec(),
+    };
+
+    let res = scenario(identity, blob.as_blob()).await;
+    assert_ok!(res);
+}
+
 async fn scenario(identity: Identity, blob: Blob) -> Result<()> {
     let mut node_modules = NodeIntegrationCtxBuilder::new().await;
     node_modules.conf.consensus.slot_duration = 200;
@@ -4,31 +4,47 @@ use crate::model::BlockHeight;
 use borsh::{BorshDeserialize, BorshSerialize};
 use hyle_model::TxHash;
 
-#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+/// Number of blocks blob transactions have to settle before timing out, for the contracts
+/// that did not declare a window at registration.
+/// These decide which transactions time out, so they are part of the protocol.
+pub const DEFAULT_TIMEOUT_WINDOW: BlockHeight = BlockHeight(100);
+/// Bounds of the windows declared by contracts
+pub const MIN_TIMEOUT_WINDOW: BlockHeight = BlockHeight(10);
+pub const MAX_TIMEOUT_WINDOW: BlockHeight = BlockHeight(10_000);
+
+#[derive(Debug, Clone, Default, BorshSerialize, BorshDeserialize)]
 pub struct Timeouts {
-    timeout_window: BlockHeight,
     by_block: HashMap<BlockHeight, Vec<TxHash>>,
 }
 
-impl Default for Timeouts {
-    fn default() -> Self {
-        Timeouts {
-            timeout_window: BlockHeight(100),
-            by_block: HashMap::new(),
-        }
+impl Timeouts {
+    pub(super) fn from_by_block(by_block: HashMap<BlockHeight, Vec<TxHash>>) -> Self {
+        Timeouts { by_block }
+    }
+
+    /// Timeout window of a transaction, given the windows declared by the contracts of its blobs.
+    /// The transaction needs a proof for each of them, so it gets the longest window.
+    pub fn window(&self, declared: impl IntoIterator<Item = Option<BlockHeight>>) -> BlockHeight {
+        declared
+            .into_iter()
+            .map(|window| {
+                window
+                    .unwrap_or(DEFAULT_TIMEOUT_WINDOW)
+                    .clamp(MIN_TIMEOUT_WINDOW, MAX_TIMEOUT_WINDOW)
+            })
+            .max()
+            .unwrap_or(DEFAULT_TIMEOUT_WINDOW)
     }
-}
 
-impl Timeouts {
     pub fn drop(&mut self, at: &BlockHeight) -> Vec<TxHash> {
         self.by_block.remove(at).unwrap_or_default()
     }
 
-    /// Set timeout for a tx.
+    /// Set timeout for a tx, `timeout_window` blocks after `block_height`.
     /// This does not check if the TX is already set to timeout at a different (or same) block.
-    pub fn set(&mut self, tx: TxHash, block_height: BlockHeight) {
+    pub fn set(&mut self, tx: TxHash, block_height: BlockHeight, timeout_window: BlockHeight) {
         self.by_block
-            .entry(block_height + self.timeout_window)
+            .entry(block_height + timeout_window)
             .or_default()
             .push(tx);
     }
@@ -54,8 +70,8 @@ pub mod tests {
         })
     }
 
-    pub fn get_timeout_window(t: &Timeouts) -> BlockHeight {
-        t.timeout_window
+    pub fn get_timeout_window(_t: &Timeouts) -> BlockHeight {
+        DEFAULT_TIMEOUT_WINDOW
     }
 
     #[test]
@@ -64,24 +80,41 @@ pub mod tests {
         let b1 = BlockHeight(0);
         let b2 = BlockHeight(1);
         let tx1 = TxHash::new("tx1");
+        let window = get_timeout_window(&t);
 
-        t.set(tx1.clone(), b1);
+        t.set(tx1.clone(), b1, window);
 
-        assert_eq!(list_timeouts(&t, b1 + t.timeout_window).unwrap().len(), 1);
-        assert_eq!(list_timeouts(&t, b2 + t.timeout_window), None);
-        assert_eq!(get(&t, &tx1), Some(b1 + t.timeout_window));
+        assert_eq!(list_timeouts(&t, b1 + window).unwrap().len(), 1);
+        assert_eq!(list_timeouts(&t, b2 + window), None);
+        assert_eq!(get(&t, &tx1), Some(b1 + window));
 
-        t.set(tx1.clone(), b2);
+        t.set(tx1.clone(), b2, window);
 
-        assert_eq!(t.drop(&(b1 + t.timeout_window)), vec![tx1.clone()]);
+        assert_eq!(t.drop(&(b1 + window)), vec![tx1.clone()]);
 
         // Now this returns b2
-        assert_eq!(get(&t, &tx1), Some(b2 + t.timeout_window));
-        assert_eq!(list_timeouts(&t, b1 + t.timeout_window), None);
-        assert_eq!(list_timeouts(&t, b2 + t.timeout_window).unwrap().len(), 1);
+        assert_eq!(get(&t, &tx1), Some(b2 + window));
+        assert_eq!(list_timeouts(&t, b1 + window), None);
+        assert_eq!(list_timeouts(&t, b2 + window).unwrap().len(), 1);
 
-        assert_eq!(t.drop(&(b2 + t.timeout_window)), vec![tx1.clone()]);
+        assert_eq!(t.drop(&(b2 + window)), vec![tx1.clone()]);
         assert_eq!(get(&t, &tx1), None);
-        assert_eq!(list_timeouts(&t, b2 + t.timeout_window), None);
+        assert_eq!(list_timeouts(&t, b2 + window), None);
+    }
+
+    #[test]
+    fn timeout_window() {
+        let t = Timeouts::default();
+
+        assert_eq!(t.window([None]), BlockHeight(100));
+        assert_eq!(t.window([Some(BlockHeight(20))]), BlockHeight(20));
+        // Declared windows are kept within the bounds
+        assert_eq!(t.window([Some(BlockHeight(1))]), BlockHeight(10));
+        assert_eq!(t.window([Some(BlockHeight(50_000))]), BlockHeight(10_000));
+        // The longest window of the contracts applies
+        assert_eq!(
+            t.window([Some(BlockHeight(20)), None, Some(BlockHeight(300))]),
+            BlockHeight(300)
+        );
     }
 }
@@ -9,6 +9,10 @@ use std::collections::HashSet;
 use std::fmt::{self, Display};
 use strum_macros::IntoStaticStr;
 
+/// Version of the messages exchanged between peers, nodes refuse peers on another version.
+/// 2: consensus proposals carry the seed signature of their leader.
+pub const PROTOCOL_VERSION: u16 = 2;
+
 #[derive(Debug, Serialize, Deserialize, Clone, BorshSerialize, BorshDeserialize, Eq, PartialEq)]
 pub struct Hello {
     pub version: u16,
@@ -2,7 +2,7 @@ use std::time::Duration;
 use std::time::SystemTime;
 
 use anyhow::Context;
-use anyhow::{Error, Result};
+use anyhow::{bail, Error, Result};
 use tokio::net::TcpStream;
 use tokio::sync::mpsc;
 use tokio::time::sleep;
@@ -14,7 +14,7 @@ use super::fifo_filter::FifoFilter;
 use super::network::HandshakeNetMessage;
 use super::network::OutboundMessage;
 use super::network::PeerEvent;
-use super::network::{Hello, NetMessage};
+use super::network::{Hello, NetMessage, PROTOCOL_VERSION};
 use super::stream::send_net_message;
 use crate::bus::bus_client;
 use crate::bus::BusClientSender;
@@ -123,6 +123,14 @@ impl Peer {
         match msg {
             HandshakeNetMessage::Hello(v) => {
                 info!("👋 Got peer hello message {:?}", v);
+                if v.version != PROTOCOL_VERSION {
+                    bail!(
+                        "Peer {} uses protocol version {}, expected {}",
+                        v.name,
+                        v.version,
+                        PROTOCOL_VERSION
+                    );
+                }
                 self.peer_pubkey = Some(v.validator_pubkey);
                 self.peer_name = Some(v.name);
                 self.peer_da_address = Some(v.da_address);
@@ -259,7 +267,7 @@ impl Peer {
         send_net_message(
             &mut self.stream,
             HandshakeNetMessage::Hello(Hello {
-                version: 1,
+                version: PROTOCOL_VERSION,
                 validator_pubkey: self.self_pubkey.clone(),
                 name: self.conf.id.clone(),
                 da_address: self.conf.da_address.clone(),
@@ -15,6 +15,7 @@ use axum_otel_metrics::HttpMetricsLayer;
 use hyle_model::api::*;
 use hyle_model::*;
 use prometheus::{Encoder, TextEncoder};
+use std::net::SocketAddr;
 use tokio::time::Instant;
 use tracing::info;
 use utoipa::OpenApi;
@@ -148,6 +149,7 @@ impl RestApi {
                     .context("Starting rest server")?,
                 #[allow(clippy::expect_used, reason="incorrect setup logic")]
                 self.app.take().expect("app is not set")
+                    .into_make_service_with_connect_info::<SocketAddr>()
             ) => { }
         };
 
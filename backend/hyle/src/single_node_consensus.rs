@@ -185,6 +185,7 @@ impl SingleNodeConsensus {
             cut: self.store.last_cut.clone(),
             staking_actions: vec![],
             parent_hash: std::mem::take(&mut self.store.last_consensus_proposal_hash),
+            seed_signature: Signature::default(),
         };
 
         self.store.last_consensus_proposal_hash = consensus_proposal.hashed();
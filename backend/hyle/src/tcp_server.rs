@@ -1,6 +1,7 @@
 use crate::{
-    bus::{BusClientSender, BusMessage},
-    model::SharedRunContext,
+    bus::command_response::{CmdRespClient, Query},
+    mempool::admission::SubmitTx,
+    model::{SharedRunContext, TxHash},
     module_handle_messages,
     utils::{
         conf::SharedConf,
@@ -10,15 +11,21 @@ use crate::{
 };
 
 use anyhow::{Context, Result};
-use client_sdk::tcp::{codec_tcp_server, TcpServerMessage};
-use tracing::info;
+use client_sdk::tcp::{
+    codec_tcp_server, TcpCommand, TcpServerMessage, TcpServerResponse, TcpTxAnswer,
+};
+use hyle_model::api::APITxRejection;
+use tokio::task::JoinSet;
+use tracing::{info, warn};
 
-impl BusMessage for TcpServerMessage {}
+/// Number of transactions submitted to the mempool and not answered yet,
+/// beyond which the TcpServer stops reading new ones
+const MAX_PENDING_SUBMISSIONS: usize = 1000;
 
 module_bus_client! {
 #[derive(Debug)]
 struct TcpServerBusClient {
-    sender(TcpServerMessage),
+    sender(Query<SubmitTx, Result<TxHash, APITxRejection>>),
 }
 }
 
@@ -53,7 +60,7 @@ impl TcpServer {
             .as_ref()
             .context("tcp_server_address not specified in conf file. Not Starting module.")?;
 
-        let (_, mut receiver) = codec_tcp_server::create_server(tcp_server_address.clone())
+        let (sender, mut receiver) = codec_tcp_server::create_server(tcp_server_address.clone())
             .run_in_background()
             .await?;
 
@@ -62,10 +69,38 @@ impl TcpServer {
             tcp_server_address
         );
 
+        let mut pending_submissions = JoinSet::new();
+
         module_handle_messages! {
             on_bus self.bus,
-            Some(res) = receiver.recv() => {
-                _ = self.bus.send(*res.data).log_error("Sending message on TcpServerMessage topic from connection pool");
+            Some(res) = receiver.recv(), if pending_submissions.len() < MAX_PENDING_SUBMISSIONS => {
+                // Transactions are submitted in the order they were sent, their answers are awaited concurrently
+                let TcpServerMessage::NewTx(tx) = *res.data;
+                let submit = SubmitTx {
+                    tx,
+                    sender: Some(res.dest.clone()),
+                };
+                let answer = self.bus.send_request(submit);
+                pending_submissions.spawn(async move { (res.dest, answer.await) });
+            }
+            Some(submitted) = pending_submissions.join_next() => {
+                let Ok((dest, answer)) = submitted.log_error("Awaiting transaction submission") else {
+                    continue;
+                };
+                let answer = match answer {
+                    Ok(Ok(tx_hash)) => TcpTxAnswer::Accepted(tx_hash),
+                    Ok(Err(rejection)) => TcpTxAnswer::Rejected(rejection),
+                    Err(e) => {
+                        warn!("Submitting transaction from the connection pool: {:#}", e);
+                        continue;
+                    }
+                };
+                if let Ok(response) = TcpServerResponse::new(&answer).log_error("Encoding transaction answer") {
+                    _ = sender
+                        .send(TcpCommand::Send(dest, Box::new(response)))
+                        .await
+                        .log_error("Answering transaction to the connection pool");
+                }
             }
         };
 
@@ -742,7 +742,9 @@ async fn autobahn_rejoin_flow() {
         };
 
         // Swap so we handle leader changes correctly
-        std::mem::swap(&mut node1, &mut node2);
+        if node2.consensus_ctx.is_leader() {
+            std::mem::swap(&mut node1, &mut node2);
+        }
     }
 
     // Now process block 2
@@ -766,7 +768,9 @@ async fn autobahn_rejoin_flow() {
         joining: joining_node.consensus_ctx
     };
 
-    std::mem::swap(&mut node1, &mut node2);
+    if node2.consensus_ctx.is_leader() {
+        std::mem::swap(&mut node1, &mut node2);
+    }
 
     // We still aren't caught up
     assert!(joining_node.consensus_ctx.is_joining());
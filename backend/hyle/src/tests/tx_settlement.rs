@@ -31,6 +31,7 @@ fn make_register_blob_action(
             program_id: ProgramId(vec![1, 2, 3]),
             state_digest,
             contract_name,
+            timeout_window: None,
         }
         .as_blob("hyle".into(), None, None)],
     )
@@ -69,6 +70,7 @@ async fn test_full_settlement_flow() -> Result<()> {
             program_id: ProgramId(vec![1, 2, 3]),
             state_digest: StateDigest(vec![7, 7, 7]),
             contract_name: "c2.hyle".into(),
+            timeout_window: None,
         })
         .await
         .unwrap();
@@ -186,6 +188,7 @@ async fn test_tx_settlement_duplicates() -> Result<()> {
             program_id: ProgramId(vec![1, 2, 3]),
             state_digest: StateDigest(vec![7, 7, 7]),
             contract_name: "c2.hyle".into(),
+            timeout_window: None,
         })
         .await
         .unwrap();
@@ -382,6 +385,7 @@ async fn test_contract_upgrade() -> Result<()> {
             // The state digest is ignored during the update phase.
             state_digest: StateDigest(vec![3, 3, 3]),
             contract_name: "c1.hyle".into(),
+            timeout_window: None,
         }));
 
     let proof_update = ProofTransaction {
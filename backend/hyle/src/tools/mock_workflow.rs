@@ -169,6 +169,7 @@ impl MockWorkflowHandler {
                 program_id: ProgramId(vec![]),
                 state_digest: StateDigest(vec![]),
                 contract_name: ContractName::new("contract"),
+                timeout_window: None,
             }
             .as_blob("hyle".into(), None, None)],
         );
@@ -3,6 +3,8 @@ use config::{Config, Environment, File};
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, fmt::Debug, path::PathBuf, sync::Arc};
 
+use crate::model::ConsensusParams;
+
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct Storage {
     pub interval: u64,
@@ -12,12 +14,81 @@ pub struct Storage {
 pub struct Consensus {
     pub slot_duration: u64,
     pub genesis_stakers: HashMap<String, u64>,
+    pub jailing: JailingConf,
+    /// TLD admin set in the genesis block, also used when migrating node states written before
+    /// contracts recorded their owner. All genesis nodes need the same value.
+    pub genesis_tld_admin: Option<String>,
+    /// Consensus parameters set in the genesis block, the other nodes take them from the chain
+    pub genesis_params: ConsensusParams,
+}
+
+/// Removal from the consensus of the validators that stop voting.
+/// Jailing decisions are checked by the other validators, so all nodes need the same values.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JailingConf {
+    pub enabled: bool,
+    /// Number of consecutive views led by a validator that can end with a timeout certificate
+    /// before it is jailed
+    pub missed_views: u64,
+}
+
+impl Default for JailingConf {
+    fn default() -> Self {
+        JailingConf {
+            enabled: false,
+            missed_views: 10,
+        }
+    }
+}
+
+/// Admission and verification of the transactions received by the node
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MempoolConf {
+    /// Maximum number of blobs of a blob transaction
+    pub max_blobs_per_tx: usize,
+    /// Maximum size of the data of a blob, in bytes
+    pub max_blob_size: usize,
+    /// Maximum size of a blob transaction, in bytes
+    pub max_tx_size: usize,
+    /// Number of recently admitted transaction hashes kept to refuse duplicates
+    pub recent_tx_hashes: usize,
+    /// Number of proofs verified in parallel
+    pub verification_workers: usize,
+    /// Number of proofs waiting for verification beyond which new proofs are refused
+    pub verification_queue_size: usize,
+    /// Number of proofs of a single contract, or sent from a single address, waiting for verification
+    /// beyond which new proofs of this contract or from this address are refused
+    pub verification_queue_size_per_key: usize,
+    /// Minimum fees a blob transaction pays per byte, with a `PayTxFees` staking blob
+    pub min_fee_per_byte: u64,
+}
+
+impl Default for MempoolConf {
+    fn default() -> Self {
+        MempoolConf {
+            max_blobs_per_tx: 100,
+            max_blob_size: 1_048_576,
+            max_tx_size: 4_194_304,
+            recent_tx_hashes: 10_000,
+            verification_workers: 4,
+            verification_queue_size: 1000,
+            verification_queue_size_per_key: 100,
+            min_fee_per_byte: 0,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct P2pConf {
     pub ping_interval: u64,
 }
+/// A contract indexed by a contract state indexer, and the name of its registered handler
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ContractIndexerConf {
+    pub contract_name: String,
+    pub handler: String,
+}
+
 pub type SharedConf = Arc<Conf>;
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
@@ -28,12 +99,14 @@ pub struct Conf {
     pub peers: Vec<String>,
     pub storage: Storage,
     pub consensus: Consensus,
+    pub mempool: MempoolConf,
     pub rest: String,
     pub rest_max_body_size: usize,
     pub database_url: String,
     pub p2p: P2pConf,
     pub data_directory: PathBuf,
     pub run_indexer: bool,
+    pub contract_indexers: Vec<ContractIndexerConf>,
     pub run_tcp_server: bool,
     pub da_address: String,
     pub tcp_server_address: Option<String>,
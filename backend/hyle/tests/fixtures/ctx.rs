@@ -296,6 +296,7 @@ impl E2ECtx {
             program_id: Contract::program_id(),
             state_digest: Contract::state_digest(),
             contract_name: name.into(),
+            timeout_window: None,
         }
         .as_blob("hyle".into(), None, None)];
 
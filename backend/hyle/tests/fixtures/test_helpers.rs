@@ -5,10 +5,7 @@ use client_sdk::transaction_builder::{ProvableBlobTx, StateUpdater, TxExecutor};
 use hyle::{
     model::BlobTransaction,
     rest::client::NodeApiHttpClient,
-    utils::{
-        conf::{Conf, Consensus},
-        crypto::BlstCrypto,
-    },
+    utils::{conf::Conf, crypto::BlstCrypto},
 };
 use hyle_model::TxHash;
 use rand::Rng;
@@ -56,14 +53,12 @@ impl Default for ConfMaker {
         default.run_indexer = false; // disable indexer by default to avoid needed PG
         default.log_format = "node".to_string(); // Activate node name in logs for convenience in tests.
         info!("Default conf: {:?}", default);
-        default.consensus = Consensus {
-            slot_duration: 1,
-            genesis_stakers: {
-                let mut stakers = std::collections::HashMap::new();
-                stakers.insert("node-1".to_owned(), 100);
-                stakers.insert("node-2".to_owned(), 100);
-                stakers
-            },
+        default.consensus.slot_duration = 1;
+        default.consensus.genesis_stakers = {
+            let mut stakers = std::collections::HashMap::new();
+            stakers.insert("node-1".to_owned(), 100);
+            stakers.insert("node-2".to_owned(), 100);
+            stakers
         };
         default.faucet_password = "password".into();
         info!("Default conf: {:?}", default);
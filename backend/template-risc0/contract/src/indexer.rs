@@ -0,0 +1,414 @@
+use anyhow::{anyhow, Context, Result};
+use borsh::{BorshDeserialize, BorshSerialize};
+use client_sdk::contract_indexer::{
+    axum::{
+        extract::{Path, Query, State},
+        http::StatusCode,
+        response::IntoResponse,
+        Json, Router,
+    },
+    utoipa::{openapi::OpenApi, IntoParams, ToSchema},
+    utoipa_axum::{router::OpenApiRouter, routes},
+    AppError, ContractHandler, ContractHandlerStore,
+};
+use sdk::{
+    info, Blob, BlobIndex, BlobTransaction, ContractInput, Digestable, Hashed, HyleContract,
+    Identity, RunResult, StateDigest, TxContext, TxHash,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::merkle::{MerkleProof, MerkleRoot};
+use crate::store::IndexedReviews;
+use crate::visit::{Nullifier, SignatureScheme};
+use crate::{Restaurant, RestaurantReview, RestaurantReviewAction, Review};
+use client_sdk::contract_indexer::axum;
+use client_sdk::contract_indexer::utoipa;
+
+/// État de l'indexeur : l'état du contrat et les reviews complètes reconstruites à partir des blobs
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Debug, Clone, Default)]
+pub struct ReviewIndex {
+    pub state: RestaurantReview,
+    pub reviews: IndexedReviews,
+    pub review_txs: Vec<TxHash>, // Transaction de chaque review, dans l'ordre de publication
+}
+
+// Required by ContractHandler, settled transactions are replayed by `handle` instead
+impl HyleContract for ReviewIndex {
+    fn execute(&mut self, contract_input: &ContractInput) -> RunResult {
+        self.state.execute(contract_input)
+    }
+}
+
+impl Digestable for ReviewIndex {
+    fn as_digest(&self) -> StateDigest {
+        self.state.as_digest()
+    }
+}
+
+impl ContractHandler for ReviewIndex {
+    async fn api(store: ContractHandlerStore<ReviewIndex>) -> (Router<()>, OpenApi) {
+        let (router, api) = OpenApiRouter::default()
+            .routes(routes!(get_state))
+            .routes(routes!(get_nullifier))
+            .routes(routes!(get_review))
+            .routes(routes!(get_review_by_tx))
+            .routes(routes!(get_restaurants))
+            .routes(routes!(get_restaurant))
+            .routes(routes!(get_restaurant_reviews))
+            .split_for_parts();
+
+        (router.with_state(store), api)
+    }
+
+    /// L'indexeur part de l'état enregistré avec le contrat, modérateur compris
+    fn initial_state(state_digest: StateDigest) -> Result<Self> {
+        Ok(ReviewIndex {
+            state: RestaurantReview::try_from(state_digest).map_err(|e| anyhow!(e))?,
+            ..Default::default()
+        })
+    }
+
+    /// Le témoin est une entrée privée : l'indexeur ne peut pas le revérifier.
+    /// Les transactions reçues ici sont déjà prouvées, on applique donc directement l'action
+    /// sur les reviews complètes, qui servent ensuite à produire les témoins suivants.
+    fn handle(
+        tx: &BlobTransaction,
+        index: BlobIndex,
+        mut state: Self,
+        tx_context: TxContext,
+    ) -> Result<Self> {
+        let Blob {
+            contract_name,
+            data,
+        } = tx.blobs.get(index.0).context("Failed to get blob")?;
+
+        let action: RestaurantReviewAction = borsh::from_slice(&data.0)?;
+        let submitted = matches!(action, RestaurantReviewAction::SubmitReview { .. });
+        state
+            .state
+            .apply_action(&mut state.reviews, action, &tx.identity, Some(&tx_context))
+            .map_err(|e| anyhow!(e))?;
+        if submitted {
+            state.review_txs.push(tx.hashed());
+        }
+        info!("🚀 Executed {contract_name}");
+
+        Ok(state)
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/state",
+    tag = "Contract",
+    responses(
+        (status = OK, description = "Get json state of contract")
+    )
+)]
+pub async fn get_state(
+    State(state): State<ContractHandlerStore<ReviewIndex>>,
+) -> Result<impl IntoResponse, AppError> {
+    let store = state.read().await;
+    store
+        .state
+        .as_ref()
+        .map(|index| Json(index.state.clone()))
+        .ok_or(AppError(
+            StatusCode::NOT_FOUND,
+            anyhow!("No state found for contract '{}'", store.contract_name),
+        ))
+}
+
+#[derive(Serialize, ToSchema)]
+struct NullifierResponse {
+    nullifier: String,
+    spent: bool,
+    #[schema(value_type = Object)]
+    proof: MerkleProof,
+}
+
+#[utoipa::path(
+    get,
+    path = "/nullifier/{nullifier}",
+    params(
+        ("nullifier" = String, Path, description = "Hex encoded visit nullifier")
+    ),
+    tag = "Contract",
+    responses(
+        (status = OK, description = "Tell whether a visit was already used for a review, with the Merkle proof of its leaf", body = NullifierResponse)
+    )
+)]
+pub async fn get_nullifier(
+    Path(nullifier): Path<String>,
+    State(state): State<ContractHandlerStore<ReviewIndex>>,
+) -> Result<impl IntoResponse, AppError> {
+    let parsed: Nullifier = hex::decode(&nullifier)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(AppError(
+            StatusCode::BAD_REQUEST,
+            anyhow!("Invalid nullifier '{nullifier}'"),
+        ))?;
+
+    let store = state.read().await;
+    let index = store.state.as_ref().ok_or(AppError(
+        StatusCode::NOT_FOUND,
+        anyhow!("Contract '{}' not found", store.contract_name),
+    ))?;
+
+    Ok(Json(NullifierResponse {
+        spent: index.reviews.is_nullifier_spent(&parsed),
+        proof: index.reviews.nullifier_proof(&parsed),
+        nullifier,
+    }))
+}
+
+#[derive(Serialize, ToSchema)]
+struct ReviewResponse {
+    review_id: u32,
+    #[schema(value_type = Object)]
+    review: Option<Review>,
+    #[schema(value_type = Object)]
+    proof: MerkleProof,
+    #[schema(value_type = String)]
+    root: MerkleRoot,
+}
+
+#[utoipa::path(
+    get,
+    path = "/review/{review_id}",
+    params(
+        ("review_id" = u32, Path, description = "Review number, in publication order")
+    ),
+    tag = "Contract",
+    responses(
+        (status = OK, description = "Get a review with its Merkle proof. The next review number gives the proof of the empty slot", body = ReviewResponse)
+    )
+)]
+pub async fn get_review(
+    Path(review_id): Path<u32>,
+    State(state): State<ContractHandlerStore<ReviewIndex>>,
+) -> Result<impl IntoResponse, AppError> {
+    let store = state.read().await;
+    let index = store.state.as_ref().ok_or(AppError(
+        StatusCode::NOT_FOUND,
+        anyhow!("Contract '{}' not found", store.contract_name),
+    ))?;
+    if review_id > index.state.review_count() {
+        return Err(AppError(
+            StatusCode::NOT_FOUND,
+            anyhow!("Review {review_id} not found"),
+        ));
+    }
+
+    Ok(Json(ReviewResponse {
+        review_id,
+        review: index.reviews.reviews().get(review_id as usize).cloned(),
+        proof: index.reviews.review_proof(review_id),
+        root: index.state.reviews_root,
+    }))
+}
+
+impl ReviewIndex {
+    fn entry(&self, review_id: u32) -> Option<ReviewEntry> {
+        Some(ReviewEntry {
+            review_id,
+            tx_hash: self.review_txs.get(review_id as usize)?.clone(),
+            review: self.reviews.reviews().get(review_id as usize)?.clone(),
+        })
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct ReviewEntry {
+    review_id: u32,
+    tx_hash: TxHash,
+    #[schema(value_type = Object)]
+    review: Review,
+}
+
+#[utoipa::path(
+    get,
+    path = "/review/tx/{tx_hash}",
+    params(
+        ("tx_hash" = String, Path, description = "Hash of the transaction that submitted the review")
+    ),
+    tag = "Contract",
+    responses(
+        (status = OK, description = "Get the review submitted by a transaction", body = ReviewEntry)
+    )
+)]
+pub async fn get_review_by_tx(
+    Path(tx_hash): Path<TxHash>,
+    State(state): State<ContractHandlerStore<ReviewIndex>>,
+) -> Result<impl IntoResponse, AppError> {
+    let store = state.read().await;
+    let index = store.state.as_ref().ok_or(AppError(
+        StatusCode::NOT_FOUND,
+        anyhow!("Contract '{}' not found", store.contract_name),
+    ))?;
+
+    index
+        .review_txs
+        .iter()
+        .position(|hash| *hash == tx_hash)
+        .and_then(|review_id| index.entry(review_id as u32))
+        .map(Json)
+        .ok_or(AppError(
+            StatusCode::NOT_FOUND,
+            anyhow!("No review submitted by transaction {tx_hash}"),
+        ))
+}
+
+#[derive(Serialize, ToSchema)]
+struct RestaurantResponse {
+    id: String,
+    owner: Identity,
+    /// Hex encoded public key
+    public_key: String,
+    #[schema(value_type = String)]
+    scheme: SignatureScheme,
+    rating_count: u64,
+    average_rating: Option<f64>,
+}
+
+impl RestaurantResponse {
+    fn new(id: &str, restaurant: &Restaurant) -> Self {
+        RestaurantResponse {
+            id: id.to_string(),
+            owner: restaurant.owner.clone(),
+            public_key: hex::encode(&restaurant.public_key),
+            scheme: restaurant.scheme,
+            rating_count: restaurant.rating_count,
+            average_rating: restaurant.average_rating(),
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/restaurants",
+    tag = "Contract",
+    responses(
+        (status = OK, description = "List registered restaurants with their aggregate rating", body = [RestaurantResponse])
+    )
+)]
+pub async fn get_restaurants(
+    State(state): State<ContractHandlerStore<ReviewIndex>>,
+) -> Result<impl IntoResponse, AppError> {
+    let store = state.read().await;
+    let index = store.state.as_ref().ok_or(AppError(
+        StatusCode::NOT_FOUND,
+        anyhow!("Contract '{}' not found", store.contract_name),
+    ))?;
+
+    let mut restaurants: Vec<RestaurantResponse> = index
+        .state
+        .restaurants()
+        .map(|(id, restaurant)| RestaurantResponse::new(id, restaurant))
+        .collect();
+    restaurants.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(Json(restaurants))
+}
+
+#[utoipa::path(
+    get,
+    path = "/restaurant/{id}",
+    params(
+        ("id" = String, Path, description = "Restaurant id")
+    ),
+    tag = "Contract",
+    responses(
+        (status = OK, description = "Get a restaurant with its aggregate rating", body = RestaurantResponse)
+    )
+)]
+pub async fn get_restaurant(
+    Path(id): Path<String>,
+    State(state): State<ContractHandlerStore<ReviewIndex>>,
+) -> Result<impl IntoResponse, AppError> {
+    let store = state.read().await;
+    let index = store.state.as_ref().ok_or(AppError(
+        StatusCode::NOT_FOUND,
+        anyhow!("Contract '{}' not found", store.contract_name),
+    ))?;
+
+    index
+        .state
+        .restaurant(&id)
+        .map(|restaurant| Json(RestaurantResponse::new(&id, restaurant)))
+        .ok_or(AppError(
+            StatusCode::NOT_FOUND,
+            anyhow!("Restaurant {id} not found"),
+        ))
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct ReviewPagination {
+    /// Number of reviews to skip, most recent first
+    pub offset: Option<usize>,
+    /// Number of reviews to return, 20 by default and 100 at most
+    pub limit: Option<usize>,
+    /// Include the reviews hidden by the moderator
+    pub include_hidden: Option<bool>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ReviewPage {
+    restaurant_id: String,
+    total: usize,
+    offset: usize,
+    reviews: Vec<ReviewEntry>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/restaurant/{id}/reviews",
+    params(
+        ("id" = String, Path, description = "Restaurant id"),
+        ReviewPagination
+    ),
+    tag = "Contract",
+    responses(
+        (status = OK, description = "Get a page of the reviews of a restaurant, most recent first", body = ReviewPage)
+    )
+)]
+pub async fn get_restaurant_reviews(
+    Path(id): Path<String>,
+    Query(pagination): Query<ReviewPagination>,
+    State(state): State<ContractHandlerStore<ReviewIndex>>,
+) -> Result<impl IntoResponse, AppError> {
+    let store = state.read().await;
+    let index = store.state.as_ref().ok_or(AppError(
+        StatusCode::NOT_FOUND,
+        anyhow!("Contract '{}' not found", store.contract_name),
+    ))?;
+    if index.state.restaurant(&id).is_none() {
+        return Err(AppError(
+            StatusCode::NOT_FOUND,
+            anyhow!("Restaurant {id} not found"),
+        ));
+    }
+
+    let include_hidden = pagination.include_hidden.unwrap_or(false);
+    let review_ids: Vec<u32> = (0..index.reviews.reviews().len() as u32)
+        .rev()
+        .filter(|review_id| {
+            let review = &index.reviews.reviews()[*review_id as usize];
+            review.restaurant_id == id && (include_hidden || !review.hidden)
+        })
+        .collect();
+    let offset = pagination.offset.unwrap_or(0);
+    let limit = pagination.limit.unwrap_or(20).min(100);
+
+    Ok(Json(ReviewPage {
+        total: review_ids.len(),
+        offset,
+        reviews: review_ids
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .filter_map(|review_id| index.entry(review_id))
+            .collect(),
+        restaurant_id: id,
+    }))
+}
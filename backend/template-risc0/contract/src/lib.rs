@@ -1,39 +1,121 @@
 use borsh::{io::Error, BorshDeserialize, BorshSerialize};
+use sdk::identity_provider::IdentityAction;
+use sdk::{Digestable, HyleContract, Identity, RunResult};
 use serde::{Deserialize, Serialize};
-use sdk::{Digestable, HyleContract, RunResult};
 
-/// État du contrat
-#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone)]
+#[cfg(feature = "client")]
+pub mod indexer;
+pub mod merkle;
+pub mod pseudonym;
+pub mod store;
+pub mod visit;
+
+use merkle::{MerkleRoot, EMPTY};
+use pseudonym::{check_pseudonym, Pseudonym, ReviewerSecret};
+use store::{ReviewStore, ReviewWitness};
+use visit::{Nullifier, SignatureScheme, SignedMessage, VISIT_VALIDITY_SECS};
+
+/// Contrat d'identité qui authentifie les propriétaires de restaurants et le modérateur
+pub const IDENTITY_CONTRACT: &str = "hydentity";
+
+/// Longueur maximale (en caractères) d'une review ou d'une réponse
+pub const MAX_TEXT_LEN: usize = 2000;
+/// Longueur maximale (en caractères) du motif d'un signalement
+pub const MAX_FLAG_REASON_LEN: usize = 280;
+
+/// État du contrat.
+/// Les reviews et les visites utilisées ne sont gardées que sous forme de racines de Merkle :
+/// leur contenu est dans les blobs, et reconstruit par l'indexeur ([store::IndexedReviews]).
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone, Default)]
 pub struct RestaurantReview {
     restaurants: std::collections::HashMap<String, Restaurant>, // Champ privé
-    reviews: std::collections::HashMap<String, Vec<Review>>,    // Champ privé
+    review_count: u32,        // Nombre de reviews publiées, numéro de la prochaine
+    reviews_root: MerkleRoot, // Racine de l'arbre des reviews
+    nullifiers_root: MerkleRoot, // Racine de l'arbre des visites déjà utilisées
+    moderator: Option<Identity>, // Identité autorisée à masquer des reviews
 }
 
 /// Structure pour représenter un restaurant
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone)]
 pub struct Restaurant {
-    pub public_key: Vec<u8>, // Clé publique du restaurant
+    pub owner: Identity,         // Seul le propriétaire peut modifier le restaurant
+    pub public_key: Vec<u8>,     // Clé publique du restaurant
+    pub scheme: SignatureScheme, // Schéma de signature des reçus
+    pub rating_count: u64,       // Nombre de notes prises en compte
+    pub rating_sum: u64,         // Somme des notes prises en compte
 }
 
 /// Structure pour représenter une review
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone)]
 pub struct Review {
-    pub client: String,
+    pub restaurant_id: String,
+    #[serde(with = "hex::serde")]
+    pub pseudonym: Pseudonym, // Seule trace du reviewer
     pub review_text: String,
-    pub zk_proof: Vec<u8>, // Preuve zkp
-    pub timestamp: u64,
+    pub rating: u8,               // Note de 1 à 5 étoiles
+    pub timestamp: u64,           // Heure du bloc qui a séquencé la review
+    pub response: Option<String>, // Réponse du propriétaire du restaurant
+    pub flags: Vec<ReviewFlag>,   // Signalements en attente de modération
+    pub hidden: bool,             // Masquée par le modérateur
+}
+
+/// Signalement d'une review
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone)]
+pub struct ReviewFlag {
+    pub by: Identity,
+    pub reason: String,
+}
+
+impl Restaurant {
+    /// Note moyenne des reviews visibles
+    pub fn average_rating(&self) -> Option<f64> {
+        (self.rating_count > 0).then(|| self.rating_sum as f64 / self.rating_count as f64)
+    }
 }
 
-/// Actions possibles sur le contrat
+/// Actions possibles sur le contrat.
+/// Les actions sur les reviews prennent en entrée privée un [ReviewWitness], qui contient
+/// le reçu de visite pour `SubmitReview` et les preuves de Merkle des données modifiées.
+/// Les actions autres que `SubmitReview` doivent être accompagnées d'un blob `VerifyIdentity`
+/// de [IDENTITY_CONTRACT] pour l'identité de la transaction.
+/// Les reviews sont désignées par leur numéro de publication.
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
 pub enum RestaurantReviewAction {
-    RegisterRestaurant { id: String, public_key: Vec<u8> },
+    RegisterRestaurant {
+        id: String,
+        public_key: Vec<u8>,
+        scheme: SignatureScheme,
+    },
     SubmitReview {
         restaurant_id: String,
-        client: String,
+        pseudonym: Pseudonym, // Pseudonyme du reviewer dans ce restaurant, vérifié par le contrat
         review_text: String,
-        zk_proof: Vec<u8>,
-        timestamp: u64,
+        rating: u8,
+        nullifier: Nullifier, // Nullifier de la visite, vérifié par le contrat
+    },
+    RotateRestaurantKey {
+        id: String,
+        public_key: Vec<u8>,
+        scheme: SignatureScheme,
+    },
+    TransferRestaurantOwnership {
+        id: String,
+        new_owner: Identity,
+    },
+    RespondToReview {
+        review_id: u32,
+        response: String,
+    },
+    FlagReview {
+        review_id: u32,
+        reason: String,
+    },
+    HideReview {
+        review_id: u32,
+        hidden: bool,
+    },
+    SetModerator {
+        moderator: Identity,
     },
 }
 
@@ -43,41 +125,374 @@ impl RestaurantReview {
     pub fn new() -> Self {
         RestaurantReview {
             restaurants: std::collections::HashMap::new(),
-            reviews: std::collections::HashMap::new(),
+            review_count: 0,
+            reviews_root: EMPTY,
+            nullifiers_root: EMPTY,
+            moderator: None,
+        }
+    }
+
+    /// Définir le modérateur initial du contrat
+    pub fn with_moderator(mut self, moderator: Identity) -> Self {
+        self.moderator = Some(moderator);
+        self
+    }
+
+    /// Enregistrer un restaurant, au nom de son propriétaire
+    pub fn register_restaurant(
+        &mut self,
+        id: String,
+        owner: Identity,
+        public_key: Vec<u8>,
+        scheme: SignatureScheme,
+    ) -> Result<(), String> {
+        if self.restaurants.contains_key(&id) {
+            return Err(format!("Restaurant {id} is already registered"));
+        }
+        scheme.check_public_key(&public_key)?;
+        self.restaurants.insert(
+            id,
+            Restaurant {
+                owner,
+                public_key,
+                scheme,
+                rating_count: 0,
+                rating_sum: 0,
+            },
+        );
+        Ok(())
+    }
+
+    /// Changer la clé de signature des reçus d'un restaurant.
+    /// Les reçus signés avec l'ancienne clé ne sont plus acceptés.
+    pub fn rotate_restaurant_key(
+        &mut self,
+        id: &str,
+        caller: &Identity,
+        public_key: Vec<u8>,
+        scheme: SignatureScheme,
+    ) -> Result<(), String> {
+        scheme.check_public_key(&public_key)?;
+        let restaurant = self.owned_restaurant_mut(id, caller)?;
+        restaurant.public_key = public_key;
+        restaurant.scheme = scheme;
+        Ok(())
+    }
+
+    /// Transférer un restaurant à un nouveau propriétaire
+    pub fn transfer_restaurant_ownership(
+        &mut self,
+        id: &str,
+        caller: &Identity,
+        new_owner: Identity,
+    ) -> Result<(), String> {
+        if new_owner.0.split_once('.').map(|(_, contract)| contract) != Some(IDENTITY_CONTRACT) {
+            return Err(format!(
+                "New owner {new_owner} must be an identity of {IDENTITY_CONTRACT}"
+            ));
         }
+        self.owned_restaurant_mut(id, caller)?.owner = new_owner;
+        Ok(())
     }
 
-    /// Enregistrer un restaurant
-    pub fn register_restaurant(&mut self, id: String, public_key: Vec<u8>) {
-        self.restaurants.insert(id, Restaurant { public_key });
+    fn owned_restaurant_mut(
+        &mut self,
+        id: &str,
+        caller: &Identity,
+    ) -> Result<&mut Restaurant, String> {
+        let restaurant = self
+            .restaurants
+            .get_mut(id)
+            .ok_or("Restaurant not found".to_string())?;
+        if restaurant.owner != *caller {
+            return Err(format!("Only the owner of restaurant {id} can modify it"));
+        }
+        Ok(restaurant)
+    }
+
+    /// Propriétaire d'un restaurant
+    pub fn restaurant_owner(&self, id: &str) -> Option<&Identity> {
+        self.restaurants.get(id).map(|restaurant| &restaurant.owner)
+    }
+
+    /// Vérifier le reçu de visite signé par le restaurant
+    pub fn verify_visit(
+        &self,
+        signed_message: &SignedMessage,
+        time_range_start: u64,
+        time_range_end: u64,
+    ) -> Result<(), String> {
+        let restaurant = self
+            .restaurants
+            .get(&signed_message.restaurant_id)
+            .ok_or("Restaurant not found".to_string())?;
+
+        // Verify the timestamp is within the valid range
+        if signed_message.timestamp < time_range_start || signed_message.timestamp > time_range_end
+        {
+            return Err("Visit timestamp is outside the valid time range".to_string());
+        }
+
+        // Verify the receipt was signed with the restaurant's key
+        let payload = SignedMessage::signing_payload(
+            &signed_message.restaurant_id,
+            &signed_message.visit_id,
+            signed_message.timestamp,
+        );
+        restaurant.scheme.verify(
+            &restaurant.public_key,
+            &payload,
+            &signed_message.signature_bytes()?,
+        )
+    }
+
+    /// Vérifier le reçu de visite fourni en entrée privée d'une `SubmitReview`
+    pub fn check_visit_receipt(
+        &self,
+        receipt: Option<&SignedMessage>,
+        reviewer_secret: Option<&ReviewerSecret>,
+        tx_ctx: Option<&sdk::TxContext>,
+        restaurant_id: &str,
+        nullifier: &Nullifier,
+    ) -> Result<(), String> {
+        let signed_message = receipt.ok_or("Missing visit receipt in private input".to_string())?;
+        if signed_message.restaurant_id != restaurant_id {
+            return Err("Visit receipt was issued by another restaurant".to_string());
+        }
+
+        // The visit must have happened in the 30 days before the block that sequenced the review.
+        // The node checks that this context is the one of the blob transaction.
+        let now = block_time(tx_ctx)?;
+        self.verify_visit(signed_message, now.saturating_sub(VISIT_VALIDITY_SECS), now)?;
+
+        // The nullifier hides the visit from the restaurant, which doesn't know the secret
+        let secret =
+            reviewer_secret.ok_or("Missing reviewer secret in private input".to_string())?;
+        if signed_message.nullifier(secret) != *nullifier {
+            return Err("Nullifier does not match the visit receipt".to_string());
+        }
+        Ok(())
     }
 
     /// Soumettre une review
+    #[allow(clippy::too_many_arguments)]
     pub fn submit_review(
         &mut self,
+        store: &mut impl ReviewStore,
         restaurant_id: String,
-        client: String,
+        pseudonym: Pseudonym,
         review_text: String,
-        zk_proof: Vec<u8>,
+        rating: u8,
         timestamp: u64,
+        nullifier: Nullifier,
     ) -> Result<(), String> {
+        if !(1..=5).contains(&rating) {
+            return Err("Rating must be between 1 and 5".to_string());
+        }
+        check_text_len("Review", &review_text, MAX_TEXT_LEN)?;
+
         // Verify that the restaurant exists
-        if let Some(_restaurant) = self.restaurants.get(&restaurant_id) {
-            // Note: In the Hylé platform, zk-proof verification is done at the blockchain level
-            // We no longer need to verify the proof in the contract itself
-            // The contract is only executed if the proof is valid
-            
-            // Add the review
-            let review = Review {
-                client,
-                review_text,
-                zk_proof,
-                timestamp,
-            };
-            self.reviews.entry(restaurant_id).or_insert_with(Vec::new).push(review);
-            Ok(())
+        if !self.restaurants.contains_key(&restaurant_id) {
+            return Err("Restaurant not found".to_string());
+        }
+
+        // Only one review per visit
+        self.nullifiers_root = store.spend_nullifier(&self.nullifiers_root, &nullifier)?;
+
+        // Add the review
+        let review = Review {
+            restaurant_id,
+            pseudonym,
+            review_text,
+            rating,
+            timestamp,
+            response: None,
+            flags: vec![],
+            hidden: false,
+        };
+        self.reviews_root = store.put_review(&self.reviews_root, self.review_count, &review)?;
+        self.review_count += 1;
+        self.count_rating(&review.restaurant_id, review.rating, true)
+    }
+
+    /// Ajouter une note à la moyenne du restaurant, ou l'en retirer
+    fn count_rating(
+        &mut self,
+        restaurant_id: &str,
+        rating: u8,
+        counted: bool,
+    ) -> Result<(), String> {
+        let restaurant = self
+            .restaurants
+            .get_mut(restaurant_id)
+            .ok_or("Restaurant not found".to_string())?;
+        if counted {
+            restaurant.rating_count += 1;
+            restaurant.rating_sum += rating as u64;
         } else {
-            Err("Restaurant not found".to_string())
+            restaurant.rating_count -= 1;
+            restaurant.rating_sum -= rating as u64;
+        }
+        Ok(())
+    }
+
+    /// Modifier une review existante
+    fn update_review(
+        &mut self,
+        store: &mut impl ReviewStore,
+        review_id: u32,
+        update: impl FnOnce(&mut Self, &mut Review) -> Result<(), String>,
+    ) -> Result<(), String> {
+        if review_id >= self.review_count {
+            return Err(format!("Review {review_id} not found"));
+        }
+        let mut review = store.review(&self.reviews_root, review_id)?;
+        update(self, &mut review)?;
+        self.reviews_root = store.put_review(&self.reviews_root, review_id, &review)?;
+        Ok(())
+    }
+
+    /// Répondre à une review, réservé au propriétaire du restaurant.
+    /// Une nouvelle réponse remplace la précédente.
+    pub fn respond_to_review(
+        &mut self,
+        store: &mut impl ReviewStore,
+        caller: &Identity,
+        review_id: u32,
+        response: String,
+    ) -> Result<(), String> {
+        check_text_len("Response", &response, MAX_TEXT_LEN)?;
+        self.update_review(store, review_id, |contract, review| {
+            contract.owned_restaurant_mut(&review.restaurant_id, caller)?;
+            review.response = Some(response);
+            Ok(())
+        })
+    }
+
+    /// Signaler une review au modérateur, une seule fois par identité
+    pub fn flag_review(
+        &mut self,
+        store: &mut impl ReviewStore,
+        caller: &Identity,
+        review_id: u32,
+        reason: String,
+    ) -> Result<(), String> {
+        check_text_len("Flag reason", &reason, MAX_FLAG_REASON_LEN)?;
+        self.update_review(store, review_id, |_, review| {
+            if review.flags.iter().any(|flag| flag.by == *caller) {
+                return Err(format!("Review already flagged by {caller}"));
+            }
+            review.flags.push(ReviewFlag {
+                by: caller.clone(),
+                reason,
+            });
+            Ok(())
+        })
+    }
+
+    /// Masquer ou réafficher une review, réservé au modérateur.
+    /// La décision traite les signalements, et une review masquée ne compte plus dans la note.
+    pub fn hide_review(
+        &mut self,
+        store: &mut impl ReviewStore,
+        caller: &Identity,
+        review_id: u32,
+        hidden: bool,
+    ) -> Result<(), String> {
+        self.check_moderator(caller)?;
+        self.update_review(store, review_id, |contract, review| {
+            review.flags.clear();
+            if review.hidden != hidden {
+                review.hidden = hidden;
+                contract.count_rating(&review.restaurant_id, review.rating, !hidden)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Changer de modérateur, réservé au modérateur actuel
+    pub fn set_moderator(&mut self, caller: &Identity, moderator: Identity) -> Result<(), String> {
+        self.check_moderator(caller)?;
+        self.moderator = Some(moderator);
+        Ok(())
+    }
+
+    fn check_moderator(&self, caller: &Identity) -> Result<(), String> {
+        match &self.moderator {
+            Some(moderator) if moderator == caller => Ok(()),
+            Some(_) => Err(format!("{caller} is not the moderator")),
+            None => Err("No moderator configured".to_string()),
+        }
+    }
+
+    /// Restaurant enregistré, avec ses notes agrégées
+    pub fn restaurant(&self, id: &str) -> Option<&Restaurant> {
+        self.restaurants.get(id)
+    }
+
+    /// Restaurants enregistrés, par identifiant
+    pub fn restaurants(&self) -> impl Iterator<Item = (&String, &Restaurant)> {
+        self.restaurants.iter()
+    }
+
+    /// Nombre de reviews publiées
+    pub fn review_count(&self) -> u32 {
+        self.review_count
+    }
+
+    /// Appliquer une action à l'état.
+    /// Les vérifications qui dépendent du reçu de visite ou des autres blobs sont faites
+    /// dans `execute` : l'indexeur rejoue des transactions déjà prouvées, avec son propre `store`.
+    /// Les reviews sont datées par le bloc qui les a séquencées, d'après `tx_ctx`.
+    pub fn apply_action(
+        &mut self,
+        store: &mut impl ReviewStore,
+        action: RestaurantReviewAction,
+        caller: &Identity,
+        tx_ctx: Option<&sdk::TxContext>,
+    ) -> Result<(), String> {
+        match action {
+            RestaurantReviewAction::RegisterRestaurant {
+                id,
+                public_key,
+                scheme,
+            } => self.register_restaurant(id, caller.clone(), public_key, scheme),
+            RestaurantReviewAction::SubmitReview {
+                restaurant_id,
+                pseudonym,
+                review_text,
+                rating,
+                nullifier,
+            } => self.submit_review(
+                store,
+                restaurant_id,
+                pseudonym,
+                review_text,
+                rating,
+                block_time(tx_ctx)?,
+                nullifier,
+            ),
+            RestaurantReviewAction::RotateRestaurantKey {
+                id,
+                public_key,
+                scheme,
+            } => self.rotate_restaurant_key(&id, caller, public_key, scheme),
+            RestaurantReviewAction::TransferRestaurantOwnership { id, new_owner } => {
+                self.transfer_restaurant_ownership(&id, caller, new_owner)
+            }
+            RestaurantReviewAction::RespondToReview {
+                review_id,
+                response,
+            } => self.respond_to_review(store, caller, review_id, response),
+            RestaurantReviewAction::FlagReview { review_id, reason } => {
+                self.flag_review(store, caller, review_id, reason)
+            }
+            RestaurantReviewAction::HideReview { review_id, hidden } => {
+                self.hide_review(store, caller, review_id, hidden)
+            }
+            RestaurantReviewAction::SetModerator { moderator } => {
+                self.set_moderator(caller, moderator)
+            }
         }
     }
 
@@ -92,30 +507,100 @@ impl HyleContract for RestaurantReview {
     /// Point d'entrée de la logique du contrat
     fn execute(&mut self, contract_input: &sdk::ContractInput) -> RunResult {
         // Parse contract inputs
-        let (action, ctx) = sdk::utils::parse_raw_contract_input::<RestaurantReviewAction>(contract_input)?;
+        let (action, ctx) =
+            sdk::utils::parse_raw_contract_input::<RestaurantReviewAction>(contract_input)?;
 
-        // Execute contract logic
-        match action {
-            RestaurantReviewAction::RegisterRestaurant { id, public_key } => {
-                self.register_restaurant(id, public_key);
-            }
+        // Reviews data and proofs come from the private input
+        let mut witness: ReviewWitness = if contract_input.private_input.is_empty() {
+            ReviewWitness::default()
+        } else {
+            borsh::from_slice(&contract_input.private_input)
+                .map_err(|_| "Invalid private input".to_string())?
+        };
+
+        // Check what the state alone can't tell
+        match &action {
             RestaurantReviewAction::SubmitReview {
                 restaurant_id,
-                client,
-                review_text,
-                zk_proof,
-                timestamp,
+                pseudonym,
+                nullifier,
+                ..
             } => {
-                self.submit_review(restaurant_id, client, review_text, zk_proof, timestamp)?;
+                // The review is only accepted with a valid receipt, checked inside the zkvm
+                self.check_visit_receipt(
+                    witness.receipt.as_ref(),
+                    witness.reviewer_secret.as_ref(),
+                    contract_input.tx_ctx.as_ref(),
+                    restaurant_id,
+                    nullifier,
+                )?;
+                // The secret never leaves the zkvm, only the per-restaurant pseudonym is public
+                check_pseudonym(witness.reviewer_secret.as_ref(), restaurant_id, pseudonym)?;
+            }
+            RestaurantReviewAction::RegisterRestaurant { .. }
+            | RestaurantReviewAction::RotateRestaurantKey { .. }
+            | RestaurantReviewAction::TransferRestaurantOwnership { .. }
+            | RestaurantReviewAction::RespondToReview { .. }
+            | RestaurantReviewAction::FlagReview { .. }
+            | RestaurantReviewAction::HideReview { .. }
+            | RestaurantReviewAction::SetModerator { .. } => {
+                check_identity_verified(contract_input)?;
             }
         }
 
+        // Execute contract logic
+        self.apply_action(
+            &mut witness,
+            action,
+            &contract_input.identity,
+            contract_input.tx_ctx.as_ref(),
+        )?;
+
         // Return a result
         let program_output = "Action executed successfully".to_string();
         Ok((program_output, ctx, vec![]))
     }
 }
 
+/// Heure (en secondes) du bloc qui a séquencé la transaction
+fn block_time(tx_ctx: Option<&sdk::TxContext>) -> Result<u64, String> {
+    let tx_ctx = tx_ctx.ok_or("Missing transaction context".to_string())?;
+    Ok((tx_ctx.timestamp / 1000) as u64)
+}
+
+fn check_text_len(field: &str, text: &str, max_len: usize) -> Result<(), String> {
+    if text.chars().count() > max_len {
+        return Err(format!("{field} must be at most {max_len} characters"));
+    }
+    Ok(())
+}
+
+/// Vérifie que l'identité de la transaction est prouvée par un blob `VerifyIdentity`
+/// de [IDENTITY_CONTRACT] dans la même transaction.
+/// La transaction n'est réglée que si ce blob est lui aussi prouvé.
+pub fn check_identity_verified(contract_input: &sdk::ContractInput) -> Result<(), String> {
+    let identity = &contract_input.identity;
+    if identity.0.split_once('.').map(|(_, contract)| contract) != Some(IDENTITY_CONTRACT) {
+        return Err(format!(
+            "Identity {identity} must be an identity of {IDENTITY_CONTRACT}"
+        ));
+    }
+
+    let verified = contract_input.blobs.iter().any(|blob| {
+        blob.contract_name.0 == IDENTITY_CONTRACT
+            && matches!(
+                borsh::from_slice::<IdentityAction>(&blob.data.0),
+                Ok(IdentityAction::VerifyIdentity { account, .. }) if account == identity.0
+            )
+    });
+    if !verified {
+        return Err(format!(
+            "Missing {IDENTITY_CONTRACT} identity verification for {identity}"
+        ));
+    }
+    Ok(())
+}
+
 /// Implémentation de Digestable pour RestaurantReview
 impl Digestable for RestaurantReview {
     fn as_digest(&self) -> sdk::StateDigest {
@@ -124,11 +609,12 @@ impl Digestable for RestaurantReview {
 }
 
 /// Conversion de StateDigest en RestaurantReview
-impl From<sdk::StateDigest> for RestaurantReview {
-    fn from(state: sdk::StateDigest) -> Self {
+impl TryFrom<sdk::StateDigest> for RestaurantReview {
+    type Error = String;
+
+    fn try_from(state: sdk::StateDigest) -> Result<Self, Self::Error> {
         borsh::from_slice(&state.0)
-            .map_err(|_| "Could not decode Hylar state".to_string())
-            .unwrap()
+            .map_err(|_| "Could not decode restaurant review state".to_string())
     }
 }
 
@@ -140,4 +626,621 @@ impl RestaurantReviewAction {
             data: sdk::BlobData(borsh::to_vec(self).expect("failed to encode BlobData")),
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::Signer;
+    use pseudonym::derive_pseudonym;
+    use store::IndexedReviews;
+
+    const REVIEWER_SECRET: pseudonym::ReviewerSecret = [7; 32];
+
+    fn reviewer_pseudonym() -> Pseudonym {
+        derive_pseudonym(&REVIEWER_SECRET, "restaurant_1")
+    }
+
+    fn signed_message(signing_key: &ed25519_dalek::SigningKey, timestamp: u64) -> SignedMessage {
+        let payload = SignedMessage::signing_payload("restaurant_1", "visit_1", timestamp);
+        SignedMessage {
+            restaurant_id: "restaurant_1".to_string(),
+            visit_id: "visit_1".to_string(),
+            timestamp,
+            signature: hex::encode(signing_key.sign(&payload).to_bytes()),
+        }
+    }
+
+    fn contract_with_restaurant(signing_key: &ed25519_dalek::SigningKey) -> RestaurantReview {
+        let mut contract = RestaurantReview::new();
+        contract
+            .register_restaurant(
+                "restaurant_1".to_string(),
+                "owner.hydentity".into(),
+                signing_key.verifying_key().to_bytes().to_vec(),
+                SignatureScheme::Ed25519,
+            )
+            .unwrap();
+        contract
+    }
+
+    #[test]
+    fn test_register_restaurant_invalid_key() {
+        let mut contract = RestaurantReview::new();
+        let owner: Identity = "owner.hydentity".into();
+        assert!(contract
+            .register_restaurant(
+                "r".to_string(),
+                owner.clone(),
+                vec![1, 2, 3],
+                SignatureScheme::Ed25519
+            )
+            .is_err());
+        assert!(contract
+            .register_restaurant(
+                "r".to_string(),
+                owner,
+                vec![1, 2, 3],
+                SignatureScheme::Secp256k1
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_verify_visit_ed25519() {
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[1; 32]);
+        let contract = contract_with_restaurant(&signing_key);
+        let message = signed_message(&signing_key, 100);
+
+        assert!(contract.verify_visit(&message, 50, 150).is_ok());
+    }
+
+    #[test]
+    fn test_signing_payload_and_nullifier() {
+        // Length prefixes keep field boundaries in the signed bytes
+        assert_ne!(
+            SignedMessage::signing_payload("restaurant_1", "visit_1:2", 3),
+            SignedMessage::signing_payload("restaurant_1:visit_1", "2", 3)
+        );
+
+        // The nullifier is stable for a reviewer and a visit, and needs the reviewer secret
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[1; 32]);
+        let message = signed_message(&signing_key, 100);
+        assert_eq!(
+            message.nullifier(&REVIEWER_SECRET),
+            signed_message(&signing_key, 100).nullifier(&REVIEWER_SECRET)
+        );
+        assert_ne!(
+            message.nullifier(&REVIEWER_SECRET),
+            message.nullifier(&[8; 32])
+        );
+        let mut other_visit = message.clone();
+        other_visit.visit_id = "visit_2".to_string();
+        assert_ne!(
+            message.nullifier(&REVIEWER_SECRET),
+            other_visit.nullifier(&REVIEWER_SECRET)
+        );
+    }
+
+    #[test]
+    fn test_verify_visit_secp256k1() {
+        use k256::ecdsa::signature::Signer;
+
+        let signing_key = k256::ecdsa::SigningKey::from_slice(&[2; 32]).unwrap();
+        let public_key = k256::ecdsa::VerifyingKey::from(&signing_key)
+            .to_encoded_point(true)
+            .as_bytes()
+            .to_vec();
+        let mut contract = RestaurantReview::new();
+        contract
+            .register_restaurant(
+                "restaurant_1".to_string(),
+                "owner.hydentity".into(),
+                public_key,
+                SignatureScheme::Secp256k1,
+            )
+            .unwrap();
+
+        let payload = SignedMessage::signing_payload("restaurant_1", "visit_1", 100);
+        let signature: k256::ecdsa::Signature = signing_key.sign(&payload);
+        let mut message = SignedMessage {
+            restaurant_id: "restaurant_1".to_string(),
+            visit_id: "visit_1".to_string(),
+            timestamp: 100,
+            signature: hex::encode(signature.to_bytes()),
+        };
+        assert!(contract.verify_visit(&message, 50, 150).is_ok());
+
+        // The high-s twin of a valid signature must be rejected
+        let (r, s) = signature.split_scalars();
+        let high_s = k256::ecdsa::Signature::from_scalars(r, -*s).unwrap();
+        message.signature = hex::encode(high_s.to_bytes());
+        assert_eq!(
+            contract.verify_visit(&message, 50, 150).unwrap_err(),
+            "Secp256k1 signature must be low-s normalized"
+        );
+    }
+
+    #[test]
+    fn test_verify_visit_forged_signature() {
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[1; 32]);
+        let forger_key = ed25519_dalek::SigningKey::from_bytes(&[3; 32]);
+        let contract = contract_with_restaurant(&signing_key);
+
+        let message = signed_message(&forger_key, 100);
+        assert_eq!(
+            contract.verify_visit(&message, 50, 150).unwrap_err(),
+            "Invalid visit signature"
+        );
+
+        // Tampering with the signed fields invalidates the signature
+        let mut message = signed_message(&signing_key, 100);
+        message.visit_id = "visit_2".to_string();
+        assert_eq!(
+            contract.verify_visit(&message, 50, 150).unwrap_err(),
+            "Invalid visit signature"
+        );
+    }
+
+    #[test]
+    fn test_verify_visit_out_of_range() {
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[1; 32]);
+        let contract = contract_with_restaurant(&signing_key);
+        let message = signed_message(&signing_key, 200);
+
+        assert_eq!(
+            contract.verify_visit(&message, 50, 150).unwrap_err(),
+            "Visit timestamp is outside the valid time range"
+        );
+    }
+
+    #[test]
+    fn test_submit_review_nullifier_spent_once() {
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[1; 32]);
+        let mut contract = contract_with_restaurant(&signing_key);
+        let mut store = IndexedReviews::default();
+        let nullifier = signed_message(&signing_key, 100).nullifier(&REVIEWER_SECRET);
+
+        assert!(!store.is_nullifier_spent(&nullifier));
+        contract
+            .submit_review(
+                &mut store,
+                "restaurant_1".to_string(),
+                reviewer_pseudonym(),
+                "Great service!".to_string(),
+                5,
+                100,
+                nullifier,
+            )
+            .unwrap();
+        assert!(store.is_nullifier_spent(&nullifier));
+
+        let err = contract
+            .submit_review(
+                &mut store,
+                "restaurant_1".to_string(),
+                reviewer_pseudonym(),
+                "Great service, again!".to_string(),
+                5,
+                100,
+                nullifier,
+            )
+            .unwrap_err();
+        assert!(err.starts_with("A review was already submitted for this visit"));
+        assert_eq!(store.reviews().len(), 1);
+        assert_eq!(contract.review_count(), 1);
+    }
+
+    #[test]
+    fn test_verify_visit_unknown_restaurant() {
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[1; 32]);
+        let contract = RestaurantReview::new();
+        let message = signed_message(&signing_key, 100);
+
+        assert_eq!(
+            contract.verify_visit(&message, 50, 150).unwrap_err(),
+            "Restaurant not found"
+        );
+    }
+
+    #[test]
+    fn test_pseudonym_per_restaurant() {
+        // Stable in a restaurant, different across restaurants and reviewers
+        assert_eq!(
+            derive_pseudonym(&REVIEWER_SECRET, "restaurant_1"),
+            reviewer_pseudonym()
+        );
+        assert_ne!(
+            derive_pseudonym(&REVIEWER_SECRET, "restaurant_2"),
+            reviewer_pseudonym()
+        );
+        assert_ne!(
+            derive_pseudonym(&[8; 32], "restaurant_1"),
+            reviewer_pseudonym()
+        );
+    }
+
+    fn submit_action(nullifier: Nullifier) -> RestaurantReviewAction {
+        RestaurantReviewAction::SubmitReview {
+            restaurant_id: "restaurant_1".to_string(),
+            pseudonym: reviewer_pseudonym(),
+            review_text: "Great service!".to_string(),
+            rating: 5,
+            nullifier,
+        }
+    }
+
+    fn review_input(
+        contract: &RestaurantReview,
+        store: &IndexedReviews,
+        message: &SignedMessage,
+        nullifier: Nullifier,
+        tx_ctx: Option<sdk::TxContext>,
+    ) -> sdk::ContractInput {
+        sdk::ContractInput {
+            state: contract.as_bytes().unwrap(),
+            identity: "client_1.hydentity".into(),
+            index: sdk::BlobIndex(0),
+            blobs: vec![submit_action(nullifier).as_blob("restaurant_review")],
+            tx_hash: sdk::TxHash("tx".to_string()),
+            tx_ctx,
+            private_input: store
+                .submit_witness(message.clone(), REVIEWER_SECRET, &nullifier)
+                .as_private_input(),
+        }
+    }
+
+    fn tx_ctx_at(timestamp_secs: u64) -> Option<sdk::TxContext> {
+        Some(sdk::TxContext {
+            timestamp: timestamp_secs as u128 * 1000,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_execute_submit_review() {
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[1; 32]);
+        let mut contract = contract_with_restaurant(&signing_key);
+        let mut store = IndexedReviews::default();
+        let message = signed_message(&signing_key, 100);
+        let nullifier = message.nullifier(&REVIEWER_SECRET);
+
+        let input = review_input(&contract, &store, &message, nullifier, tx_ctx_at(200));
+        let (state, output) = sdk::guest::execute::<RestaurantReview>(&input);
+        assert!(
+            output.success,
+            "{}",
+            String::from_utf8_lossy(&output.program_outputs)
+        );
+        assert_eq!(output.initial_state, contract.as_digest());
+        assert_eq!(output.next_state, state.as_digest());
+        assert_ne!(output.initial_state, output.next_state);
+
+        // The indexer replays the action on its full trees and reaches the same state
+        contract
+            .apply_action(
+                &mut store,
+                submit_action(nullifier),
+                &input.identity,
+                input.tx_ctx.as_ref(),
+            )
+            .unwrap();
+        assert_eq!(contract.as_digest(), output.next_state);
+        assert!(store.is_nullifier_spent(&nullifier));
+        // Dated by the block that sequenced it
+        assert_eq!(store.reviews()[0].timestamp, 200);
+
+        // The same visit can't be used twice, even with a fresh proof
+        let input = review_input(&contract, &store, &message, nullifier, tx_ctx_at(200));
+        let (_, output) = sdk::guest::execute::<RestaurantReview>(&input);
+        assert!(!output.success);
+        assert!(String::from_utf8_lossy(&output.program_outputs)
+            .starts_with("A review was already submitted for this visit"));
+    }
+
+    #[test]
+    fn test_execute_submit_review_rejected() {
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[1; 32]);
+        let contract = contract_with_restaurant(&signing_key);
+        let store = IndexedReviews::default();
+        let message = signed_message(&signing_key, 100);
+        let nullifier = message.nullifier(&REVIEWER_SECRET);
+
+        let assert_rejected = |input: sdk::ContractInput, expected: &str| {
+            let (_, output) = sdk::guest::execute::<RestaurantReview>(&input);
+            assert!(!output.success);
+            assert_eq!(output.program_outputs, expected.as_bytes());
+        };
+
+        assert_rejected(
+            review_input(&contract, &store, &message, nullifier, None),
+            "Missing transaction context",
+        );
+        assert_rejected(
+            review_input(&contract, &store, &message, [0; 32], tx_ctx_at(200)),
+            "Nullifier does not match the visit receipt",
+        );
+        assert_rejected(
+            review_input(
+                &contract,
+                &store,
+                &message,
+                message.nullifier(&[8; 32]),
+                tx_ctx_at(200),
+            ),
+            "Nullifier does not match the visit receipt",
+        );
+        assert_rejected(
+            review_input(
+                &contract,
+                &store,
+                &message,
+                nullifier,
+                tx_ctx_at(100 + VISIT_VALIDITY_SECS + 1),
+            ),
+            "Visit timestamp is outside the valid time range",
+        );
+
+        let mut other_restaurant = message.clone();
+        other_restaurant.restaurant_id = "restaurant_2".to_string();
+        assert_rejected(
+            review_input(
+                &contract,
+                &store,
+                &other_restaurant,
+                nullifier,
+                tx_ctx_at(200),
+            ),
+            "Visit receipt was issued by another restaurant",
+        );
+
+        let mut missing_witness =
+            review_input(&contract, &store, &message, nullifier, tx_ctx_at(200));
+        missing_witness.private_input = vec![];
+        assert_rejected(missing_witness, "Missing visit receipt in private input");
+
+        let mut missing_secret = store.submit_witness(message.clone(), REVIEWER_SECRET, &nullifier);
+        missing_secret.reviewer_secret = None;
+        let mut missing_secret_input =
+            review_input(&contract, &store, &message, nullifier, tx_ctx_at(200));
+        missing_secret_input.private_input = missing_secret.as_private_input();
+        assert_rejected(
+            missing_secret_input,
+            "Missing reviewer secret in private input",
+        );
+
+        // A proof that doesn't match the current roots
+        let mut forged_proof = store.submit_witness(message.clone(), REVIEWER_SECRET, &nullifier);
+        forged_proof.review_proof.siblings[0] = [1; 32];
+        let mut forged_input = review_input(&contract, &store, &message, nullifier, tx_ctx_at(200));
+        forged_input.private_input = forged_proof.as_private_input();
+        assert_rejected(forged_input, "Merkle proof does not match the current root");
+    }
+
+    #[test]
+    fn test_restaurant_ownership() {
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[1; 32]);
+        let new_key = ed25519_dalek::SigningKey::from_bytes(&[4; 32]);
+        let mut contract = contract_with_restaurant(&signing_key);
+        let owner: Identity = "owner.hydentity".into();
+        let intruder: Identity = "intruder.hydentity".into();
+
+        // An existing restaurant can't be registered again, even by its owner
+        assert_eq!(
+            contract
+                .register_restaurant(
+                    "restaurant_1".to_string(),
+                    intruder.clone(),
+                    new_key.verifying_key().to_bytes().to_vec(),
+                    SignatureScheme::Ed25519,
+                )
+                .unwrap_err(),
+            "Restaurant restaurant_1 is already registered"
+        );
+
+        let new_public_key = new_key.verifying_key().to_bytes().to_vec();
+        assert!(contract
+            .rotate_restaurant_key(
+                "restaurant_1",
+                &intruder,
+                new_public_key.clone(),
+                SignatureScheme::Ed25519
+            )
+            .is_err());
+        contract
+            .rotate_restaurant_key(
+                "restaurant_1",
+                &owner,
+                new_public_key,
+                SignatureScheme::Ed25519,
+            )
+            .unwrap();
+        // Receipts signed with the old key are no longer valid
+        assert!(contract
+            .verify_visit(&signed_message(&signing_key, 100), 50, 150)
+            .is_err());
+        assert!(contract
+            .verify_visit(&signed_message(&new_key, 100), 50, 150)
+            .is_ok());
+
+        assert!(contract
+            .transfer_restaurant_ownership("restaurant_1", &intruder, intruder.clone())
+            .is_err());
+        assert!(contract
+            .transfer_restaurant_ownership("restaurant_1", &owner, "new_owner.other".into())
+            .is_err());
+        contract
+            .transfer_restaurant_ownership("restaurant_1", &owner, "new_owner.hydentity".into())
+            .unwrap();
+        assert_eq!(
+            contract.restaurant_owner("restaurant_1"),
+            Some(&"new_owner.hydentity".into())
+        );
+        assert!(contract
+            .transfer_restaurant_ownership("restaurant_1", &owner, owner.clone())
+            .is_err());
+    }
+
+    #[test]
+    fn test_execute_register_restaurant_requires_identity() {
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[1; 32]);
+        let contract = RestaurantReview::new();
+        let action = RestaurantReviewAction::RegisterRestaurant {
+            id: "restaurant_1".to_string(),
+            public_key: signing_key.verifying_key().to_bytes().to_vec(),
+            scheme: SignatureScheme::Ed25519,
+        };
+        let verify_identity = |account: &str| {
+            IdentityAction::VerifyIdentity {
+                account: account.to_string(),
+                nonce: 0,
+            }
+            .as_blob(IDENTITY_CONTRACT.into())
+        };
+        let input = |blobs: Vec<sdk::Blob>| sdk::ContractInput {
+            state: contract.as_bytes().unwrap(),
+            identity: "owner.hydentity".into(),
+            index: sdk::BlobIndex(1),
+            blobs,
+            tx_hash: sdk::TxHash("tx".to_string()),
+            tx_ctx: None,
+            private_input: vec![],
+        };
+
+        // Without the identity blob, or with one for another account
+        for identity_blob in [
+            action.as_blob(IDENTITY_CONTRACT),
+            verify_identity("intruder.hydentity"),
+        ] {
+            let (_, output) = sdk::guest::execute::<RestaurantReview>(&input(vec![
+                identity_blob,
+                action.as_blob("restaurant_review"),
+            ]));
+            assert!(!output.success);
+        }
+
+        let (state, output) = sdk::guest::execute::<RestaurantReview>(&input(vec![
+            verify_identity("owner.hydentity"),
+            action.as_blob("restaurant_review"),
+        ]));
+        assert!(output.success);
+        assert_eq!(
+            state.restaurant_owner("restaurant_1"),
+            Some(&"owner.hydentity".into())
+        );
+    }
+
+    #[test]
+    fn test_ratings_responses_and_moderation() {
+        let signing_key = ed25519_dalek::SigningKey::from_bytes(&[1; 32]);
+        let owner: Identity = "owner.hydentity".into();
+        let moderator: Identity = "moderator.hydentity".into();
+        let reader: Identity = "reader.hydentity".into();
+        let mut contract = contract_with_restaurant(&signing_key).with_moderator(moderator.clone());
+        let mut store = IndexedReviews::default();
+
+        let submit = |contract: &mut RestaurantReview,
+                      store: &mut IndexedReviews,
+                      text: &str,
+                      rating: u8| {
+            // One visit per rating
+            let visit = SignedMessage {
+                visit_id: format!("visit_{rating}"),
+                ..signed_message(&signing_key, 100)
+            };
+            let nullifier = visit.nullifier(&REVIEWER_SECRET);
+            contract.submit_review(
+                store,
+                "restaurant_1".to_string(),
+                reviewer_pseudonym(),
+                text.to_string(),
+                rating,
+                100,
+                nullifier,
+            )
+        };
+        assert_eq!(
+            submit(&mut contract, &mut store, "Nope", 0).unwrap_err(),
+            "Rating must be between 1 and 5"
+        );
+        assert_eq!(
+            submit(&mut contract, &mut store, "Nope", 6).unwrap_err(),
+            "Rating must be between 1 and 5"
+        );
+        assert!(submit(&mut contract, &mut store, &"a".repeat(MAX_TEXT_LEN + 1), 3).is_err());
+        submit(&mut contract, &mut store, "Great", 5).unwrap();
+        submit(&mut contract, &mut store, "Meh", 2).unwrap();
+        let restaurant = contract.restaurant("restaurant_1").unwrap();
+        assert_eq!(restaurant.rating_count, 2);
+        assert_eq!(restaurant.average_rating(), Some(3.5));
+
+        // Only the owner responds
+        assert!(contract
+            .respond_to_review(&mut store, &reader, 0, "Thanks".to_string())
+            .is_err());
+        assert_eq!(
+            contract
+                .respond_to_review(&mut store, &owner, 2, "Thanks".to_string())
+                .unwrap_err(),
+            "Review 2 not found"
+        );
+
+        // The zkvm reaches the same state from the witness as the indexer from its trees
+        let mut proven = contract.clone();
+        let mut witness = store.review_witness(0);
+        proven
+            .respond_to_review(&mut witness, &owner, 0, "Thanks".to_string())
+            .unwrap();
+        contract
+            .respond_to_review(&mut store, &owner, 0, "Thanks".to_string())
+            .unwrap();
+        assert_eq!(proven.as_digest(), contract.as_digest());
+        assert_eq!(store.reviews()[0].response.as_deref(), Some("Thanks"));
+
+        // A witness for another review is rejected
+        let mut witness = store.review_witness(1);
+        assert!(proven
+            .respond_to_review(&mut witness, &owner, 0, "Thanks again".to_string())
+            .is_err());
+
+        // Anyone flags once, only the moderator hides
+        contract
+            .flag_review(&mut store, &owner, 1, "Fake".to_string())
+            .unwrap();
+        assert!(contract
+            .flag_review(&mut store, &owner, 1, "Fake".to_string())
+            .is_err());
+        assert!(contract.hide_review(&mut store, &owner, 1, true).is_err());
+        contract
+            .hide_review(&mut store, &moderator, 1, true)
+            .unwrap();
+        let review = &store.reviews()[1];
+        assert!(review.hidden);
+        assert!(review.flags.is_empty());
+        let restaurant = contract.restaurant("restaurant_1").unwrap();
+        assert_eq!(restaurant.rating_count, 1);
+        assert_eq!(restaurant.average_rating(), Some(5.0));
+
+        // Hiding twice doesn't change the aggregate
+        contract
+            .hide_review(&mut store, &moderator, 1, true)
+            .unwrap();
+        assert_eq!(contract.restaurant("restaurant_1").unwrap().rating_count, 1);
+        contract
+            .hide_review(&mut store, &moderator, 1, false)
+            .unwrap();
+        assert_eq!(contract.restaurant("restaurant_1").unwrap().rating_count, 2);
+
+        // The moderator hands over to someone else
+        assert!(contract.set_moderator(&owner, owner.clone()).is_err());
+        contract.set_moderator(&moderator, reader.clone()).unwrap();
+        assert!(contract
+            .hide_review(&mut store, &moderator, 1, true)
+            .is_err());
+        assert_eq!(
+            RestaurantReview::new()
+                .hide_review(&mut IndexedReviews::default(), &moderator, 0, true)
+                .unwrap_err(),
+            "No moderator configured"
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// Racine (ou noeud) d'un arbre de Merkle creux
+pub type MerkleRoot = [u8; 32];
+
+/// Valeur d'une feuille ou d'un sous-arbre vide
+pub const EMPTY: MerkleRoot = [0; 32];
+
+/// Preuve d'appartenance d'une feuille : les frères du chemin, de la feuille vers la racine
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone, Default)]
+pub struct MerkleProof {
+    pub siblings: Vec<MerkleRoot>,
+}
+
+// Empty subtrees hash to zero, so proofs don't need precomputed default hashes
+fn hash_node(left: &MerkleRoot, right: &MerkleRoot) -> MerkleRoot {
+    if *left == EMPTY && *right == EMPTY {
+        return EMPTY;
+    }
+    let mut hasher = Sha256::new();
+    hasher.update([1]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Hash d'une feuille, jamais égal à [EMPTY]
+pub fn hash_leaf(domain: &[u8], data: &[u8]) -> MerkleRoot {
+    let mut hasher = Sha256::new();
+    hasher.update([0]);
+    hasher.update(domain);
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+fn bit(key: &[u8; 32], position: usize) -> bool {
+    key[position / 8] & (0x80 >> (position % 8)) != 0
+}
+
+/// Garde les `len` premiers bits de la clé
+fn prefix(key: &[u8; 32], len: usize) -> [u8; 32] {
+    let mut prefix = [0; 32];
+    for position in 0..len {
+        if bit(key, position) {
+            prefix[position / 8] |= 0x80 >> (position % 8);
+        }
+    }
+    prefix
+}
+
+fn flip(key: &[u8; 32], position: usize) -> [u8; 32] {
+    let mut key = *key;
+    key[position / 8] ^= 0x80 >> (position % 8);
+    key
+}
+
+/// Clé d'une feuille à partir d'un index, pour un arbre de profondeur 32
+pub fn index_key(index: u32) -> [u8; 32] {
+    let mut key = [0; 32];
+    key[..4].copy_from_slice(&index.to_be_bytes());
+    key
+}
+
+impl MerkleProof {
+    /// Racine obtenue avec `leaf` à la position `key` d'un arbre de profondeur `depth`
+    pub fn compute_root(
+        &self,
+        key: &[u8; 32],
+        depth: usize,
+        leaf: MerkleRoot,
+    ) -> Result<MerkleRoot, String> {
+        if self.siblings.len() != depth {
+            return Err(format!("Merkle proof must have {depth} siblings"));
+        }
+        let mut node = leaf;
+        for (height, sibling) in self.siblings.iter().enumerate() {
+            node = if bit(key, depth - 1 - height) {
+                hash_node(sibling, &node)
+            } else {
+                hash_node(&node, sibling)
+            };
+        }
+        Ok(node)
+    }
+
+    /// Remplace la feuille `old_leaf` par `new_leaf`, après avoir vérifié `old_leaf` sous `root`
+    pub fn update(
+        &self,
+        root: &MerkleRoot,
+        key: &[u8; 32],
+        depth: usize,
+        old_leaf: MerkleRoot,
+        new_leaf: MerkleRoot,
+    ) -> Result<MerkleRoot, String> {
+        if self.compute_root(key, depth, old_leaf)? != *root {
+            return Err("Merkle proof does not match the current root".to_string());
+        }
+        self.compute_root(key, depth, new_leaf)
+    }
+}
+
+/// Arbre de Merkle creux complet, gardé hors chaîne pour produire les preuves
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct SparseMerkleTree {
+    depth: usize,
+    nodes: BTreeMap<(usize, [u8; 32]), MerkleRoot>, // (hauteur, préfixe) -> noeud non vide
+}
+
+impl SparseMerkleTree {
+    pub fn new(depth: usize) -> Self {
+        assert!(depth <= 256, "Merkle tree depth must be at most 256");
+        SparseMerkleTree {
+            depth,
+            nodes: BTreeMap::new(),
+        }
+    }
+
+    fn node(&self, height: usize, prefix: &[u8; 32]) -> MerkleRoot {
+        self.nodes.get(&(height, *prefix)).copied().unwrap_or(EMPTY)
+    }
+
+    fn set_node(&mut self, height: usize, prefix: [u8; 32], node: MerkleRoot) {
+        if node == EMPTY {
+            self.nodes.remove(&(height, prefix));
+        } else {
+            self.nodes.insert((height, prefix), node);
+        }
+    }
+
+    pub fn root(&self) -> MerkleRoot {
+        self.node(self.depth, &[0; 32])
+    }
+
+    pub fn leaf(&self, key: &[u8; 32]) -> MerkleRoot {
+        self.node(0, &prefix(key, self.depth))
+    }
+
+    /// Écrit une feuille et renvoie la nouvelle racine
+    pub fn insert(&mut self, key: &[u8; 32], leaf: MerkleRoot) -> MerkleRoot {
+        let mut path = prefix(key, self.depth);
+        let mut node = leaf;
+        self.set_node(0, path, node);
+        for height in 0..self.depth {
+            let position = self.depth - 1 - height;
+            let sibling = self.node(height, &flip(&path, position));
+            node = if bit(&path, position) {
+                hash_node(&sibling, &node)
+            } else {
+                hash_node(&node, &sibling)
+            };
+            path = prefix(&path, position);
+            self.set_node(height + 1, path, node);
+        }
+        node
+    }
+
+    pub fn proof(&self, key: &[u8; 32]) -> MerkleProof {
+        let mut path = prefix(key, self.depth);
+        let mut siblings = Vec::with_capacity(self.depth);
+        for height in 0..self.depth {
+            let position = self.depth - 1 - height;
+            siblings.push(self.node(height, &flip(&path, position)));
+            path = prefix(&path, position);
+        }
+        MerkleProof { siblings }
+    }
+}
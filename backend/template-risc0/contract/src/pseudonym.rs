@@ -0,0 +1,31 @@
+use sha2::{Digest, Sha256};
+
+/// Secret du reviewer, gardé localement par son client et fourni au contrat en entrée privée
+pub type ReviewerSecret = [u8; 32];
+
+/// Pseudonyme d'un reviewer dans un restaurant, publié à la place de son nom.
+/// Les reviews d'une même personne sont liées dans un restaurant, mais pas d'un restaurant à l'autre.
+pub type Pseudonym = [u8; 32];
+
+/// Pseudonyme du détenteur de `secret` dans le restaurant `restaurant_id`
+pub fn derive_pseudonym(secret: &ReviewerSecret, restaurant_id: &str) -> Pseudonym {
+    let mut hasher = Sha256::new();
+    hasher.update(b"myreview-pseudonym");
+    hasher.update(secret);
+    hasher.update((restaurant_id.len() as u64).to_le_bytes());
+    hasher.update(restaurant_id.as_bytes());
+    hasher.finalize().into()
+}
+
+/// Vérifier que le pseudonyme publié est dérivé du secret fourni en entrée privée
+pub fn check_pseudonym(
+    secret: Option<&ReviewerSecret>,
+    restaurant_id: &str,
+    pseudonym: &Pseudonym,
+) -> Result<(), String> {
+    let secret = secret.ok_or("Missing reviewer secret in private input".to_string())?;
+    if derive_pseudonym(secret, restaurant_id) != *pseudonym {
+        return Err("Pseudonym does not match the reviewer secret".to_string());
+    }
+    Ok(())
+}
@@ -0,0 +1,243 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::Serialize;
+
+use crate::merkle::{hash_leaf, index_key, MerkleProof, MerkleRoot, SparseMerkleTree, EMPTY};
+use crate::pseudonym::ReviewerSecret;
+use crate::visit::{Nullifier, SignedMessage};
+use crate::Review;
+
+/// Profondeur de l'arbre des reviews, indexé par numéro de review
+pub const REVIEW_TREE_DEPTH: usize = 32;
+/// Profondeur de l'arbre des visites utilisées, indexé par nullifier
+pub const NULLIFIER_TREE_DEPTH: usize = 256;
+
+impl Review {
+    /// Feuille de la review dans l'arbre des reviews
+    pub fn leaf(&self) -> MerkleRoot {
+        hash_leaf(
+            b"myreview-review",
+            &borsh::to_vec(self).expect("Failed to encode Review"),
+        )
+    }
+}
+
+fn nullifier_leaf(nullifier: &Nullifier) -> MerkleRoot {
+    hash_leaf(b"myreview-nullifier", nullifier)
+}
+
+fn already_spent(nullifier: &Nullifier) -> String {
+    format!(
+        "A review was already submitted for this visit (nullifier {})",
+        hex::encode(nullifier)
+    )
+}
+
+/// Accès aux reviews et aux visites utilisées, dont l'état du contrat ne garde que les racines
+pub trait ReviewStore {
+    /// Review publiée sous ce numéro
+    fn review(&self, root: &MerkleRoot, review_id: u32) -> Result<Review, String>;
+
+    /// Écrit une review, nouvelle ou modifiée, et renvoie la nouvelle racine
+    fn put_review(
+        &mut self,
+        root: &MerkleRoot,
+        review_id: u32,
+        review: &Review,
+    ) -> Result<MerkleRoot, String>;
+
+    /// Marque une visite comme utilisée et renvoie la nouvelle racine
+    fn spend_nullifier(
+        &mut self,
+        root: &MerkleRoot,
+        nullifier: &Nullifier,
+    ) -> Result<MerkleRoot, String>;
+}
+
+/// Entrée privée d'une action : les données hors de l'état dont le contrat a besoin,
+/// avec les preuves de Merkle qui les lient aux racines de l'état.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Default)]
+pub struct ReviewWitness {
+    pub receipt: Option<SignedMessage>, // Reçu de visite, pour `SubmitReview`
+    pub reviewer_secret: Option<ReviewerSecret>, // Secret du pseudonyme, pour `SubmitReview`
+    pub review: Option<Review>, // Contenu actuel de la review visée, `None` pour une nouvelle
+    pub review_proof: MerkleProof, // Preuve de la review visée
+    pub nullifier_proof: MerkleProof, // Preuve que la visite n'est pas encore utilisée
+}
+
+impl ReviewWitness {
+    pub fn as_private_input(&self) -> Vec<u8> {
+        borsh::to_vec(self).expect("Failed to encode ReviewWitness")
+    }
+}
+
+/// Accès vérifiés dans le zkvm : chaque lecture ou écriture est prouvée par le témoin
+impl ReviewStore for ReviewWitness {
+    fn review(&self, root: &MerkleRoot, review_id: u32) -> Result<Review, String> {
+        let review = self
+            .review
+            .clone()
+            .ok_or("Missing review in private input".to_string())?;
+        let key = index_key(review_id);
+        if self
+            .review_proof
+            .compute_root(&key, REVIEW_TREE_DEPTH, review.leaf())?
+            != *root
+        {
+            return Err(format!(
+                "Review {review_id} does not match the reviews root"
+            ));
+        }
+        Ok(review)
+    }
+
+    fn put_review(
+        &mut self,
+        root: &MerkleRoot,
+        review_id: u32,
+        review: &Review,
+    ) -> Result<MerkleRoot, String> {
+        let old_leaf = self.review.as_ref().map(Review::leaf).unwrap_or(EMPTY);
+        let root = self.review_proof.update(
+            root,
+            &index_key(review_id),
+            REVIEW_TREE_DEPTH,
+            old_leaf,
+            review.leaf(),
+        )?;
+        self.review = Some(review.clone());
+        Ok(root)
+    }
+
+    fn spend_nullifier(
+        &mut self,
+        root: &MerkleRoot,
+        nullifier: &Nullifier,
+    ) -> Result<MerkleRoot, String> {
+        let leaf = nullifier_leaf(nullifier);
+        if self
+            .nullifier_proof
+            .compute_root(nullifier, NULLIFIER_TREE_DEPTH, leaf)?
+            == *root
+        {
+            return Err(already_spent(nullifier));
+        }
+        self.nullifier_proof
+            .update(root, nullifier, NULLIFIER_TREE_DEPTH, EMPTY, leaf)
+    }
+}
+
+/// Reviews complètes reconstruites hors chaîne à partir des blobs,
+/// qui servent à répondre aux lectures et à produire les témoins.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Debug, Clone)]
+pub struct IndexedReviews {
+    reviews: Vec<Review>,
+    #[serde(skip)]
+    review_tree: SparseMerkleTree,
+    #[serde(skip)]
+    nullifier_tree: SparseMerkleTree,
+}
+
+impl Default for IndexedReviews {
+    fn default() -> Self {
+        IndexedReviews {
+            reviews: vec![],
+            review_tree: SparseMerkleTree::new(REVIEW_TREE_DEPTH),
+            nullifier_tree: SparseMerkleTree::new(NULLIFIER_TREE_DEPTH),
+        }
+    }
+}
+
+impl IndexedReviews {
+    /// Reviews dans l'ordre de publication, le numéro d'une review est sa position
+    pub fn reviews(&self) -> &[Review] {
+        &self.reviews
+    }
+
+    pub fn is_nullifier_spent(&self, nullifier: &Nullifier) -> bool {
+        self.nullifier_tree.leaf(nullifier) != EMPTY
+    }
+
+    /// Preuve d'appartenance de la review, ou de la place vide d'une nouvelle review
+    pub fn review_proof(&self, review_id: u32) -> MerkleProof {
+        self.review_tree.proof(&index_key(review_id))
+    }
+
+    /// Preuve de la feuille d'un nullifier, vide tant que la visite n'est pas utilisée
+    pub fn nullifier_proof(&self, nullifier: &Nullifier) -> MerkleProof {
+        self.nullifier_tree.proof(nullifier)
+    }
+
+    /// Témoin d'une nouvelle review
+    pub fn submit_witness(
+        &self,
+        receipt: SignedMessage,
+        reviewer_secret: ReviewerSecret,
+        nullifier: &Nullifier,
+    ) -> ReviewWitness {
+        ReviewWitness {
+            receipt: Some(receipt),
+            reviewer_secret: Some(reviewer_secret),
+            review: None,
+            review_proof: self.review_proof(self.reviews.len() as u32),
+            nullifier_proof: self.nullifier_proof(nullifier),
+        }
+    }
+
+    /// Témoin d'une action sur une review existante
+    pub fn review_witness(&self, review_id: u32) -> ReviewWitness {
+        ReviewWitness {
+            review: self.reviews.get(review_id as usize).cloned(),
+            review_proof: self.review_proof(review_id),
+            ..Default::default()
+        }
+    }
+
+    fn check_root(tree: &SparseMerkleTree, root: &MerkleRoot) -> Result<(), String> {
+        if tree.root() != *root {
+            return Err("Indexed reviews are out of sync with the contract state".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl ReviewStore for IndexedReviews {
+    fn review(&self, root: &MerkleRoot, review_id: u32) -> Result<Review, String> {
+        Self::check_root(&self.review_tree, root)?;
+        self.reviews
+            .get(review_id as usize)
+            .cloned()
+            .ok_or(format!("Review {review_id} not found"))
+    }
+
+    fn put_review(
+        &mut self,
+        root: &MerkleRoot,
+        review_id: u32,
+        review: &Review,
+    ) -> Result<MerkleRoot, String> {
+        Self::check_root(&self.review_tree, root)?;
+        let count = self.reviews.len();
+        match self.reviews.get_mut(review_id as usize) {
+            Some(stored) => *stored = review.clone(),
+            None if review_id as usize == count => self.reviews.push(review.clone()),
+            None => return Err(format!("Review {review_id} not found")),
+        }
+        Ok(self
+            .review_tree
+            .insert(&index_key(review_id), review.leaf()))
+    }
+
+    fn spend_nullifier(
+        &mut self,
+        root: &MerkleRoot,
+        nullifier: &Nullifier,
+    ) -> Result<MerkleRoot, String> {
+        Self::check_root(&self.nullifier_tree, root)?;
+        if self.is_nullifier_spent(nullifier) {
+            return Err(already_spent(nullifier));
+        }
+        Ok(self
+            .nullifier_tree
+            .insert(nullifier, nullifier_leaf(nullifier)))
+    }
+}
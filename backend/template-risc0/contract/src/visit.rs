@@ -0,0 +1,120 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::pseudonym::ReviewerSecret;
+
+/// Identifiant unique d'une visite, publié à la place du `visit_id`
+pub type Nullifier = [u8; 32];
+
+/// Durée pendant laquelle un reçu permet de publier une review (30 jours)
+pub const VISIT_VALIDITY_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// Schéma de signature utilisé par un restaurant pour signer ses reçus
+#[derive(
+    BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq,
+)]
+pub enum SignatureScheme {
+    Ed25519,
+    Secp256k1,
+}
+
+/// Reçu de visite signé par le restaurant (contenu du QR code).
+/// Il est fourni au contrat dans l'entrée privée : il est vérifié dans le zkvm sans être publié.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone)]
+pub struct SignedMessage {
+    pub restaurant_id: String,
+    pub visit_id: String,
+    pub timestamp: u64,
+    pub signature: String, // Signature hexadécimale de `signing_payload`
+}
+
+impl SignatureScheme {
+    /// Vérifie qu'une clé publique est bien encodée pour ce schéma
+    pub fn check_public_key(&self, public_key: &[u8]) -> Result<(), String> {
+        match self {
+            SignatureScheme::Ed25519 => {
+                let bytes: &[u8; 32] = public_key
+                    .try_into()
+                    .map_err(|_| "Ed25519 public key must be 32 bytes".to_string())?;
+                ed25519_dalek::VerifyingKey::from_bytes(bytes)
+                    .map_err(|_| "Invalid Ed25519 public key".to_string())?;
+            }
+            SignatureScheme::Secp256k1 => {
+                k256::ecdsa::VerifyingKey::from_sec1_bytes(public_key)
+                    .map_err(|_| "Invalid Secp256k1 public key".to_string())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Vérifie une signature sur `message`.
+    /// Les signatures secp256k1 doivent être normalisées (low-s) pour qu'un reçu
+    /// n'ait qu'une seule signature valide.
+    pub fn verify(
+        &self,
+        public_key: &[u8],
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<(), String> {
+        match self {
+            SignatureScheme::Ed25519 => {
+                let bytes: &[u8; 32] = public_key
+                    .try_into()
+                    .map_err(|_| "Ed25519 public key must be 32 bytes".to_string())?;
+                let key = ed25519_dalek::VerifyingKey::from_bytes(bytes)
+                    .map_err(|_| "Invalid Ed25519 public key".to_string())?;
+                let signature = ed25519_dalek::Signature::from_slice(signature)
+                    .map_err(|_| "Invalid Ed25519 signature encoding".to_string())?;
+                key.verify_strict(message, &signature)
+                    .map_err(|_| "Invalid visit signature".to_string())
+            }
+            SignatureScheme::Secp256k1 => {
+                use k256::ecdsa::signature::Verifier;
+
+                let key = k256::ecdsa::VerifyingKey::from_sec1_bytes(public_key)
+                    .map_err(|_| "Invalid Secp256k1 public key".to_string())?;
+                let signature = k256::ecdsa::Signature::from_slice(signature)
+                    .map_err(|_| "Invalid Secp256k1 signature encoding".to_string())?;
+                if signature.normalize_s().is_some() {
+                    return Err("Secp256k1 signature must be low-s normalized".to_string());
+                }
+                key.verify(message, &signature)
+                    .map_err(|_| "Invalid visit signature".to_string())
+            }
+        }
+    }
+}
+
+impl SignedMessage {
+    /// Octets signés par le restaurant pour une visite.
+    /// Chaque champ est précédé de sa longueur : deux reçus différents ne signent jamais les mêmes octets.
+    pub fn signing_payload(restaurant_id: &str, visit_id: &str, timestamp: u64) -> Vec<u8> {
+        let mut payload = b"myreview-visit".to_vec();
+        for field in [restaurant_id, visit_id] {
+            payload.extend_from_slice(&(field.len() as u64).to_le_bytes());
+            payload.extend_from_slice(field.as_bytes());
+        }
+        payload.extend_from_slice(&timestamp.to_le_bytes());
+        payload
+    }
+
+    pub fn signature_bytes(&self) -> Result<Vec<u8>, String> {
+        hex::decode(&self.signature).map_err(|_| "Signature is not valid hex".to_string())
+    }
+
+    /// Calcule le nullifier de la visite pour le reviewer qui détient `secret`.
+    /// Le restaurant connaît le reçu mais pas le secret du client : il ne peut pas retrouver
+    /// la visite à partir du nullifier publié. Le reçu n'étant lié à aucun reviewer,
+    /// il ne doit être remis qu'au client de la visite.
+    pub fn nullifier(&self, secret: &ReviewerSecret) -> Nullifier {
+        let mut hasher = Sha256::new();
+        hasher.update(b"myreview-nullifier");
+        hasher.update(secret);
+        hasher.update((self.restaurant_id.len() as u64).to_le_bytes());
+        hasher.update(self.restaurant_id.as_bytes());
+        hasher.update((self.visit_id.len() as u64).to_le_bytes());
+        hasher.update(self.visit_id.as_bytes());
+        hasher.finalize().into()
+    }
+}
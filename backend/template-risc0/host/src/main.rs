@@ -1,10 +1,32 @@
-use anyhow::Result;
-use clap::{Parser, Subcommand};
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use client_sdk::helpers::risc0::Risc0Prover;
-use contract::{RestaurantReview, RestaurantReviewAction};
-    use sdk::api::APIRegisterContract;
-use sdk::{BlobTransaction, ProofTransaction};
-use sdk::{ContractInput, Digestable};
+use client_sdk::rest_client::{IndexerApiHttpClient, NodeApiHttpClient};
+use contract::merkle::MerkleProof;
+use contract::pseudonym::{derive_pseudonym, ReviewerSecret};
+use contract::store::ReviewWitness;
+use contract::visit::{SignatureScheme, SignedMessage};
+use contract::{RestaurantReview, RestaurantReviewAction, Review, IDENTITY_CONTRACT};
+use hydentity::client::metadata::HYDENTITY_ELF;
+use hydentity::Hydentity;
+use sdk::api::APIRegisterContract;
+use sdk::identity_provider::IdentityAction;
+use sdk::{
+    Blob, ContractAction, ContractInput, Digestable, RegisterContractAction,
+    UpdateContractProgramAction,
+};
+use sdk::{BlobTransaction, ContractName, Hashed, ProofTransaction, TxContext, TxHash};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+mod receipts;
+mod review;
+mod serve;
+
+use receipts::{read_visits, write_receipt, QrFormat, RestaurantKey};
+use review::{Progress, ReviewContext, ReviewRequest};
 
 // Ces constantes représentent l'ELF RISC-V et l'ID d'image généré par risc0-build.
 // L'ELF est utilisé pour prouver et l'ID pour vérifier.
@@ -20,167 +42,691 @@ struct Cli {
     #[arg(long, default_value = "http://localhost:4321")]
     pub host: String,
 
+    /// Indexer of the review and hydentity contracts, run from `indexer/`
+    #[arg(long, default_value = "http://localhost:4322")]
+    pub indexer: String,
+
     #[arg(long, default_value = "restaurant_review")]
     pub contract_name: String,
 }
 
 #[derive(Subcommand)]
 enum Commands {
-    RegisterContract {},
+    RegisterContract {
+        /// Identité du modérateur, de la forme <nom>.hydentity
+        #[arg(long)]
+        moderator: Option<String>,
+        /// Nombre de blocs avant qu'une transaction non prouvée expire,
+        /// borné par le protocole (entre 10 et 10 000)
+        #[arg(long)]
+        timeout_window: Option<u64>,
+        /// Propriétaire du contrat, de la forme <nom>.hydentity : lui seul pourra le mettre à jour
+        /// ou le supprimer. Sans propriétaire, le contrat est enregistré par l'API du nœud.
+        #[arg(long, requires = "password")]
+        owner: Option<String>,
+        /// Mot de passe hydentity du propriétaire
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// Remplace le programme du contrat par celui de ce build, en conservant son état
+    UpgradeContract {
+        #[command(flatten)]
+        owner: IdentityArgs,
+    },
     RegisterRestaurant {
         id: String,
-        public_key: String,
+        #[command(flatten)]
+        key: PublicKeyArgs,
+        #[command(flatten)]
+        owner: IdentityArgs,
+    },
+    RotateRestaurantKey {
+        id: String,
+        #[command(flatten)]
+        key: PublicKeyArgs,
+        #[command(flatten)]
+        owner: IdentityArgs,
+    },
+    /// Génère et enregistre localement la clé de signature des reçus d'un restaurant
+    GenerateRestaurantKey {
+        restaurant_id: String,
+        #[arg(long, value_enum, default_value_t = Scheme::Ed25519)]
+        scheme: Scheme,
+        #[arg(long, default_value = "restaurant.key")]
+        key_file: PathBuf,
+    },
+    /// Signe le reçu d'une visite
+    IssueReceipt {
+        visit_id: String,
+        /// Heure de la visite en secondes Unix, maintenant par défaut
+        #[arg(long)]
+        timestamp: Option<u64>,
+        #[command(flatten)]
+        output: ReceiptArgs,
+    },
+    /// Signe les reçus de toutes les visites d'une journée
+    IssueReceipts {
+        /// Fichier des visites, une par ligne : `visit_id` ou `visit_id,timestamp`
+        #[arg(long, required_unless_present = "count", conflicts_with = "count")]
+        visits: Option<PathBuf>,
+        /// Nombre de reçus à signer pour des visites d'identifiants aléatoires
+        #[arg(long)]
+        count: Option<u32>,
+        #[command(flatten)]
+        output: ReceiptArgs,
+    },
+    TransferRestaurantOwnership {
+        id: String,
+        /// Identité du nouveau propriétaire, de la forme <nom>.hydentity
+        new_owner: String,
+        #[command(flatten)]
+        owner: IdentityArgs,
+    },
+    RespondToReview {
+        /// Numéro de la review, dans l'ordre de publication
+        review_id: u32,
+        response: String,
+        #[command(flatten)]
+        owner: IdentityArgs,
+    },
+    FlagReview {
+        /// Numéro de la review, dans l'ordre de publication
+        review_id: u32,
+        reason: String,
+        #[command(flatten)]
+        identity: IdentityArgs,
+    },
+    HideReview {
+        /// Numéro de la review, dans l'ordre de publication
+        review_id: u32,
+        /// Réafficher la review au lieu de la masquer
+        #[arg(long)]
+        unhide: bool,
+        #[command(flatten)]
+        moderator: IdentityArgs,
+    },
+    SetModerator {
+        /// Identité du nouveau modérateur, de la forme <nom>.hydentity
+        new_moderator: String,
+        #[command(flatten)]
+        moderator: IdentityArgs,
     },
     SubmitReview {
         restaurant_id: String,
-        client_name: String,
         review_text: String,
         signed_message: String,
-        timestamp: u64,
+        /// Note de 1 à 5 étoiles
+        #[arg(long)]
+        rating: u8,
+        #[command(flatten)]
+        reviewer: ReviewerArgs,
+    },
+    /// Lance un service HTTP qui prouve et publie les reviews qu'on lui soumet
+    Serve {
+        #[arg(long, default_value_t = 3003)]
+        port: u16,
+    },
+    /// Affiche le pseudonyme du reviewer dans un restaurant
+    Pseudonym {
+        restaurant_id: String,
+        #[command(flatten)]
+        reviewer: ReviewerArgs,
     },
 }
 
+/// Clé publique d'un restaurant, donnée directement ou lue dans son fichier de clé
+#[derive(Args)]
+struct PublicKeyArgs {
+    /// Clé publique hexadécimale du restaurant
+    #[arg(required_unless_present = "key_file")]
+    public_key: Option<String>,
+    #[arg(long, value_enum, default_value_t = Scheme::Ed25519)]
+    scheme: Scheme,
+    /// Fichier de clé créé par `GenerateRestaurantKey`, à la place de `public_key` et `--scheme`
+    #[arg(long, conflicts_with = "public_key")]
+    key_file: Option<PathBuf>,
+}
+
+impl PublicKeyArgs {
+    fn public_key(&self, restaurant_id: &str) -> Result<(Vec<u8>, SignatureScheme)> {
+        let Some(key_file) = &self.key_file else {
+            let scheme = SignatureScheme::from(self.scheme);
+            let public_key = self.public_key.as_deref().unwrap_or_default();
+            return Ok((parse_public_key(public_key, scheme)?, scheme));
+        };
+        let key = RestaurantKey::load(key_file)?;
+        if key.restaurant_id != restaurant_id {
+            bail!(
+                "{} is the key of restaurant {}, not {}",
+                key_file.display(),
+                key.restaurant_id,
+                restaurant_id
+            );
+        }
+        Ok((key.public_key()?, key.scheme))
+    }
+}
+
+/// Clé du restaurant qui signe les reçus, et où les écrire
+#[derive(Args)]
+struct ReceiptArgs {
+    #[arg(long, default_value = "restaurant.key")]
+    key_file: PathBuf,
+    /// Dossier où écrire les reçus
+    #[arg(long, default_value = "receipts")]
+    out_dir: PathBuf,
+    /// Écrire aussi le QR code de chaque reçu, à imprimer sur le ticket
+    #[arg(long, value_enum)]
+    qr: Option<QrFormat>,
+}
+
+impl ReceiptArgs {
+    fn issue(&self, visits: Vec<(String, u64)>) -> Result<()> {
+        let key = RestaurantKey::load(&self.key_file)?;
+        for (visit_id, timestamp) in visits {
+            let receipt = key.issue_receipt(visit_id, timestamp)?;
+            for path in write_receipt(&receipt, &self.out_dir, self.qr)? {
+                println!("✅ {}", path.display());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Crée un fichier lisible par son seul propriétaire, sans jamais écraser un fichier existant
+fn write_private_file(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new();
+    file.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut file, 0o600);
+    file.open(path)
+        .and_then(|mut file| file.write_all(contents))
+        .context(format!("Failed to write {}", path.display()))
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|now| now.as_secs())
+        .unwrap_or_default()
+}
+
+/// Secret du reviewer, dont sont dérivés ses pseudonymes
+#[derive(Args)]
+struct ReviewerArgs {
+    /// Fichier du secret, créé au premier usage. Le perdre empêche de reprendre ses pseudonymes.
+    #[arg(long, default_value = "reviewer.secret")]
+    secret_file: PathBuf,
+}
+
+impl ReviewerArgs {
+    /// Lit le secret du reviewer, ou en génère un nouveau s'il n'existe pas encore
+    fn load_or_create(&self) -> Result<ReviewerSecret> {
+        if self.secret_file.exists() {
+            let secret = std::fs::read_to_string(&self.secret_file)
+                .context(format!("Failed to read {}", self.secret_file.display()))?;
+            return hex::decode(secret.trim())
+                .ok()
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or(anyhow!(
+                    "{} does not contain a valid reviewer secret",
+                    self.secret_file.display()
+                ));
+        }
+
+        let secret: ReviewerSecret = rand::random();
+        write_private_file(&self.secret_file, hex::encode(secret).as_bytes())?;
+        println!(
+            "🔑 New reviewer secret saved to {}",
+            self.secret_file.display()
+        );
+        Ok(secret)
+    }
+}
+
+/// Identité hydentity de l'auteur de l'action
+#[derive(Args)]
+struct IdentityArgs {
+    /// Identité de l'auteur, de la forme <nom>.hydentity
+    #[arg(long)]
+    identity: String,
+    /// Mot de passe hydentity de l'auteur
+    #[arg(long)]
+    password: String,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Scheme {
+    Ed25519,
+    Secp256k1,
+}
+
+impl From<Scheme> for SignatureScheme {
+    fn from(scheme: Scheme) -> Self {
+        match scheme {
+            Scheme::Ed25519 => SignatureScheme::Ed25519,
+            Scheme::Secp256k1 => SignatureScheme::Secp256k1,
+        }
+    }
+}
+
+/// Contexte de publication des reviews, qui prouve avec le guest du contrat
+fn review_context(
+    client: NodeApiHttpClient,
+    indexer_url: String,
+    contract_name: &str,
+) -> ReviewContext {
+    ReviewContext {
+        client,
+        indexer_url,
+        contract_name: contract_name.to_string(),
+        prover: Risc0Prover::new(GUEST_ELF),
+    }
+}
+
+/// Attend que la transaction blob soit séquencée, et renvoie son contexte (bloc, timestamp)
+async fn wait_for_tx_context(client: &NodeApiHttpClient, tx_hash: &TxHash) -> Result<TxContext> {
+    for _ in 0..30 {
+        if let Ok(tx) = client.get_unsettled_tx(tx_hash).await {
+            return Ok((*tx.tx_context).clone());
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    }
+    bail!("Blob transaction {tx_hash} was not sequenced in time")
+}
+
+/// Récupère l'état courant du contrat sur le nœud
+async fn fetch_state(client: &NodeApiHttpClient, contract_name: &str) -> Result<RestaurantReview> {
+    let contract = client.get_contract(&contract_name.into()).await?;
+    RestaurantReview::try_from(contract.state).map_err(|e| anyhow!(e))
+}
+
+/// Lit une route de l'indexeur du contrat (`/review/{id}`, `/nullifier/{nullifier}`...)
+async fn fetch_from_indexer<T: DeserializeOwned>(
+    indexer: &str,
+    contract_name: &str,
+    route: &str,
+) -> Result<T> {
+    let url = format!("{indexer}/v1/indexer/contract/{contract_name}/{route}");
+    reqwest::get(&url)
+        .await?
+        .error_for_status()
+        .context(format!("Failed to get {route} from the indexer"))?
+        .json()
+        .await
+        .context(format!("Invalid response for {route}"))
+}
+
+#[derive(Deserialize)]
+struct ReviewResponse {
+    review: Option<Review>,
+    proof: MerkleProof,
+}
+
+#[derive(Deserialize)]
+struct NullifierResponse {
+    spent: bool,
+    proof: MerkleProof,
+}
+
+/// Témoin d'une action sur une review existante : son contenu et sa preuve de Merkle
+async fn fetch_review_witness(
+    indexer: &str,
+    contract_name: &str,
+    review_id: u32,
+) -> Result<ReviewWitness> {
+    let response: ReviewResponse =
+        fetch_from_indexer(indexer, contract_name, &format!("review/{review_id}")).await?;
+    let review = response
+        .review
+        .ok_or(anyhow!("Review {review_id} not found"))?;
+    Ok(ReviewWitness {
+        review: Some(review),
+        review_proof: response.proof,
+        ..Default::default()
+    })
+}
+
+/// Vérifie et encode une clé publique passée en hexadécimal
+fn parse_public_key(public_key: &str, scheme: SignatureScheme) -> Result<Vec<u8>> {
+    let public_key = hex::decode(public_key).context("Public key is not valid hex")?;
+    scheme
+        .check_public_key(&public_key)
+        .map_err(|e| anyhow!(e))?;
+    Ok(public_key)
+}
+
+/// Envoie une action qui nécessite une identité vérifiée (propriétaire, modérateur...).
+/// La transaction contient aussi un blob `VerifyIdentity` de hydentity, prouvé avec le mot de passe.
+async fn send_verified_action(
+    client: &NodeApiHttpClient,
+    indexer: &IndexerApiHttpClient,
+    contract_name: &str,
+    author: IdentityArgs,
+    action: RestaurantReviewAction,
+    witness: ReviewWitness,
+) -> Result<()> {
+    let hydentity_name: ContractName = IDENTITY_CONTRACT.into();
+    let hydentity: Hydentity = indexer.fetch_current_state(&hydentity_name).await?;
+    let nonce = hydentity
+        .get_nonce(&author.identity)
+        .map_err(|e| anyhow!(e))?;
+
+    // Construire la transaction blob
+    let blobs = vec![
+        IdentityAction::VerifyIdentity {
+            account: author.identity.clone(),
+            nonce,
+        }
+        .as_blob(hydentity_name.clone()),
+        action.as_blob(contract_name),
+    ];
+    let blob_tx = BlobTransaction::new(author.identity.clone(), blobs.clone());
+
+    // Exécuter le contrat localement pour obtenir une erreur lisible avant d'envoyer
+    let state = fetch_state(client, contract_name).await?;
+    let inputs = ContractInput {
+        state: state.as_bytes()?,
+        identity: author.identity.clone().into(),
+        tx_hash: blob_tx.hashed(),
+        private_input: witness.as_private_input(),
+        tx_ctx: None,
+        blobs: blobs.clone(),
+        index: sdk::BlobIndex(1),
+    };
+    let (_, output) = sdk::guest::execute::<RestaurantReview>(&inputs);
+    if !output.success {
+        bail!(
+            "Action rejected by the contract: {}",
+            String::from_utf8_lossy(&output.program_outputs)
+        );
+    }
+
+    // Envoyer la transaction blob
+    let blob_tx_hash = client.send_tx_blob(&blob_tx).await?;
+    println!("✅ Transaction blob envoyée. Hash : {}", blob_tx_hash);
+
+    // Générer et envoyer une preuve pour chaque blob
+    let identity_inputs = ContractInput {
+        state: hydentity.as_bytes()?,
+        private_input: author.password.into_bytes(),
+        index: sdk::BlobIndex(0),
+        ..inputs.clone()
+    };
+    let proofs = [
+        (
+            hydentity_name,
+            Risc0Prover::new(HYDENTITY_ELF),
+            identity_inputs,
+        ),
+        (contract_name.into(), Risc0Prover::new(GUEST_ELF), inputs),
+    ];
+    for (contract_name, prover, inputs) in proofs {
+        let proof = prover.prove(inputs).await?;
+        let proof_tx_hash = client
+            .send_tx_proof(&ProofTransaction {
+                proof,
+                contract_name,
+            })
+            .await?;
+        println!("✅ Transaction de preuve envoyée. Hash : {}", proof_tx_hash);
+    }
+    Ok(())
+}
+
+/// Envoie une action du TLD `hyle` au nom du propriétaire du contrat.
+/// Seul le blob `VerifyIdentity` de hydentity est à prouver : le nœud traite lui-même le blob `hyle`
+/// et vérifie que l'identité prouvée est bien celle du propriétaire.
+async fn send_owner_action(
+    client: &NodeApiHttpClient,
+    indexer: &IndexerApiHttpClient,
+    owner: IdentityArgs,
+    hyle_blob: Blob,
+) -> Result<()> {
+    let hydentity_name: ContractName = IDENTITY_CONTRACT.into();
+    let hydentity: Hydentity = indexer.fetch_current_state(&hydentity_name).await?;
+    let nonce = hydentity
+        .get_nonce(&owner.identity)
+        .map_err(|e| anyhow!(e))?;
+
+    let blobs = vec![
+        IdentityAction::VerifyIdentity {
+            account: owner.identity.clone(),
+            nonce,
+        }
+        .as_blob(hydentity_name.clone()),
+        hyle_blob,
+    ];
+    let blob_tx = BlobTransaction::new(owner.identity.clone(), blobs.clone());
+    let blob_tx_hash = client.send_tx_blob(&blob_tx).await?;
+    println!("✅ Transaction blob envoyée. Hash : {}", blob_tx_hash);
+
+    let inputs = ContractInput {
+        state: hydentity.as_bytes()?,
+        identity: owner.identity.into(),
+        tx_hash: blob_tx.hashed(),
+        private_input: owner.password.into_bytes(),
+        tx_ctx: None,
+        blobs,
+        index: sdk::BlobIndex(0),
+    };
+    let proof = Risc0Prover::new(HYDENTITY_ELF).prove(inputs).await?;
+    let proof_tx_hash = client
+        .send_tx_proof(&ProofTransaction {
+            proof,
+            contract_name: hydentity_name,
+        })
+        .await?;
+    println!("✅ Transaction de preuve envoyée. Hash : {}", proof_tx_hash);
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
     // Client pour envoyer des requêtes au nœud
-    let client = client_sdk::rest_client::NodeApiHttpClient::new(cli.host)?;
+    let client = NodeApiHttpClient::new(cli.host)?;
+    let indexer_url = cli.indexer.trim_end_matches('/').to_string();
+    let indexer = IndexerApiHttpClient::new(cli.indexer)?;
     let contract_name = &cli.contract_name;
 
-    // Utilisé pour générer des preuves zk de l'exécution.
-    let prover = Risc0Prover::new(GUEST_ELF);
-
-    // Cet exemple utilise une identité factice. Les identités sont des champs obligatoires.
-    let identity = format!("none.{}", contract_name);
-
     match cli.command {
-        Commands::RegisterContract {} => {
+        Commands::RegisterContract {
+            moderator,
+            timeout_window,
+            owner,
+            password,
+        } => {
             // Construire l'état initial du contrat
-            let initial_state = RestaurantReview::new();
+            let mut initial_state = RestaurantReview::new();
+            if let Some(moderator) = moderator {
+                initial_state = initial_state.with_moderator(moderator.into());
+            }
 
             // Envoyer la transaction pour enregistrer le contrat
-            let res = client
-                .register_contract(&APIRegisterContract {
-                    verifier: "risc0".into(),
-                    program_id: sdk::ProgramId(sdk::to_u8_array(&GUEST_ID).to_vec()),
-                    state_digest: initial_state.as_digest(),
-                    contract_name: contract_name.clone().into(),
-                })
-                .await?;
-            println!("✅ Contrat enregistré. Hash de la transaction : {}", res);
+            let registration = APIRegisterContract {
+                verifier: "risc0-1".into(),
+                program_id: sdk::ProgramId(sdk::to_u8_array(&GUEST_ID).to_vec()),
+                state_digest: initial_state.as_digest(),
+                contract_name: contract_name.clone().into(),
+                timeout_window: timeout_window.map(sdk::BlockHeight),
+            };
+            match owner.zip(password) {
+                Some((identity, password)) => {
+                    let action = RegisterContractAction {
+                        verifier: registration.verifier,
+                        program_id: registration.program_id,
+                        state_digest: registration.state_digest,
+                        contract_name: registration.contract_name,
+                        timeout_window: registration.timeout_window,
+                    };
+                    let blob = action.as_blob("hyle".into(), None, None);
+                    let owner = IdentityArgs { identity, password };
+                    send_owner_action(&client, &indexer, owner, blob).await?;
+                }
+                None => {
+                    let res = client.register_contract(&registration).await?;
+                    println!("✅ Contrat enregistré. Hash de la transaction : {}", res);
+                }
+            }
         }
-        Commands::RegisterRestaurant { id, public_key } => {
-            // Construire l'action pour enregistrer un restaurant
+        Commands::UpgradeContract { owner } => {
+            let action = UpdateContractProgramAction {
+                contract_name: contract_name.clone().into(),
+                verifier: "risc0-1".into(),
+                program_id: sdk::ProgramId(sdk::to_u8_array(&GUEST_ID).to_vec()),
+            };
+            let blob = action.as_blob("hyle".into(), None, None);
+            send_owner_action(&client, &indexer, owner, blob).await?;
+        }
+        Commands::RegisterRestaurant { id, key, owner } => {
+            let (public_key, scheme) = key.public_key(&id)?;
             let action = RestaurantReviewAction::RegisterRestaurant {
                 id,
-                public_key: public_key.into_bytes(),
+                public_key,
+                scheme,
             };
-
-            // Construire la transaction blob
-            let blobs = vec![action.as_blob(contract_name)];
-            let blob_tx = BlobTransaction::new(identity.clone(), blobs.clone());
-
-            // Envoyer la transaction blob
-            let blob_tx_hash = client.send_tx_blob(&blob_tx).await.unwrap();
-            println!("✅ Transaction blob envoyée. Hash : {}", blob_tx_hash);
-
-            // Générer la preuve zk
-            let inputs = ContractInput {
-                state: Vec::new(), // État initial non nécessaire pour cette action
-                identity: identity.clone().into(),
-                tx_hash: blob_tx_hash,
-                private_input: vec![],
-                tx_ctx: None,
-                blobs: blobs.clone(),
-                index: sdk::BlobIndex(0),
+            let witness = ReviewWitness::default();
+            send_verified_action(&client, &indexer, contract_name, owner, action, witness).await?;
+        }
+        Commands::RotateRestaurantKey { id, key, owner } => {
+            let (public_key, scheme) = key.public_key(&id)?;
+            let action = RestaurantReviewAction::RotateRestaurantKey {
+                id,
+                public_key,
+                scheme,
             };
-
-            let proof = prover.prove(inputs).await.unwrap();
-
-            // Construire la transaction de preuve
-            let proof_tx = ProofTransaction {
-                proof,
-                contract_name: contract_name.clone().into(),
+            let witness = ReviewWitness::default();
+            send_verified_action(&client, &indexer, contract_name, owner, action, witness).await?;
+        }
+        Commands::GenerateRestaurantKey {
+            restaurant_id,
+            scheme,
+            key_file,
+        } => {
+            let key = RestaurantKey::generate(restaurant_id, scheme.into());
+            key.save(&key_file)?;
+            println!("🔑 Restaurant key saved to {}", key_file.display());
+            println!("Public key: {}", hex::encode(key.public_key()?));
+        }
+        Commands::IssueReceipt {
+            visit_id,
+            timestamp,
+            output,
+        } => {
+            output.issue(vec![(visit_id, timestamp.unwrap_or_else(now_secs))])?;
+        }
+        Commands::IssueReceipts {
+            visits,
+            count,
+            output,
+        } => {
+            let visits = match visits {
+                Some(visits) => read_visits(&visits, now_secs())?,
+                None => (0..count.unwrap_or_default())
+                    .map(|_| (hex::encode(rand::random::<[u8; 16]>()), now_secs()))
+                    .collect(),
             };
-
-            // Envoyer la transaction de preuve
-            let proof_tx_hash = client.send_tx_proof(&proof_tx).await.unwrap();
-            println!("✅ Transaction de preuve envoyée. Hash : {}", proof_tx_hash);
+            output.issue(visits)?;
+        }
+        Commands::TransferRestaurantOwnership {
+            id,
+            new_owner,
+            owner,
+        } => {
+            let action = RestaurantReviewAction::TransferRestaurantOwnership {
+                id,
+                new_owner: new_owner.into(),
+            };
+            let witness = ReviewWitness::default();
+            send_verified_action(&client, &indexer, contract_name, owner, action, witness).await?;
+        }
+        Commands::RespondToReview {
+            review_id,
+            response,
+            owner,
+        } => {
+            let witness = fetch_review_witness(&indexer_url, contract_name, review_id).await?;
+            let action = RestaurantReviewAction::RespondToReview {
+                review_id,
+                response,
+            };
+            send_verified_action(&client, &indexer, contract_name, owner, action, witness).await?;
+        }
+        Commands::FlagReview {
+            review_id,
+            reason,
+            identity,
+        } => {
+            let witness = fetch_review_witness(&indexer_url, contract_name, review_id).await?;
+            let action = RestaurantReviewAction::FlagReview { review_id, reason };
+            send_verified_action(&client, &indexer, contract_name, identity, action, witness)
+                .await?;
+        }
+        Commands::HideReview {
+            review_id,
+            unhide,
+            moderator,
+        } => {
+            let witness = fetch_review_witness(&indexer_url, contract_name, review_id).await?;
+            let action = RestaurantReviewAction::HideReview {
+                review_id,
+                hidden: !unhide,
+            };
+            send_verified_action(&client, &indexer, contract_name, moderator, action, witness)
+                .await?;
+        }
+        Commands::SetModerator {
+            new_moderator,
+            moderator,
+        } => {
+            let action = RestaurantReviewAction::SetModerator {
+                moderator: new_moderator.into(),
+            };
+            let witness = ReviewWitness::default();
+            send_verified_action(&client, &indexer, contract_name, moderator, action, witness)
+                .await?;
+        }
+        Commands::Pseudonym {
+            restaurant_id,
+            reviewer,
+        } => {
+            let secret = reviewer.load_or_create()?;
+            println!("{}", hex::encode(derive_pseudonym(&secret, &restaurant_id)));
         }
-        
-        
         Commands::SubmitReview {
             restaurant_id,
-            client_name,
             review_text,
             signed_message,
-            timestamp,
+            rating,
+            reviewer,
         } => {
-            // Generate the zk proof from the signed message
-            // Calculate time range (last 30 days)
-            let current_time = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs();
-            let time_range_start = current_time - (30 * 24 * 60 * 60); // 30 days ago
-            let time_range_end = current_time;
-            
-            // Prepare inputs for the zk proof generation
-            let inputs = vec![
-                signed_message.as_bytes().to_vec(),
-                time_range_start.to_string().as_bytes().to_vec(),
-                time_range_end.to_string().as_bytes().to_vec(),
-            ];
-            
-            // Generate the zk proof
-            let (proof, journal) = prover.prove_inputs(&inputs).await.unwrap();
-            println!("✅ ZK Proof generated successfully");
-            println!("Journal: {}", String::from_utf8_lossy(&journal));
-            
-            // Construct the action to submit a review with the generated proof
-            let action = RestaurantReviewAction::SubmitReview {
+            let signed_message: SignedMessage =
+                serde_json::from_str(&signed_message).context("Invalid signed message")?;
+            let request = ReviewRequest {
                 restaurant_id,
-                client: client_name,
                 review_text,
-                zk_proof: proof.to_vec(),
-                timestamp,
-            };
-
-            // Build the blob transaction
-            let blobs = vec![action.as_blob(contract_name)];
-            let blob_tx = BlobTransaction::new(identity.clone(), blobs.clone());
-
-            // Send the blob transaction
-            let blob_tx_hash = client.send_tx_blob(&blob_tx).await.unwrap();
-            println!("✅ Blob transaction sent. Hash: {}", blob_tx_hash);
-
-            // Generate the zk proof for the contract execution
-            let inputs = ContractInput {
-                state: Vec::new(),
-                identity: identity.clone().into(),
-                tx_hash: blob_tx_hash,
-                private_input: vec![],
-                tx_ctx: None,
-                blobs: blobs.clone(),
-                index: sdk::BlobIndex(0),
-            };
-
-            let proof = prover.prove(inputs).await.unwrap();
-
-            // Build the proof transaction
-            let proof_tx = ProofTransaction {
-                proof,
-                contract_name: contract_name.clone().into(),
+                rating,
+                signed_message,
             };
-
-            // Send the proof transaction
-            let proof_tx_hash = client.send_tx_proof(&proof_tx).await.unwrap();
-            println!("✅ Proof transaction sent. Hash: {}", proof_tx_hash);
+            let reviewer_secret = reviewer.load_or_create()?;
+            let context = review_context(client, indexer_url, contract_name);
+            context
+                .submit_review(request, reviewer_secret, |progress| match progress {
+                    Progress::BlobSent { blob_tx_hash } => {
+                        println!("✅ Blob transaction sent. Hash: {}", blob_tx_hash)
+                    }
+                    Progress::Proving { .. } => println!("✅ Visit verified, proving the review"),
+                    Progress::ProofSent { proof_tx_hash, .. } => {
+                        println!("✅ Proof transaction sent. Hash: {}", proof_tx_hash)
+                    }
+                })
+                .await?;
+        }
+        Commands::Serve { port } => {
+            serve::serve(review_context(client, indexer_url, contract_name), port).await?;
         }
     }
     Ok(())
 }
-
-
-
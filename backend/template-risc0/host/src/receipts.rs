@@ -0,0 +1,175 @@
+use anyhow::{anyhow, bail, Context, Result};
+use clap::ValueEnum;
+use contract::visit::{SignatureScheme, SignedMessage};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::write_private_file;
+
+/// Clé de signature des reçus d'un restaurant, gardée dans un fichier local
+#[derive(Serialize, Deserialize)]
+pub struct RestaurantKey {
+    pub restaurant_id: String,
+    pub scheme: SignatureScheme,
+    #[serde(with = "hex::serde")]
+    secret_key: [u8; 32],
+}
+
+/// Format de l'image du QR code d'un reçu
+#[derive(Clone, Copy, ValueEnum)]
+pub enum QrFormat {
+    Png,
+    Svg,
+}
+
+impl RestaurantKey {
+    pub fn generate(restaurant_id: String, scheme: SignatureScheme) -> Self {
+        let secret_key = loop {
+            let secret_key: [u8; 32] = rand::random();
+            // Not every 32-byte string is a valid secp256k1 scalar
+            if scheme == SignatureScheme::Ed25519
+                || k256::ecdsa::SigningKey::from_slice(&secret_key).is_ok()
+            {
+                break secret_key;
+            }
+        };
+        RestaurantKey {
+            restaurant_id,
+            scheme,
+            secret_key,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let key =
+            std::fs::read_to_string(path).context(format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&key).context(format!("{} is not a restaurant key", path.display()))
+    }
+
+    /// Enregistre la clé, sans jamais écraser une clé existante
+    pub fn save(&self, path: &Path) -> Result<()> {
+        write_private_file(path, serde_json::to_string_pretty(self)?.as_bytes())
+    }
+
+    /// Clé publique à enregistrer dans le contrat
+    pub fn public_key(&self) -> Result<Vec<u8>> {
+        Ok(match self.scheme {
+            SignatureScheme::Ed25519 => ed25519_dalek::SigningKey::from_bytes(&self.secret_key)
+                .verifying_key()
+                .to_bytes()
+                .to_vec(),
+            SignatureScheme::Secp256k1 => {
+                let signing_key = k256::ecdsa::SigningKey::from_slice(&self.secret_key)?;
+                k256::ecdsa::VerifyingKey::from(&signing_key)
+                    .to_encoded_point(true)
+                    .as_bytes()
+                    .to_vec()
+            }
+        })
+    }
+
+    /// Signe le reçu d'une visite
+    pub fn issue_receipt(&self, visit_id: String, timestamp: u64) -> Result<SignedMessage> {
+        let payload = SignedMessage::signing_payload(&self.restaurant_id, &visit_id, timestamp);
+        let signature = match self.scheme {
+            SignatureScheme::Ed25519 => {
+                use ed25519_dalek::Signer;
+                ed25519_dalek::SigningKey::from_bytes(&self.secret_key)
+                    .sign(&payload)
+                    .to_bytes()
+                    .to_vec()
+            }
+            SignatureScheme::Secp256k1 => {
+                use k256::ecdsa::signature::Signer;
+                // k256 signatures are already low-s normalized, as the contract requires
+                let signing_key = k256::ecdsa::SigningKey::from_slice(&self.secret_key)?;
+                let signature: k256::ecdsa::Signature = signing_key.sign(&payload);
+                signature.to_bytes().to_vec()
+            }
+        };
+        Ok(SignedMessage {
+            restaurant_id: self.restaurant_id.clone(),
+            visit_id,
+            timestamp,
+            signature: hex::encode(signature),
+        })
+    }
+}
+
+/// Écrit le reçu en JSON dans `out_dir`, et son QR code si demandé.
+/// Renvoie les fichiers écrits.
+pub fn write_receipt(
+    receipt: &SignedMessage,
+    out_dir: &Path,
+    qr: Option<QrFormat>,
+) -> Result<Vec<PathBuf>> {
+    // Both ids end up in the file names
+    for id in [&receipt.restaurant_id, &receipt.visit_id] {
+        if id.is_empty()
+            || !id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            bail!("'{id}' must only contain letters, digits, '-' and '_' to name a receipt file");
+        }
+    }
+    std::fs::create_dir_all(out_dir).context(format!("Failed to create {}", out_dir.display()))?;
+
+    // The QR code holds the same JSON, which is what the customer uploads
+    let json = serde_json::to_string(receipt)?;
+    let name = format!("{}-{}", receipt.restaurant_id, receipt.visit_id);
+    let json_path = out_dir.join(format!("{name}.json"));
+    std::fs::write(&json_path, &json)
+        .context(format!("Failed to write {}", json_path.display()))?;
+    let mut written = vec![json_path];
+
+    if let Some(format) = qr {
+        let code = qrcode::QrCode::new(json.as_bytes())
+            .map_err(|e| anyhow!("Failed to encode the receipt as a QR code: {e}"))?;
+        let qr_path = match format {
+            QrFormat::Png => {
+                let path = out_dir.join(format!("{name}.png"));
+                code.render::<image::Luma<u8>>()
+                    .min_dimensions(256, 256)
+                    .build()
+                    .save(&path)
+                    .context(format!("Failed to write {}", path.display()))?;
+                path
+            }
+            QrFormat::Svg => {
+                let path = out_dir.join(format!("{name}.svg"));
+                let svg = code
+                    .render::<qrcode::render::svg::Color>()
+                    .min_dimensions(256, 256)
+                    .build();
+                std::fs::write(&path, svg)
+                    .context(format!("Failed to write {}", path.display()))?;
+                path
+            }
+        };
+        written.push(qr_path);
+    }
+    Ok(written)
+}
+
+/// Lit la liste des visites d'une journée : une visite par ligne, `visit_id` ou `visit_id,timestamp`.
+/// Les visites sans timestamp prennent `default_timestamp`.
+pub fn read_visits(path: &Path, default_timestamp: u64) -> Result<Vec<(String, u64)>> {
+    let visits =
+        std::fs::read_to_string(path).context(format!("Failed to read {}", path.display()))?;
+    visits
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.split_once(',') {
+            Some((visit_id, timestamp)) => {
+                let timestamp = timestamp
+                    .trim()
+                    .parse()
+                    .context(format!("Invalid timestamp in '{line}'"))?;
+                Ok((visit_id.trim().to_string(), timestamp))
+            }
+            None => Ok((line.to_string(), default_timestamp)),
+        })
+        .collect()
+}
@@ -0,0 +1,161 @@
+use anyhow::{bail, Result};
+use client_sdk::helpers::risc0::Risc0Prover;
+use client_sdk::rest_client::NodeApiHttpClient;
+use contract::pseudonym::{derive_pseudonym, ReviewerSecret};
+use contract::store::ReviewWitness;
+use contract::visit::SignedMessage;
+use contract::{RestaurantReview, RestaurantReviewAction};
+use sdk::{BlobTransaction, ContractInput, ProofTransaction, TxHash};
+use serde::Deserialize;
+
+use crate::{fetch_from_indexer, fetch_state, wait_for_tx_context};
+use crate::{NullifierResponse, ReviewResponse};
+
+/// Review à publier, avec le reçu de visite qui la justifie
+#[derive(Deserialize, Debug, Clone)]
+pub struct ReviewRequest {
+    pub restaurant_id: String,
+    pub review_text: String,
+    pub rating: u8,
+    pub signed_message: SignedMessage,
+}
+
+/// Étapes de la publication d'une review
+#[derive(Debug, Clone)]
+pub enum Progress {
+    BlobSent {
+        blob_tx_hash: TxHash,
+    },
+    Proving {
+        blob_tx_hash: TxHash,
+    },
+    ProofSent {
+        blob_tx_hash: TxHash,
+        proof_tx_hash: TxHash,
+    },
+}
+
+/// Ce qu'il faut pour publier des reviews : le nœud, l'indexeur et le prouveur du contrat
+pub struct ReviewContext {
+    pub client: NodeApiHttpClient,
+    pub indexer_url: String,
+    pub contract_name: String,
+    pub prover: Risc0Prover<'static>,
+}
+
+impl ReviewContext {
+    /// Publie une review sous le pseudonyme que `reviewer_secret` donne dans ce restaurant.
+    /// Renvoie le hash de la transaction blob une fois la preuve envoyée.
+    pub async fn submit_review(
+        &self,
+        request: ReviewRequest,
+        reviewer_secret: ReviewerSecret,
+        mut on_progress: impl FnMut(Progress),
+    ) -> Result<TxHash> {
+        let ReviewRequest {
+            restaurant_id,
+            review_text,
+            rating,
+            signed_message,
+        } = request;
+        let contract_name = &self.contract_name;
+
+        if signed_message.restaurant_id != restaurant_id {
+            bail!(
+                "Signed message was issued by restaurant {}, not {}",
+                signed_message.restaurant_id,
+                restaurant_id
+            );
+        }
+        let nullifier = signed_message.nullifier(&reviewer_secret);
+
+        // The secret stays in the private input, only its pseudonym for this restaurant
+        // is published, and serves as the transaction identity
+        let pseudonym = derive_pseudonym(&reviewer_secret, &restaurant_id);
+        let identity = format!("{}.{}", hex::encode(pseudonym), contract_name);
+
+        // The contract state only keeps Merkle roots: the indexer provides the proofs
+        // of the empty review slot and of the unspent nullifier
+        let state = fetch_state(&self.client, contract_name).await?;
+        let slot: ReviewResponse = fetch_from_indexer(
+            &self.indexer_url,
+            contract_name,
+            &format!("review/{}", state.review_count()),
+        )
+        .await?;
+        let spent: NullifierResponse = fetch_from_indexer(
+            &self.indexer_url,
+            contract_name,
+            &format!("nullifier/{}", hex::encode(nullifier)),
+        )
+        .await?;
+        if spent.spent {
+            bail!("A review was already submitted for this visit");
+        }
+        let witness = ReviewWitness {
+            receipt: Some(signed_message),
+            reviewer_secret: Some(reviewer_secret),
+            review: None,
+            review_proof: slot.proof,
+            nullifier_proof: spent.proof,
+        };
+
+        // Only the nullifier is published, the receipt stays in the private input
+        let action = RestaurantReviewAction::SubmitReview {
+            restaurant_id,
+            pseudonym,
+            review_text,
+            rating,
+            nullifier,
+        };
+        let blobs = vec![action.as_blob(contract_name)];
+        let blob_tx = BlobTransaction::new(identity.clone(), blobs.clone());
+
+        let blob_tx_hash = self.client.send_tx_blob(&blob_tx).await?;
+        on_progress(Progress::BlobSent {
+            blob_tx_hash: blob_tx_hash.clone(),
+        });
+
+        // The visit time range is checked against the block that sequenced the blob,
+        // so the proof needs its context. The node checks it matches on settlement.
+        let tx_ctx = wait_for_tx_context(&self.client, &blob_tx_hash).await?;
+
+        // The guest checks the receipt against the restaurant key stored in the contract state
+        let inputs = ContractInput {
+            state: state.as_bytes()?,
+            identity: identity.into(),
+            tx_hash: blob_tx_hash.clone(),
+            private_input: witness.as_private_input(),
+            tx_ctx: Some(tx_ctx),
+            blobs,
+            index: sdk::BlobIndex(0),
+        };
+
+        // Run the contract locally first to get a readable error instead of a failed proof
+        let (_, output) = sdk::guest::execute::<RestaurantReview>(&inputs);
+        if !output.success {
+            bail!(
+                "Review rejected by the contract: {}",
+                String::from_utf8_lossy(&output.program_outputs)
+            );
+        }
+
+        on_progress(Progress::Proving {
+            blob_tx_hash: blob_tx_hash.clone(),
+        });
+        let proof = self.prover.prove(inputs).await?;
+        let proof_tx_hash = self
+            .client
+            .send_tx_proof(&ProofTransaction {
+                proof,
+                contract_name: contract_name.clone().into(),
+            })
+            .await?;
+        on_progress(Progress::ProofSent {
+            blob_tx_hash: blob_tx_hash.clone(),
+            proof_tx_hash,
+        });
+
+        Ok(blob_tx_hash)
+    }
+}
@@ -0,0 +1,210 @@
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use contract::pseudonym::ReviewerSecret;
+use sdk::TxHash;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::fetch_from_indexer;
+use crate::review::{Progress, ReviewContext, ReviewRequest};
+
+/// Nombre maximal de reviews en attente de preuve
+const QUEUE_SIZE: usize = 64;
+
+/// Demande de review reçue par le service
+#[derive(Deserialize)]
+struct SubmitReviewRequest {
+    #[serde(flatten)]
+    review: ReviewRequest,
+    /// Secret du reviewer, gardé par le client qui appelle le service
+    #[serde(with = "hex::serde")]
+    reviewer_secret: ReviewerSecret,
+}
+
+/// État d'une publication de review
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum JobStatus {
+    Queued,
+    BlobSent {
+        blob_tx_hash: TxHash,
+    },
+    Proving {
+        blob_tx_hash: TxHash,
+    },
+    ProofSent {
+        blob_tx_hash: TxHash,
+        proof_tx_hash: TxHash,
+    },
+    Settled {
+        blob_tx_hash: TxHash,
+        review_id: u32,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+impl From<Progress> for JobStatus {
+    fn from(progress: Progress) -> Self {
+        match progress {
+            Progress::BlobSent { blob_tx_hash } => JobStatus::BlobSent { blob_tx_hash },
+            Progress::Proving { blob_tx_hash } => JobStatus::Proving { blob_tx_hash },
+            Progress::ProofSent {
+                blob_tx_hash,
+                proof_tx_hash,
+            } => JobStatus::ProofSent {
+                blob_tx_hash,
+                proof_tx_hash,
+            },
+        }
+    }
+}
+
+/// Review publiée, d'après l'indexeur du contrat
+#[derive(Deserialize)]
+struct ReviewEntry {
+    review_id: u32,
+}
+
+type Jobs = Arc<Mutex<HashMap<u64, JobStatus>>>;
+
+struct Job {
+    id: u64,
+    request: ReviewRequest,
+    reviewer_secret: ReviewerSecret,
+}
+
+#[derive(Clone)]
+struct ServiceState {
+    jobs: Jobs,
+    next_id: Arc<AtomicU64>,
+    queue: mpsc::Sender<Job>,
+}
+
+/// Lance le service de publication de reviews.
+/// Les reviews sont prouvées une par une : la preuve d'une review part de l'état du contrat
+/// après la précédente, on attend donc qu'elle soit réglée avant de passer à la suivante.
+pub async fn serve(context: ReviewContext, port: u16) -> Result<()> {
+    let jobs = Jobs::default();
+    let (queue, receiver) = mpsc::channel(QUEUE_SIZE);
+    tokio::spawn(run_jobs(context, jobs.clone(), receiver));
+
+    let app = Router::new()
+        .route("/reviews", post(submit_review))
+        .route("/reviews/{job_id}", get(get_job))
+        .with_state(ServiceState {
+            jobs,
+            next_id: Arc::new(AtomicU64::new(0)),
+            queue,
+        });
+
+    let listener = tokio::net::TcpListener::bind(("0.0.0.0", port))
+        .await
+        .context(format!("Failed to listen on port {port}"))?;
+    println!("✅ Review service listening on port {port}");
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+async fn submit_review(
+    State(state): State<ServiceState>,
+    Json(request): Json<SubmitReviewRequest>,
+) -> impl IntoResponse {
+    let id = state.next_id.fetch_add(1, Ordering::Relaxed);
+    state.jobs.lock().unwrap().insert(id, JobStatus::Queued);
+
+    let job = Job {
+        id,
+        request: request.review,
+        reviewer_secret: request.reviewer_secret,
+    };
+    if state.queue.try_send(job).is_err() {
+        state.jobs.lock().unwrap().remove(&id);
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({ "error": "Too many reviews waiting to be proven" })),
+        );
+    }
+    (
+        StatusCode::ACCEPTED,
+        Json(serde_json::json!({ "job_id": id })),
+    )
+}
+
+async fn get_job(
+    Path(job_id): Path<u64>,
+    State(state): State<ServiceState>,
+) -> Result<Json<JobStatus>, StatusCode> {
+    state
+        .jobs
+        .lock()
+        .unwrap()
+        .get(&job_id)
+        .cloned()
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn run_jobs(context: ReviewContext, jobs: Jobs, mut receiver: mpsc::Receiver<Job>) {
+    let set_status = |id: u64, status: JobStatus| {
+        jobs.lock().unwrap().insert(id, status);
+    };
+
+    while let Some(job) = receiver.recv().await {
+        let id = job.id;
+        let submitted = context
+            .submit_review(job.request, job.reviewer_secret, |progress| {
+                set_status(id, progress.into())
+            })
+            .await;
+        let status = match submitted {
+            Ok(blob_tx_hash) => wait_for_settlement(&context, blob_tx_hash).await,
+            Err(error) => Err(error),
+        };
+        set_status(
+            id,
+            status.unwrap_or_else(|error| JobStatus::Failed {
+                error: format!("{error:#}"),
+            }),
+        );
+    }
+}
+
+/// Attend que la transaction blob ne soit plus en attente sur le nœud,
+/// puis vérifie auprès de l'indexeur qu'elle a bien publié une review
+async fn wait_for_settlement(context: &ReviewContext, blob_tx_hash: TxHash) -> Result<JobStatus> {
+    while context.client.get_unsettled_tx(&blob_tx_hash).await.is_ok() {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+
+    // The indexer may lag a block behind the node
+    for _ in 0..10 {
+        let review: Result<ReviewEntry> = fetch_from_indexer(
+            &context.indexer_url,
+            &context.contract_name,
+            &format!("review/tx/{blob_tx_hash}"),
+        )
+        .await;
+        if let Ok(review) = review {
+            return Ok(JobStatus::Settled {
+                blob_tx_hash,
+                review_id: review.review_id,
+            });
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+    Ok(JobStatus::Failed {
+        error: format!("Transaction {blob_tx_hash} settled without publishing the review"),
+    })
+}
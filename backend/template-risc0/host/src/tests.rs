@@ -1,9 +1,10 @@
 use super::*;
 use anyhow::Result;
 use client_sdk::rest_client::NodeApiHttpClient;
+use contract::visit::SignatureScheme;
 use contract::{RestaurantReview, RestaurantReviewAction};
+use methods::GUEST_ELF;
 use sdk::{BlobTransaction, ContractInput, ProofTransaction};
-use methods::{GUEST_ELF};
 
 #[tokio::test]
 async fn test_register_restaurant() -> Result<()> {
@@ -14,7 +15,8 @@ async fn test_register_restaurant() -> Result<()> {
     // Construire l'action pour enregistrer un restaurant
     let action = RestaurantReviewAction::RegisterRestaurant {
         id: "restaurant_1".to_string(),
-        public_key: "public_key_123".to_string().into_bytes(),
+        public_key: vec![0; 32],
+        scheme: SignatureScheme::Ed25519,
     };
 
     // Construire la transaction blob
@@ -61,10 +63,10 @@ async fn test_submit_review() -> Result<()> {
     // Construire l'action pour soumettre une review
     let action = RestaurantReviewAction::SubmitReview {
         restaurant_id: "restaurant_1".to_string(),
-        client: "client_1".to_string(),
+        pseudonym: [0; 32],
         review_text: "Great service!".to_string(),
-        zk_proof: "zk_proof_123".to_string().into_bytes(),
-        timestamp: 1730984240,
+        rating: 5,
+        nullifier: [0; 32],
     };
 
     // Construire la transaction blob
@@ -100,4 +102,4 @@ async fn test_submit_review() -> Result<()> {
     println!("✅ Transaction de preuve envoyée. Hash : {}", proof_tx_hash);
 
     Ok(())
-}
\ No newline at end of file
+}
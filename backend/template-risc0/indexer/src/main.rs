@@ -0,0 +1,11 @@
+use anyhow::Result;
+use clap::Parser;
+use hyle::indexer::standalone::{run, Args};
+
+// Lets `contract_indexers` index review contracts with the "restaurant_review" handler
+hyle::register_contract_handler!("restaurant_review", contract::indexer::ReviewIndex);
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    run(Args::parse()).await
+}